@@ -0,0 +1,161 @@
+//! Subcommands of the migration binary.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use axiston_db_client::{
+    apply_fixture, Database, DatabaseExt, DatabaseResult, Fixture, MigrationState, SeedError,
+    SeedResult,
+};
+
+/// Subcommands of the migration binary.
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum Command {
+    /// Executes all pending migrations.
+    Migrate,
+    /// Reverts the last applied migration.
+    Revert {
+        /// Reverts all applied migrations instead.
+        #[arg(long)]
+        all: bool,
+    },
+    /// Lists all migrations and their state.
+    Status,
+    /// Fails if there are pending or unknown migrations.
+    Verify,
+    /// Lists pending migrations without executing them.
+    Plan,
+    /// Reverts and re-applies the last applied migration.
+    Redo,
+    /// Fails if the database differs from the compiled schema.
+    Drift,
+    /// Fails if active accounts have conflicting email addresses.
+    Conflicts,
+    /// Populates the database with the TOML (or JSON) fixture.
+    Seed {
+        /// Path to the fixture file.
+        #[arg(default_value = "./configs/seed.toml")]
+        path: PathBuf,
+    },
+}
+
+impl Command {
+    /// Executes the subcommand against the database.
+    pub async fn run(self, database: &Database) -> DatabaseResult<ExitCode> {
+        match self {
+            Self::Migrate => {
+                let applied = database.apply_migrations().await?;
+                println!("Applied {applied} migration(s).");
+            }
+            Self::Revert { all: true } => {
+                let reverted = database.rollback_migrations().await?;
+                println!("Reverted {reverted} migration(s).");
+            }
+            Self::Revert { all: false } => match database.revert_last_migration().await? {
+                Some(version) => println!("Reverted migration {version}."),
+                None => println!("No migrations to revert."),
+            },
+            Self::Redo => match database.redo_last_migration().await? {
+                Some(version) => println!("Redone migration {version}."),
+                None => println!("No migrations to redo."),
+            },
+            Self::Status => {
+                for migration in database.migration_status().await? {
+                    let state = match migration.state {
+                        MigrationState::Applied => "[x]",
+                        MigrationState::Pending => "[ ]",
+                        MigrationState::Unknown => "[?]",
+                    };
+
+                    let name = migration.name.as_deref().unwrap_or("unknown");
+                    println!("{state} {} {name}", migration.version);
+                }
+            }
+            Self::Plan => {
+                let status = database.migration_status().await?;
+                let pending: Vec<_> = status
+                    .iter()
+                    .filter(|x| x.state == MigrationState::Pending)
+                    .collect();
+
+                if pending.is_empty() {
+                    println!("No pending migrations.");
+                }
+
+                for migration in pending {
+                    let name = migration.name.as_deref().unwrap_or_default();
+                    println!("Would apply {} {name}", migration.version);
+                }
+            }
+            Self::Verify => {
+                let status = database.migration_status().await?;
+                let mut exit_code = ExitCode::SUCCESS;
+
+                let pending = status.iter().filter(|x| x.state == MigrationState::Pending);
+                for migration in pending {
+                    println!("Pending migration {}.", migration.version);
+                    exit_code = ExitCode::FAILURE;
+                }
+
+                for migration in status.iter().filter(|x| x.is_unknown()) {
+                    println!("Unknown migration {}.", migration.version);
+                    exit_code = ExitCode::FAILURE;
+                }
+
+                if exit_code == ExitCode::SUCCESS {
+                    println!("All migrations are applied.");
+                }
+
+                return Ok(exit_code);
+            }
+            Self::Drift => {
+                let drift = database.detect_drift().await?;
+                if drift.is_empty() {
+                    println!("No schema drift detected.");
+                    return Ok(ExitCode::SUCCESS);
+                }
+
+                drift.tables.iter().for_each(|x| println!("table: {x}"));
+                drift.columns.iter().for_each(|x| println!("column: {x}"));
+                drift
+                    .constraints
+                    .iter()
+                    .for_each(|x| println!("constraint: {x}"));
+                drift.indexes.iter().for_each(|x| println!("index: {x}"));
+                return Ok(ExitCode::FAILURE);
+            }
+            Self::Conflicts => {
+                let conflicts = database.detect_email_conflicts().await?;
+                if conflicts.is_empty() {
+                    println!("No conflicting email addresses.");
+                    return Ok(ExitCode::SUCCESS);
+                }
+
+                for conflict in conflicts {
+                    let ids: Vec<_> = conflict.account_ids.iter().map(|x| x.to_string()).collect();
+                    println!("{}: {}", conflict.email_address, ids.join(", "));
+                }
+
+                return Ok(ExitCode::FAILURE);
+            }
+            Self::Seed { path } => match seed(database, &path).await {
+                Ok(created) => println!("Created {created} entity(ies)."),
+                Err(SeedError::Database(error)) => return Err(error),
+                Err(error) => {
+                    eprintln!("{error}");
+                    return Ok(ExitCode::FAILURE);
+                }
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Parses and applies the fixture file, returning the number of created entities.
+async fn seed(database: &Database, path: &Path) -> SeedResult<u64> {
+    let fixture = Fixture::from_path(path)?;
+    let mut conn = database.get_connection().await?;
+    let output = apply_fixture(&mut conn, &fixture).await?;
+    Ok(output.created)
+}
@@ -0,0 +1,49 @@
+#![forbid(unsafe_code)]
+#![doc = include_str!("./README.md")]
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use axiston_db_client::Database;
+use clap::Parser;
+
+use crate::command::Command;
+use crate::config::{ConnectionOverrides, MigrateConfig};
+
+mod command;
+mod config;
+
+/// Standalone migration tool for the Axiston database.
+#[derive(Debug, Parser)]
+#[command(name = "axiston-db", version, about)]
+struct Args {
+    /// Path to the configuration file.
+    #[arg(short, long, default_value = "./configs/migrate.toml")]
+    config: PathBuf,
+    #[command(flatten)]
+    overrides: ConnectionOverrides,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let config = match MigrateConfig::load(&args.config, args.overrides) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let database = Database::new_single_gateway(config.database.addr());
+    match args.command.run(&database).await {
+        Ok(exit_code) => exit_code,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -13,7 +13,7 @@ use futures::FutureExt;
 ///
 /// [`ManagerConfig`]: diesel_async::pooled_connection::ManagerConfig
 /// [`SetupCallback`]: diesel_async::pooled_connection::SetupCallback
-pub fn setup_callback<C>(addr: &str) -> BoxFuture<ConnectionResult<C>>
+pub fn setup_callback<C>(addr: &str) -> BoxFuture<'_, ConnectionResult<C>>
 where
     C: AsyncConnection + 'static,
 {
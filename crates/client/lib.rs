@@ -22,13 +22,28 @@ use diesel_async::pooled_connection::deadpool::PoolError;
 use diesel_async::pooled_connection::PoolError as PoolError2;
 
 pub use crate::config::{Database, DatabaseConfig};
-pub use crate::migrate::DatabaseExt;
+pub use crate::maintenance::{purge_expired, PurgeOutput, RetentionPolicy};
+pub use crate::migrate::{
+    ColumnDrift, DatabaseExt, EmailConflict, MigrationHooks, MigrationState, MigrationStatus,
+    ObjectDrift, SchemaDrift,
+};
 pub use crate::query::*;
+pub use crate::repository::{AccountRepository, WorkflowRepository, WorkspaceRepository};
+#[cfg(feature = "serde")]
+pub use crate::seed::{apply_fixture, Fixture, SeedError, SeedOutput, SeedResult};
 pub(crate) use crate::utils::*;
 
 mod config;
+pub mod maintenance;
 mod migrate;
 mod query;
+mod repository;
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod seed;
+#[cfg(any(test, feature = "testing"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
 mod utils;
 
 /// Type-erased [`Error`] type.
@@ -0,0 +1,236 @@
+//! Hard-deletes sessions and action tokens past their retention.
+//!
+//! Rows are deleted in bounded batches by the `purge_account_sessions` and
+//! `purge_account_tokens` SQL functions, one statement per batch, so locks
+//! are never held for long. See [`schedule_purge`] to run the `purge_expired`
+//! procedure periodically with `pg_cron` instead.
+
+mod pg_cron;
+
+use std::num::NonZeroU32;
+
+use diesel::define_sql_function;
+use diesel::dsl::*;
+use diesel::pg::data_types::PgInterval;
+use diesel::sql_types::{Integer, Interval};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use time::Duration;
+
+use crate::dsl::interval;
+pub use crate::maintenance::pg_cron::{
+    schedule_purge, schedule_purge_query, unschedule_purge, unschedule_purge_query, PURGE_JOB_NAME,
+};
+use crate::DatabaseResult;
+
+define_sql_function!(
+    /// Represents the `purge_account_sessions` SQL function.
+    fn purge_account_sessions(retention: Interval, batch_size: Integer) -> Integer
+);
+
+define_sql_function!(
+    /// Represents the `purge_account_tokens` SQL function.
+    fn purge_account_tokens(retention: Interval, batch_size: Integer) -> Integer
+);
+
+/// Retention policy of [`purge_expired`] and [`schedule_purge`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RetentionPolicy {
+    /// Keeps sessions for this long after they expire or are deleted.
+    pub session_retention: Duration,
+    /// Keeps action tokens for this long after they expire or are used.
+    pub token_retention: Duration,
+    /// Maximum number of rows deleted by a single statement.
+    ///
+    /// Values above [`i32::MAX`] are clamped, as the SQL functions take `INTEGER`.
+    pub batch_size: NonZeroU32,
+}
+
+impl RetentionPolicy {
+    /// Returns the batch size as the SQL `INTEGER`.
+    fn batch_limit(self) -> i32 {
+        i32::try_from(self.batch_size.get()).unwrap_or(i32::MAX)
+    }
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            session_retention: Duration::days(30),
+            token_retention: Duration::days(7),
+            batch_size: NonZeroU32::new(1000).unwrap(),
+        }
+    }
+}
+
+/// Number of rows deleted by [`purge_expired`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "forms do nothing unless you use them"]
+pub struct PurgeOutput {
+    pub sessions: u64,
+    pub tokens: u64,
+}
+
+/// Returns the query used by [`purge_expired`] to delete a batch of sessions.
+#[auto_type(no_type_alias)]
+pub fn purge_sessions_query(policy: RetentionPolicy) -> _ {
+    let retention: PgInterval = interval(policy.session_retention);
+    let batch_size: i32 = policy.batch_limit();
+    select(purge_account_sessions(retention, batch_size))
+}
+
+/// Returns the query used by [`purge_expired`] to delete a batch of action tokens.
+#[auto_type(no_type_alias)]
+pub fn purge_tokens_query(policy: RetentionPolicy) -> _ {
+    let retention: PgInterval = interval(policy.token_retention);
+    let batch_size: i32 = policy.batch_limit();
+    select(purge_account_tokens(retention, batch_size))
+}
+
+/// Deletes all sessions and action tokens past their retention.
+///
+/// Every batch is a separate statement, so the connection should not be
+/// inside of a transaction, otherwise locks are held until it ends.
+///
+/// # Tables
+///
+/// - account_sessions
+/// - account_tokens
+pub async fn purge_expired(
+    conn: &mut AsyncPgConnection,
+    policy: RetentionPolicy,
+) -> DatabaseResult<PurgeOutput> {
+    let mut output = PurgeOutput::default();
+    let batch_size = policy.batch_limit();
+
+    loop {
+        let deleted: i32 = purge_sessions_query(policy).get_result(conn).await?;
+        output.sessions += deleted as u64;
+        if deleted == 0 || deleted < batch_size {
+            break;
+        }
+    }
+
+    loop {
+        let deleted: i32 = purge_tokens_query(policy).get_result(conn).await?;
+        output.tokens += deleted as u64;
+        if deleted == 0 || deleted < batch_size {
+            break;
+        }
+    }
+
+    tracing::debug!(target: "database", ?output, "expired sessions and tokens are purged");
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroU32;
+
+    use axiston_db_schema::enumerations::{AccountStatus, TokenAction};
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use time::Duration;
+
+    use crate::account_sessions::{
+        create_session, delete_session, view_active_sessions, AccountSession,
+    };
+    use crate::account_tokens::{
+        consume_action_token, create_action_token, AccountTokenCreateInput, AccountTokenViewInput,
+        TokenPayload,
+    };
+    use crate::maintenance::*;
+    use crate::testing::{AccountFactory, TestDatabase};
+
+    #[test]
+    fn maintenance_queries() {
+        let policy = RetentionPolicy::default();
+
+        let query = purge_sessions_query(policy);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @"SELECT purge_account_sessions($1, $2) -- binds: [PgInterval { microseconds: 2592000000000, days: 0, months: 0 }, 1000]");
+
+        let query = purge_tokens_query(policy);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @"SELECT purge_account_tokens($1, $2) -- binds: [PgInterval { microseconds: 604800000000, days: 0, months: 0 }, 1000]");
+    }
+
+    #[test]
+    fn retention_batch_limit() {
+        let mut policy = RetentionPolicy::default();
+        assert_eq!(policy.batch_limit(), 1000);
+
+        policy.batch_size = NonZeroU32::new(i32::MAX as u32).unwrap();
+        assert_eq!(policy.batch_limit(), i32::MAX);
+
+        policy.batch_size = NonZeroU32::MAX;
+        assert_eq!(policy.batch_limit(), i32::MAX);
+    }
+
+    #[tokio::test]
+    async fn purge_expired_rows() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new()
+            .status(AccountStatus::Active)
+            .create(&mut conn)
+            .await?;
+
+        let form = AccountSession {
+            region_id: "EU".to_owned(),
+            ip_address: "127.0.0.1/32".parse().unwrap(),
+            user_agent: "Mozilla/5.0".to_owned(),
+        };
+
+        for _ in 0..3 {
+            let token = create_session(&mut conn, account.id, form.clone()).await?;
+            delete_session(&mut conn, token).await?;
+        }
+
+        let _ = create_session(&mut conn, account.id, form).await?;
+
+        let ip_address = "127.0.0.1/32".parse().unwrap();
+        let payload = TokenPayload::ResetPassword;
+        let form = AccountTokenCreateInput::new(account.id, &payload, ip_address, "Mozilla/5.0");
+        let _ = create_action_token(&mut conn, form.clone()).await?;
+        let consumed = create_action_token(&mut conn, form).await?;
+
+        let form = AccountTokenViewInput {
+            account_id: account.id,
+            action_token: consumed.action_token,
+        };
+
+        let result = consume_action_token(&mut conn, form, TokenAction::ResetPassword).await?;
+        assert!(result.is_ok());
+
+        let payload = TokenPayload::ActivateAccount;
+        let form = AccountTokenCreateInput::new(account.id, &payload, ip_address, "Mozilla/5.0");
+        let _ = create_action_token(&mut conn, form).await?;
+
+        // Keeps rows within the retention.
+        let output = purge_expired(&mut conn, RetentionPolicy::default()).await?;
+        assert_eq!(output, PurgeOutput::default());
+
+        let policy = RetentionPolicy {
+            session_retention: Duration::ZERO,
+            token_retention: Duration::ZERO,
+            batch_size: NonZeroU32::new(2).unwrap(),
+        };
+
+        let output = purge_expired(&mut conn, policy).await?;
+        assert_eq!(
+            output,
+            PurgeOutput {
+                sessions: 3,
+                tokens: 2
+            }
+        );
+        assert_eq!(view_active_sessions(&mut conn, account.id).await?.len(), 1);
+
+        let output = purge_expired(&mut conn, policy).await?;
+        assert_eq!(output, PurgeOutput::default());
+
+        Ok(())
+    }
+}
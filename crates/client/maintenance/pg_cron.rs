@@ -0,0 +1,97 @@
+//! Registers the `purge_expired` procedure as the `pg_cron` job.
+//!
+//! Requires the `pg_cron` extension, preloaded by the Docker image.
+
+use diesel::define_sql_function;
+use diesel::dsl::*;
+use diesel::sql_types::Text;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::maintenance::RetentionPolicy;
+use crate::DatabaseResult;
+
+/// Name of the `pg_cron` job registered by [`schedule_purge`].
+pub const PURGE_JOB_NAME: &str = "axiston_purge_expired";
+
+define_sql_function!(
+    /// Represents the `cron.schedule` SQL function.
+    #[sql_name = "cron.schedule"]
+    fn cron_schedule(job_name: Text, schedule: Text, command: Text) -> BigInt
+);
+
+define_sql_function!(
+    /// Represents the `cron.unschedule` SQL function.
+    #[sql_name = "cron.unschedule"]
+    fn cron_unschedule(job_name: Text) -> Bool
+);
+
+/// Returns the `CALL purge_expired(...)` command of the policy.
+fn purge_command(policy: RetentionPolicy) -> String {
+    format!(
+        "CALL purge_expired(make_interval(secs => {}), make_interval(secs => {}), {})",
+        policy.session_retention.whole_seconds(),
+        policy.token_retention.whole_seconds(),
+        policy.batch_limit(),
+    )
+}
+
+/// Returns the query used by [`schedule_purge`].
+#[auto_type(no_type_alias)]
+pub fn schedule_purge_query<'a>(schedule: &'a str, policy: RetentionPolicy) -> _ {
+    let job_name: &'static str = PURGE_JOB_NAME;
+    let command: String = purge_command(policy);
+    select(cron_schedule(job_name, schedule, command))
+}
+
+/// Creates or updates the [`PURGE_JOB_NAME`] job and returns its ID.
+///
+/// The schedule uses the `cron` syntax, e.g. `0 3 * * *` for every night.
+///
+/// # Tables
+///
+/// - cron.job
+pub async fn schedule_purge(
+    conn: &mut AsyncPgConnection,
+    schedule: &str,
+    policy: RetentionPolicy,
+) -> DatabaseResult<i64> {
+    let query = schedule_purge_query(schedule, policy)
+        .get_result(conn)
+        .await?;
+
+    Ok(query)
+}
+
+/// Returns the query used by [`unschedule_purge`].
+#[auto_type(no_type_alias)]
+pub fn unschedule_purge_query() -> _ {
+    let job_name: &'static str = PURGE_JOB_NAME;
+    select(cron_unschedule(job_name))
+}
+
+/// Deletes the [`PURGE_JOB_NAME`] job.
+///
+/// # Tables
+///
+/// - cron.job
+pub async fn unschedule_purge(conn: &mut AsyncPgConnection) -> DatabaseResult<()> {
+    let _query: bool = unschedule_purge_query().get_result(conn).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+
+    use crate::maintenance::pg_cron::*;
+
+    #[test]
+    fn pg_cron_queries() {
+        let query = schedule_purge_query("0 3 * * *", RetentionPolicy::default());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT cron.schedule($1, $2, $3) -- binds: ["axiston_purge_expired", "0 3 * * *", "CALL purge_expired(make_interval(secs => 2592000), make_interval(secs => 604800), 1000)"]"#);
+
+        let query = unschedule_purge_query();
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT cron.unschedule($1) -- binds: ["axiston_purge_expired"]"#);
+    }
+}
@@ -6,14 +6,41 @@ use diesel_async::AsyncPgConnection;
 
 use crate::DatabaseResult;
 
-pub async fn pre_migrate(_conn: &mut AsyncPgConnection) -> DatabaseResult<()> {
-    tracing::trace!(target: "database", "pre_migrate hook is running");
+/// User-supplied callbacks executed around migrations.
+///
+/// Used to refresh materialized views, re-grant privileges or register
+/// `pg_cron` jobs after migrations without forking the crate.
+///
+/// See [`DatabaseExt::apply_migrations_with_hooks`] for more details.
+///
+/// [`DatabaseExt::apply_migrations_with_hooks`]: crate::DatabaseExt::apply_migrations_with_hooks
+pub trait MigrationHooks: Sync {
+    /// Custom hook called before migrations are applied or reverted.
+    ///
+    /// Receives versions of the migrations that are about to be executed.
+    /// Not called if there is nothing to execute or if planning fails.
+    async fn before(
+        &self,
+        conn: &mut AsyncPgConnection,
+        versions: &[String],
+    ) -> DatabaseResult<()> {
+        let _ = conn;
+        tracing::trace!(target: "database", ?versions, "pre_migrate hook is running");
+        Ok(())
+    }
 
-    Ok(())
+    /// Custom hook called after migrations were applied or reverted.
+    ///
+    /// Receives versions of the migrations that were executed.
+    /// Not called if executing migrations fails, even if [`before`] was called.
+    ///
+    /// [`before`]: MigrationHooks::before
+    async fn after(&self, conn: &mut AsyncPgConnection, versions: &[String]) -> DatabaseResult<()> {
+        let _ = conn;
+        tracing::trace!(target: "database", ?versions, "post_migrate hook is running");
+        Ok(())
+    }
 }
 
-pub async fn post_migrate(_conn: &mut AsyncPgConnection) -> DatabaseResult<()> {
-    tracing::trace!(target: "database", "post_migrate hook is running");
-
-    Ok(())
-}
+/// Default no-op hooks.
+impl MigrationHooks for () {}
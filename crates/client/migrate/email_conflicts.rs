@@ -0,0 +1,84 @@
+//! Detection of accounts with conflicting email addresses.
+
+use axiston_db_schema::identifiers::AccountId;
+use diesel::sql_types::{Array, Text, Uuid};
+use diesel::QueryableByName;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::DatabaseResult;
+
+/// Active accounts sharing the email address once it is normalized.
+///
+/// Normalization matches [`normalize_email`], i.e. only ASCII is trimmed and lower-cased.
+///
+/// See [`DatabaseExt::detect_email_conflicts`] for more details.
+///
+/// [`DatabaseExt::detect_email_conflicts`]: crate::DatabaseExt::detect_email_conflicts
+/// [`normalize_email`]: crate::accounts::normalize_email
+#[derive(Debug, Clone, PartialEq, Eq, QueryableByName)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "conflict reports do nothing unless you use them"]
+pub struct EmailConflict {
+    /// Normalized (trimmed and lower-cased) email address.
+    #[diesel(sql_type = Text)]
+    pub email_address: String,
+    /// Conflicting accounts, oldest first.
+    #[diesel(sql_type = Array<Uuid>)]
+    pub account_ids: Vec<AccountId>,
+}
+
+const CONFLICTS_QUERY: &str = r#"
+SELECT lower(btrim(email_address, E' \t\n\f\r') COLLATE "C") AS email_address,
+       array_agg(id ORDER BY created_at) AS account_ids
+FROM accounts WHERE deleted_at IS NULL
+GROUP BY 1 HAVING count(*) > 1
+ORDER BY 1"#;
+
+/// Returns all groups of active accounts with conflicting email addresses.
+pub(crate) async fn detect_email_conflicts(
+    conn: &mut AsyncPgConnection,
+) -> DatabaseResult<Vec<EmailConflict>> {
+    let conflicts = diesel::sql_query(CONFLICTS_QUERY).load(conn).await?;
+    Ok(conflicts)
+}
+
+#[cfg(test)]
+mod test {
+    use diesel_async::RunQueryDsl;
+
+    use crate::testing::{AccountFactory, TestDatabase};
+    use crate::{DatabaseExt, DatabaseResult};
+
+    #[tokio::test]
+    async fn detect_email_conflicts() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        assert!(database.detect_email_conflicts().await?.is_empty());
+
+        // Simulates the database before email addresses were normalized.
+        let index = "DROP INDEX accounts_email_address_idx";
+        let _ = diesel::sql_query(index).execute(&mut conn).await?;
+
+        let account = AccountFactory::new()
+            .email_address("account@example.com")
+            .create(&mut conn)
+            .await?;
+        let duplicate = AccountFactory::new()
+            .email_address("account@example.com")
+            .create(&mut conn)
+            .await?;
+        let update = "UPDATE accounts SET email_address = 'Account@Example.com ' WHERE id = $1";
+        let _ = diesel::sql_query(update)
+            .bind::<diesel::sql_types::Uuid, _>(duplicate.id)
+            .execute(&mut conn)
+            .await?;
+
+        let conflicts = database.detect_email_conflicts().await?;
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].email_address, "account@example.com");
+        assert_eq!(conflicts[0].account_ids, vec![account.id, duplicate.id]);
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! Status of embedded and applied migrations.
+
+use diesel::migration::{Migration, MigrationVersion};
+use diesel::pg::Pg;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// State of the single migration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MigrationState {
+    /// Migration is embedded and was applied to the database.
+    #[cfg_attr(feature = "serde", serde(rename = "applied"))]
+    Applied,
+    /// Migration is embedded, but was not yet applied to the database.
+    #[cfg_attr(feature = "serde", serde(rename = "pending"))]
+    Pending,
+    /// Migration was applied to the database, but is not embedded.
+    #[cfg_attr(feature = "serde", serde(rename = "unknown"))]
+    Unknown,
+}
+
+/// Status of the single migration.
+///
+/// See [`DatabaseExt::migration_status`] for more details.
+///
+/// [`DatabaseExt::migration_status`]: crate::DatabaseExt::migration_status
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MigrationStatus {
+    /// Version of the migration (e.g. `20241206010930`).
+    pub version: String,
+    /// Full name of the migration, unless the migration is unknown.
+    pub name: Option<String>,
+    /// Current state of the migration.
+    pub state: MigrationState,
+}
+
+impl MigrationStatus {
+    /// Merges embedded and applied migrations into a sorted list.
+    pub(crate) fn collect(
+        migrations: &[Box<dyn Migration<Pg>>],
+        applied: &[MigrationVersion<'_>],
+    ) -> Vec<Self> {
+        let mut status: Vec<_> = migrations
+            .iter()
+            .map(|migration| {
+                let version = migration.name().version();
+                let state = if applied.contains(&version) {
+                    MigrationState::Applied
+                } else {
+                    MigrationState::Pending
+                };
+
+                Self {
+                    version: version.to_string(),
+                    name: Some(migration.name().to_string()),
+                    state,
+                }
+            })
+            .collect();
+
+        let unknown = applied.iter().filter(|version| {
+            let version = version.to_string();
+            !status.iter().any(|x| x.version == version)
+        });
+
+        let unknown: Vec<_> = unknown
+            .map(|version| Self {
+                version: version.to_string(),
+                name: None,
+                state: MigrationState::Unknown,
+            })
+            .collect();
+
+        status.extend(unknown);
+        status.sort_by(|a, b| a.version.cmp(&b.version));
+        status
+    }
+
+    /// Returns `true` if the migration is embedded and was applied to the database.
+    #[inline]
+    pub fn is_applied(&self) -> bool {
+        matches!(self.state, MigrationState::Applied)
+    }
+
+    /// Returns `true` if the migration was applied to the database, but is not embedded.
+    #[inline]
+    pub fn is_unknown(&self) -> bool {
+        matches!(self.state, MigrationState::Unknown)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use diesel::migration::MigrationVersion;
+
+    use crate::migrate::migration_status::*;
+
+    #[test]
+    fn migration_states() {
+        let applied = [
+            MigrationVersion::from("20241206010918"),
+            MigrationVersion::from("20991231000000"),
+        ];
+
+        let migrations = [];
+        let status = MigrationStatus::collect(&migrations, &applied);
+        assert_eq!(status.len(), 2);
+        assert!(status.iter().all(|x| x.is_unknown()));
+        assert!(status.iter().all(|x| !x.is_applied()));
+    }
+}
@@ -0,0 +1,156 @@
+//! Verifies that each `down.sql` reverts its `up.sql`.
+//!
+//! For every migration from [`MIGRATIONS`], applies it, dumps the catalog,
+//! reverts and re-applies it, and compares catalog snapshots. Runs against
+//! the empty [`TestDatabase`].
+//!
+//! [`TestDatabase`]: crate::testing::TestDatabase
+
+use axiston_db_schema::MIGRATIONS;
+use diesel::migration::MigrationSource;
+use diesel::pg::Pg;
+use diesel::sql_types::Text;
+use diesel::QueryableByName;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use diesel_migrations::MigrationHarness;
+
+use crate::{Database, DatabaseError, DatabaseExt, DatabaseResult};
+
+/// Dumps tables, columns, constraints, indexes, types, functions and
+/// triggers of the `public` schema as a sorted list of definitions.
+const CATALOG_QUERY: &str = r#"
+SELECT entry FROM (
+    SELECT format('table %s', c.relname) AS entry
+    FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+    WHERE n.nspname = 'public' AND c.relkind = 'r'
+    UNION ALL
+    SELECT format('column %s.%s %s not_null=%s default=%s',
+        c.relname, a.attname, format_type(a.atttypid, a.atttypmod),
+        a.attnotnull, pg_get_expr(d.adbin, d.adrelid))
+    FROM pg_attribute a
+    JOIN pg_class c ON c.oid = a.attrelid
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+    WHERE n.nspname = 'public' AND c.relkind = 'r' AND a.attnum > 0 AND NOT a.attisdropped
+    UNION ALL
+    SELECT format('constraint %s.%s %s', c.relname, k.conname, pg_get_constraintdef(k.oid))
+    FROM pg_constraint k
+    JOIN pg_class c ON c.oid = k.conrelid
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    WHERE n.nspname = 'public'
+    UNION ALL
+    SELECT format('index %s', indexdef)
+    FROM pg_indexes
+    WHERE schemaname = 'public'
+    UNION ALL
+    SELECT format('type %s enum (%s)', t.typname,
+        string_agg(e.enumlabel, ', ' ORDER BY e.enumsortorder))
+    FROM pg_type t
+    JOIN pg_enum e ON e.enumtypid = t.oid
+    JOIN pg_namespace n ON n.oid = t.typnamespace
+    WHERE n.nspname = 'public'
+    GROUP BY t.typname
+    UNION ALL
+    SELECT format('function %s', pg_get_functiondef(p.oid))
+    FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace
+    WHERE n.nspname = 'public' AND p.prokind = 'f'
+    UNION ALL
+    SELECT format('trigger %s', pg_get_triggerdef(t.oid))
+    FROM pg_trigger t
+    JOIN pg_class c ON c.oid = t.tgrelid
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    WHERE n.nspname = 'public' AND NOT t.tgisinternal
+) AS catalog
+WHERE entry NOT LIKE '%__diesel_schema_migrations%'
+ORDER BY entry
+"#;
+
+#[derive(Debug, QueryableByName)]
+struct CatalogEntry {
+    #[diesel(sql_type = Text)]
+    entry: String,
+}
+
+/// Returns the sorted snapshot of the database catalog.
+async fn dump_catalog(conn: &mut AsyncPgConnection) -> DatabaseResult<Vec<String>> {
+    let entries: Vec<CatalogEntry> = diesel::sql_query(CATALOG_QUERY).load(conn).await?;
+    Ok(entries.into_iter().map(|x| x.entry).collect())
+}
+
+/// Applies (or reverts) the migration with the specified index.
+async fn run_migration(database: &Database, index: usize, revert: bool) -> DatabaseResult<()> {
+    let conn = database.get_connection().await?;
+    let _ = database
+        .with_harness(conn.into(), move |wrapper| {
+            let migrations =
+                MigrationSource::<Pg>::migrations(&MIGRATIONS).map_err(DatabaseError::Migration)?;
+            let migration = migrations[index].as_ref();
+            let _ = match revert {
+                true => wrapper.revert_migration(migration),
+                false => wrapper.run_migration(migration),
+            }
+            .map_err(DatabaseError::Migration)?;
+
+            Ok(())
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Fails with the list of mismatched entries unless both snapshots are equal.
+fn compare_catalogs(expected: &[String], actual: &[String], message: &str) -> DatabaseResult<()> {
+    let missing = expected.iter().filter(|x| !actual.contains(x));
+    let missing = missing.map(|x| format!("- {x}"));
+    let unexpected = actual.iter().filter(|x| !expected.contains(x));
+    let unexpected = unexpected.map(|x| format!("+ {x}"));
+
+    let diff: Vec<_> = missing.chain(unexpected).collect();
+    if diff.is_empty() {
+        return Ok(());
+    }
+
+    let error = format!("{message}:\n{}", diff.join("\n"));
+    Err(DatabaseError::Migration(error.into()))
+}
+
+/// Applies all migrations one by one and checks each is reversible.
+async fn check_reversibility(database: &Database) -> DatabaseResult<()> {
+    let migrations =
+        MigrationSource::<Pg>::migrations(&MIGRATIONS).map_err(DatabaseError::Migration)?;
+    let mut conn = database.get_connection().await?;
+
+    // Creates the migrations table, so it's not part of the first snapshot.
+    let _ = database.migration_status().await?;
+
+    for (index, migration) in migrations.iter().enumerate() {
+        let name = migration.name().to_string();
+        let before = dump_catalog(&mut conn).await?;
+
+        run_migration(database, index, false).await?;
+        let applied = dump_catalog(&mut conn).await?;
+
+        run_migration(database, index, true).await?;
+        let reverted = dump_catalog(&mut conn).await?;
+        compare_catalogs(&before, &reverted, &format!("{name} is not fully reverted"))?;
+
+        run_migration(database, index, false).await?;
+        let reapplied = dump_catalog(&mut conn).await?;
+        compare_catalogs(&applied, &reapplied, &format!("{name} is not reversible"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::migrate::reversibility::check_reversibility;
+    use crate::testing::TestDatabase;
+    use crate::DatabaseResult;
+
+    #[tokio::test]
+    async fn migrations_are_reversible() -> DatabaseResult<()> {
+        let database = TestDatabase::new_empty().await?;
+        check_reversibility(&database).await
+    }
+}
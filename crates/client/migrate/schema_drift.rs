@@ -0,0 +1,328 @@
+//! Detection of drift between the live database and the compiled crate.
+//!
+//! Only tables, columns, check, unique and primary key constraints, and indexes
+//! are compared. Foreign keys and enum labels are not tracked by the generated
+//! catalog, so their drift is not detected.
+
+use std::fmt;
+
+use axiston_db_schema::catalog::{INDEXES, TABLES};
+use axiston_db_schema::constraints::ConstraintViolation;
+use diesel::sql_types::{Bool, Text};
+use diesel::QueryableByName;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::DatabaseResult;
+
+/// Drift of the table, constraint or index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ObjectDrift {
+    /// Object is expected by the crate, but is missing from the database.
+    #[cfg_attr(feature = "serde", serde(rename = "missing"))]
+    Missing(String),
+    /// Object exists in the database, but is not expected by the crate.
+    #[cfg_attr(feature = "serde", serde(rename = "unexpected"))]
+    Unexpected(String),
+}
+
+impl fmt::Display for ObjectDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(name) => write!(f, "{name} is missing"),
+            Self::Unexpected(name) => write!(f, "{name} is unexpected"),
+        }
+    }
+}
+
+/// Drift of the single column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ColumnDrift {
+    /// Column is expected by the crate, but is missing from the database.
+    #[cfg_attr(feature = "serde", serde(rename = "missing"))]
+    Missing { table: String, column: String },
+    /// Column exists in the database, but is not expected by the crate.
+    #[cfg_attr(feature = "serde", serde(rename = "unexpected"))]
+    Unexpected { table: String, column: String },
+    /// Column type differs from the expected one.
+    #[cfg_attr(feature = "serde", serde(rename = "type_mismatch"))]
+    TypeMismatch {
+        table: String,
+        column: String,
+        expected: String,
+        actual: String,
+    },
+    /// Column nullability differs from the expected one.
+    #[cfg_attr(feature = "serde", serde(rename = "nullability_mismatch"))]
+    NullabilityMismatch {
+        table: String,
+        column: String,
+        expected: bool,
+    },
+}
+
+impl fmt::Display for ColumnDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { table, column } => write!(f, "{table}.{column} is missing"),
+            Self::Unexpected { table, column } => write!(f, "{table}.{column} is unexpected"),
+            Self::TypeMismatch {
+                table,
+                column,
+                expected,
+                actual,
+            } => write!(f, "{table}.{column} has type {actual}, expected {expected}"),
+            Self::NullabilityMismatch {
+                table,
+                column,
+                expected: true,
+            } => write!(f, "{table}.{column} is not nullable, expected nullable"),
+            Self::NullabilityMismatch {
+                table,
+                column,
+                expected: false,
+            } => write!(f, "{table}.{column} is nullable, expected not nullable"),
+        }
+    }
+}
+
+/// Structured difference between the live database and the compiled crate.
+///
+/// See [`DatabaseExt::detect_drift`] for more details.
+///
+/// [`DatabaseExt::detect_drift`]: crate::DatabaseExt::detect_drift
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "drift reports do nothing unless you use them"]
+pub struct SchemaDrift {
+    pub tables: Vec<ObjectDrift>,
+    pub columns: Vec<ColumnDrift>,
+    pub constraints: Vec<ObjectDrift>,
+    pub indexes: Vec<ObjectDrift>,
+}
+
+impl SchemaDrift {
+    /// Returns `true` if the database matches the compiled crate.
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+            && self.columns.is_empty()
+            && self.constraints.is_empty()
+            && self.indexes.is_empty()
+    }
+}
+
+#[derive(Debug, QueryableByName)]
+struct TableRow {
+    #[diesel(sql_type = Text)]
+    table_name: String,
+}
+
+#[derive(Debug, QueryableByName)]
+struct ColumnRow {
+    #[diesel(sql_type = Text)]
+    table_name: String,
+    #[diesel(sql_type = Text)]
+    column_name: String,
+    #[diesel(sql_type = Text)]
+    udt_name: String,
+    #[diesel(sql_type = Bool)]
+    is_nullable: bool,
+}
+
+#[derive(Debug, QueryableByName)]
+struct ConstraintRow {
+    #[diesel(sql_type = Text)]
+    constraint_name: String,
+    #[diesel(sql_type = Bool)]
+    is_known_kind: bool,
+}
+
+#[derive(Debug, QueryableByName)]
+struct IndexRow {
+    #[diesel(sql_type = Text)]
+    index_name: String,
+}
+
+const TABLES_QUERY: &str = "
+SELECT table_name::TEXT FROM information_schema.tables
+WHERE table_schema = 'public' AND table_type = 'BASE TABLE'
+  AND table_name <> '__diesel_schema_migrations'";
+
+const COLUMNS_QUERY: &str = "
+SELECT table_name::TEXT, column_name::TEXT, udt_name::TEXT, is_nullable = 'YES' AS is_nullable
+FROM information_schema.columns
+WHERE table_schema = 'public' AND table_name <> '__diesel_schema_migrations'";
+
+const CONSTRAINTS_QUERY: &str = "
+SELECT k.conname::TEXT AS constraint_name, k.contype IN ('c', 'u', 'p') AS is_known_kind
+FROM pg_constraint k JOIN pg_namespace n ON n.oid = k.connamespace
+WHERE n.nspname = 'public' AND k.contype IN ('c', 'u', 'p', 'f')
+  AND k.conrelid::regclass::TEXT <> '__diesel_schema_migrations'";
+
+const INDEXES_QUERY: &str = "
+SELECT i.indexname::TEXT AS index_name FROM pg_indexes i
+WHERE i.schemaname = 'public' AND i.tablename <> '__diesel_schema_migrations'
+  AND NOT EXISTS (SELECT 1 FROM pg_constraint k WHERE k.conname = i.indexname)";
+
+/// Introspects the database and compares it against the compiled crate.
+pub(crate) async fn detect_drift(conn: &mut AsyncPgConnection) -> DatabaseResult<SchemaDrift> {
+    let tables: Vec<TableRow> = diesel::sql_query(TABLES_QUERY).load(conn).await?;
+    let columns: Vec<ColumnRow> = diesel::sql_query(COLUMNS_QUERY).load(conn).await?;
+    let constraints: Vec<ConstraintRow> = diesel::sql_query(CONSTRAINTS_QUERY).load(conn).await?;
+    let indexes: Vec<IndexRow> = diesel::sql_query(INDEXES_QUERY).load(conn).await?;
+
+    let mut drift = SchemaDrift::default();
+    compare_tables(&mut drift, &tables, &columns);
+    compare_constraints(&mut drift, &constraints);
+    compare_indexes(&mut drift, &indexes);
+    Ok(drift)
+}
+
+fn compare_tables(drift: &mut SchemaDrift, tables: &[TableRow], columns: &[ColumnRow]) {
+    for table in TABLES {
+        if !tables.iter().any(|x| x.table_name == table.name) {
+            drift
+                .tables
+                .push(ObjectDrift::Missing(table.name.to_owned()));
+            continue;
+        }
+
+        for column in table.columns {
+            let actual = columns
+                .iter()
+                .find(|x| x.table_name == table.name && x.column_name == column.name);
+
+            let Some(actual) = actual else {
+                drift.columns.push(ColumnDrift::Missing {
+                    table: table.name.to_owned(),
+                    column: column.name.to_owned(),
+                });
+
+                continue;
+            };
+
+            if actual.udt_name != column.sql_type {
+                drift.columns.push(ColumnDrift::TypeMismatch {
+                    table: table.name.to_owned(),
+                    column: column.name.to_owned(),
+                    expected: column.sql_type.to_owned(),
+                    actual: actual.udt_name.clone(),
+                });
+            }
+
+            if actual.is_nullable != column.nullable {
+                drift.columns.push(ColumnDrift::NullabilityMismatch {
+                    table: table.name.to_owned(),
+                    column: column.name.to_owned(),
+                    expected: column.nullable,
+                });
+            }
+        }
+    }
+
+    for table in tables {
+        let Some(expected) = TABLES.iter().find(|x| x.name == table.table_name) else {
+            drift
+                .tables
+                .push(ObjectDrift::Unexpected(table.table_name.clone()));
+            continue;
+        };
+
+        let unexpected = columns.iter().filter(|x| {
+            x.table_name == table.table_name
+                && !expected.columns.iter().any(|c| c.name == x.column_name)
+        });
+
+        for column in unexpected {
+            drift.columns.push(ColumnDrift::Unexpected {
+                table: column.table_name.clone(),
+                column: column.column_name.clone(),
+            });
+        }
+    }
+}
+
+fn compare_constraints(drift: &mut SchemaDrift, constraints: &[ConstraintRow]) {
+    for expected in ConstraintViolation::all().map(|x| x.to_string()) {
+        if !constraints.iter().any(|x| x.constraint_name == expected) {
+            drift.constraints.push(ObjectDrift::Missing(expected));
+        }
+    }
+
+    // Only check, unique and primary key constraints are tracked by the crate,
+    // so unexpected foreign keys are not reported.
+    for constraint in constraints.iter().filter(|x| x.is_known_kind) {
+        if ConstraintViolation::new(&constraint.constraint_name).is_none() {
+            let name = constraint.constraint_name.clone();
+            drift.constraints.push(ObjectDrift::Unexpected(name));
+        }
+    }
+}
+
+fn compare_indexes(drift: &mut SchemaDrift, indexes: &[IndexRow]) {
+    for expected in INDEXES {
+        if !indexes.iter().any(|x| x.index_name == *expected) {
+            let name = (*expected).to_owned();
+            drift.indexes.push(ObjectDrift::Missing(name));
+        }
+    }
+
+    for index in indexes {
+        if !INDEXES.contains(&index.index_name.as_str()) {
+            let name = index.index_name.clone();
+            drift.indexes.push(ObjectDrift::Unexpected(name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use diesel_async::RunQueryDsl;
+
+    use crate::testing::TestDatabase;
+    use crate::{ColumnDrift, DatabaseExt, DatabaseResult, ObjectDrift};
+
+    #[test]
+    fn drift_display() {
+        let index = ObjectDrift::Missing("accounts_email_address_idx".to_owned());
+        assert_eq!(index.to_string(), "accounts_email_address_idx is missing");
+
+        let column = ColumnDrift::TypeMismatch {
+            table: "accounts".to_owned(),
+            column: "email_address".to_owned(),
+            expected: "text".to_owned(),
+            actual: "varchar".to_owned(),
+        };
+
+        let expected = "accounts.email_address has type varchar, expected text";
+        assert_eq!(column.to_string(), expected);
+    }
+
+    /// Checks tables, constraints and indexes parsed from every migration.
+    #[tokio::test]
+    async fn no_drift_after_migrations() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let drift = database.detect_drift().await?;
+        assert!(drift.is_empty(), "{drift:?}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn detect_drift() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let hotfix = "CREATE INDEX accounts_hotfix_idx ON accounts (display_name)";
+        let _ = diesel::sql_query(hotfix).execute(&mut conn).await?;
+
+        let drift = database.detect_drift().await?;
+        assert!(drift.tables.is_empty());
+        assert!(drift.columns.is_empty());
+        assert!(drift.constraints.is_empty(), "{:?}", drift.constraints);
+        let hotfix = ObjectDrift::Unexpected("accounts_hotfix_idx".to_owned());
+        assert_eq!(drift.indexes, vec![hotfix]);
+        Ok(())
+    }
+}
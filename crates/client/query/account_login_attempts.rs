@@ -0,0 +1,280 @@
+//! Data layer for sign-in attempts and account lockouts.
+
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use axiston_db_schema::constraints::ConstraintViolation;
+use axiston_db_schema::identifiers::AccountId;
+use axiston_db_schema::schema;
+use axiston_db_schema::schema::account_login_attempts;
+use diesel::dsl::*;
+use diesel::expression::{SqlLiteral, UncheckedBind};
+use diesel::pg::data_types::PgInterval;
+use diesel::prelude::*;
+use diesel::sql_types::{Interval, Timestamptz};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use ipnet::IpNet;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{DatabaseResult, Validate};
+
+#[derive(Debug, Clone, Insertable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[diesel(table_name = schema::account_login_attempts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[must_use = "forms do nothing unless you use them"]
+pub struct AccountLoginAttempt {
+    /// Account matching the email address, if any.
+    pub account_id: Option<AccountId>,
+    pub region_id: String,
+    pub ip_address: IpNet,
+    pub user_agent: String,
+    pub is_successful: bool,
+}
+
+impl Validate for AccountLoginAttempt {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        ConstraintViolation::AccountLoginAttemptsRegionAlphanumeric.check_text(&self.region_id)?;
+
+        Ok(())
+    }
+}
+
+/// Lockout policy, used by [`is_account_locked_out`] and [`is_ip_locked_out`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LockoutPolicy {
+    /// Counts failed attempts for the account within the window.
+    pub account_window: Duration,
+    /// Locks the account out after this many failed attempts.
+    pub account_threshold: NonZeroU32,
+    /// Counts failed attempts from the IP address within the window.
+    pub ip_window: Duration,
+    /// Locks the IP address out after this many failed attempts.
+    pub ip_threshold: NonZeroU32,
+}
+
+impl LockoutPolicy {
+    /// Returns the window as the SQL `INTERVAL`, saturating on overflow.
+    fn interval(window: Duration) -> PgInterval {
+        let microseconds = i64::try_from(window.as_micros()).unwrap_or(i64::MAX);
+        PgInterval::from_microseconds(microseconds)
+    }
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self {
+            account_window: Duration::from_secs(15 * 60),
+            account_threshold: NonZeroU32::new(5).unwrap(),
+            ip_window: Duration::from_secs(15 * 60),
+            ip_threshold: NonZeroU32::new(20).unwrap(),
+        }
+    }
+}
+
+/// Returns the query used by [`record_login_attempt`].
+#[auto_type(no_type_alias)]
+pub fn record_login_attempt_query(form: AccountLoginAttempt) -> _ {
+    insert_into(account_login_attempts::table).values(form)
+}
+
+/// Records the successful or failed sign-in attempt.
+///
+/// # Tables
+///
+/// - account_login_attempts
+pub async fn record_login_attempt(
+    conn: &mut AsyncPgConnection,
+    form: AccountLoginAttempt,
+) -> DatabaseResult<()> {
+    let _query = record_login_attempt_query(form).execute(conn).await?;
+    Ok(())
+}
+
+/// Returns the query used by [`is_account_locked_out`].
+#[auto_type(no_type_alias)]
+pub fn view_recent_account_attempts_query(form_account_id: AccountId, policy: LockoutPolicy) -> _ {
+    let window: PgInterval = LockoutPolicy::interval(policy.account_window);
+    let window_start: UncheckedBind<SqlLiteral<Timestamptz>, AsExprOf<PgInterval, Interval>> =
+        sql::<Timestamptz>("CURRENT_TIMESTAMP - ").bind::<Interval, _>(window);
+
+    let filter_cond = account_login_attempts::account_id
+        .eq(form_account_id)
+        .and(account_login_attempts::attempted_at.gt(window_start));
+
+    let limit: i64 = policy.account_threshold.get().into();
+    account_login_attempts::table
+        .filter(filter_cond)
+        .order(account_login_attempts::attempted_at.desc())
+        .select(account_login_attempts::is_successful)
+        .limit(limit)
+}
+
+/// Returns `true` if the account is locked out.
+///
+/// Failed attempts are counted within the window of the policy,
+/// but only since the last successful attempt for the account.
+///
+/// # Tables
+///
+/// - account_login_attempts
+pub async fn is_account_locked_out(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+    policy: LockoutPolicy,
+) -> DatabaseResult<bool> {
+    let query: Vec<bool> = view_recent_account_attempts_query(form_account_id, policy)
+        .get_results(conn)
+        .await?;
+
+    let failures = query.iter().take_while(|x| !**x).count();
+    Ok(failures as u64 >= u64::from(policy.account_threshold.get()))
+}
+
+/// Returns the query used by [`is_ip_locked_out`].
+#[auto_type(no_type_alias)]
+pub fn count_ip_failures_query(form_ip_address: IpNet, policy: LockoutPolicy) -> _ {
+    let window: PgInterval = LockoutPolicy::interval(policy.ip_window);
+    let window_start: UncheckedBind<SqlLiteral<Timestamptz>, AsExprOf<PgInterval, Interval>> =
+        sql::<Timestamptz>("CURRENT_TIMESTAMP - ").bind::<Interval, _>(window);
+
+    let filter_cond = account_login_attempts::ip_address
+        .eq(form_ip_address)
+        .and(not(account_login_attempts::is_successful))
+        .and(account_login_attempts::attempted_at.gt(window_start));
+
+    account_login_attempts::table
+        .filter(filter_cond)
+        .select(count_star())
+}
+
+/// Returns `true` if the IP address is locked out.
+///
+/// Failed attempts are counted within the window of the policy, including
+/// those for unknown accounts. Successful attempts do not reset the count.
+///
+/// # Tables
+///
+/// - account_login_attempts
+pub async fn is_ip_locked_out(
+    conn: &mut AsyncPgConnection,
+    form_ip_address: IpNet,
+    policy: LockoutPolicy,
+) -> DatabaseResult<bool> {
+    let query: i64 = count_ip_failures_query(form_ip_address, policy)
+        .get_result(conn)
+        .await?;
+
+    Ok(query >= i64::from(policy.ip_threshold.get()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroU32;
+    use std::time::Duration;
+
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use uuid::Uuid;
+
+    use crate::account_login_attempts::*;
+    use crate::testing::{AccountFactory, TestDatabase};
+
+    #[test]
+    fn account_login_attempt_queries() {
+        let account_id = AccountId::new(Uuid::nil());
+        let ip_address: IpNet = "127.0.0.1/32".parse().unwrap();
+        let policy = LockoutPolicy::default();
+
+        let form = AccountLoginAttempt {
+            account_id: Some(account_id),
+            region_id: "EU".to_owned(),
+            ip_address,
+            user_agent: "Mozilla/5.0".to_owned(),
+            is_successful: false,
+        };
+
+        let query = record_login_attempt_query(form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "account_login_attempts" ("account_id", "region_id", "ip_address", "user_agent", "is_successful") VALUES ($1, $2, $3, $4, $5) -- binds: [AccountId(00000000-0000-0000-0000-000000000000), "EU", 127.0.0.1/32, "Mozilla/5.0", false]"#);
+
+        let query = view_recent_account_attempts_query(account_id, policy);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "account_login_attempts"."is_successful" FROM "account_login_attempts" WHERE (("account_login_attempts"."account_id" = $1) AND ("account_login_attempts"."attempted_at" > CURRENT_TIMESTAMP - $2)) ORDER BY "account_login_attempts"."attempted_at" DESC LIMIT $3 -- binds: [AccountId(00000000-0000-0000-0000-000000000000), PgInterval { microseconds: 900000000, days: 0, months: 0 }, 5]"#);
+
+        let query = count_ip_failures_query(ip_address, policy);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT COUNT(*) FROM "account_login_attempts" WHERE ((("account_login_attempts"."ip_address" = $1) AND  NOT ("account_login_attempts"."is_successful")) AND ("account_login_attempts"."attempted_at" > CURRENT_TIMESTAMP - $2)) -- binds: [127.0.0.1/32, PgInterval { microseconds: 900000000, days: 0, months: 0 }]"#);
+    }
+
+    #[test]
+    fn lockout_policy_intervals() {
+        let interval = LockoutPolicy::interval(Duration::from_secs(60));
+        assert_eq!(interval, PgInterval::from_microseconds(60_000_000));
+
+        let interval = LockoutPolicy::interval(Duration::MAX);
+        assert_eq!(interval, PgInterval::from_microseconds(i64::MAX));
+    }
+
+    #[tokio::test]
+    async fn lockouts() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new().create(&mut conn).await?;
+
+        let ip_address: IpNet = "127.0.0.1/32".parse().unwrap();
+        let policy = LockoutPolicy {
+            account_threshold: NonZeroU32::new(3).unwrap(),
+            ip_threshold: NonZeroU32::new(5).unwrap(),
+            ..LockoutPolicy::default()
+        };
+
+        let form = AccountLoginAttempt {
+            account_id: Some(account.id),
+            region_id: "EU".to_owned(),
+            ip_address,
+            user_agent: "Mozilla/5.0".to_owned(),
+            is_successful: false,
+        };
+
+        for _ in 0..2 {
+            record_login_attempt(&mut conn, form.clone()).await?;
+        }
+
+        assert!(!is_account_locked_out(&mut conn, account.id, policy).await?);
+        record_login_attempt(&mut conn, form.clone()).await?;
+        assert!(is_account_locked_out(&mut conn, account.id, policy).await?);
+        assert!(!is_ip_locked_out(&mut conn, ip_address, policy).await?);
+
+        // Failures outside of the window are not counted.
+        let expired = LockoutPolicy {
+            account_window: Duration::ZERO,
+            ..policy
+        };
+
+        assert!(!is_account_locked_out(&mut conn, account.id, expired).await?);
+
+        // Successful attempts reset the count for the account, not the IP address.
+        let success = AccountLoginAttempt {
+            is_successful: true,
+            ..form.clone()
+        };
+
+        record_login_attempt(&mut conn, success).await?;
+        assert!(!is_account_locked_out(&mut conn, account.id, policy).await?);
+
+        let unknown = AccountLoginAttempt {
+            account_id: None,
+            ..form
+        };
+
+        for _ in 0..2 {
+            record_login_attempt(&mut conn, unknown.clone()).await?;
+        }
+
+        assert!(is_ip_locked_out(&mut conn, ip_address, policy).await?);
+        let other_ip: IpNet = "127.0.0.2/32".parse().unwrap();
+        assert!(!is_ip_locked_out(&mut conn, other_ip, policy).await?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,701 @@
+//! Data layer for TOTP multi-factor authentication.
+//!
+//! TOTP codes are verified by [`confirm_mfa`] and [`satisfy_mfa`] with the decrypted secret,
+//! the database only stores the [`EncryptedSecret`] and recovery code hashes.
+
+use axiston_db_schema::identifiers::AccountId;
+use axiston_db_schema::schema;
+use axiston_db_schema::schema::{account_mfa, account_sessions};
+use axiston_db_schema::secrets::{
+    EncryptedSecret, EncryptionKey, RecoveryCode, TokenHash, TotpSecret,
+};
+use diesel::dsl::*;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_builder::{QueryFragment, QueryId};
+use diesel::result::Error;
+use diesel::upsert::excluded;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::account_sessions::AccountSessionToken;
+use crate::dsl::{array_remove, cardinality};
+use crate::DatabaseResult;
+
+/// Number of recovery codes generated on enrollment and regeneration.
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Number of time steps before and after the current one accepted for clock drift.
+pub const TOTP_SKEW: i64 = 1;
+
+#[derive(Debug, Clone, Insertable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[diesel(table_name = schema::account_mfa)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[must_use = "forms do nothing unless you use them"]
+pub struct AccountMfaEnrollInput {
+    pub account_id: AccountId,
+    pub totp_secret: EncryptedSecret,
+}
+
+impl AccountMfaEnrollInput {
+    /// Returns a new [`AccountMfaEnrollInput`] with the encrypted secret.
+    pub fn new(key: &EncryptionKey, account_id: AccountId, totp_secret: &TotpSecret) -> Self {
+        Self {
+            account_id,
+            totp_secret: key.encrypt(account_id, totp_secret),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[diesel(table_name = schema::account_mfa)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[must_use = "forms do nothing unless you use them"]
+pub struct AccountMfaViewOutput {
+    pub totp_secret: EncryptedSecret,
+    #[diesel(select_expression = cardinality(account_mfa::recovery_code_hashes))]
+    #[diesel(select_expression_type = cardinality<account_mfa::recovery_code_hashes>)]
+    pub recovery_codes_left: i32,
+
+    pub created_at: OffsetDateTime,
+    pub confirmed_at: Option<OffsetDateTime>,
+}
+
+impl AccountMfaViewOutput {
+    /// Returns `true` if the enrollment is confirmed and MFA is required.
+    #[inline]
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+/// Plaintext recovery codes, only their hashes are stored.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "forms do nothing unless you use them"]
+pub struct AccountMfaRecoveryCodes {
+    pub recovery_codes: Vec<RecoveryCode>,
+}
+
+impl AccountMfaRecoveryCodes {
+    /// Returns [`RECOVERY_CODE_COUNT`] new random recovery codes.
+    fn generate() -> Self {
+        let recovery_codes = (0..RECOVERY_CODE_COUNT)
+            .map(|_| RecoveryCode::generate())
+            .collect();
+
+        Self { recovery_codes }
+    }
+
+    /// Returns hashes of the recovery codes.
+    fn hashes(&self) -> Vec<TokenHash> {
+        self.recovery_codes.iter().map(RecoveryCode::hash).collect()
+    }
+}
+
+/// Returns the query used by [`enroll_mfa`].
+///
+/// Returns the opaque type instead of using `#[auto_type]`, as `diesel`
+/// doesn't export the types of `ON CONFLICT` clauses.
+pub fn enroll_mfa_query(
+    form: AccountMfaEnrollInput,
+    form_recovery_code_hashes: Vec<TokenHash>,
+) -> impl QueryFragment<Pg> + QueryId + Send {
+    // Filters the upsert, as `QueryDsl` is not implemented for `INSERT` statements.
+    use diesel::query_dsl::methods::FilterDsl;
+
+    // Confirmed enrollments are never replaced.
+    insert_into(account_mfa::table)
+        .values((
+            account_mfa::recovery_code_hashes.eq(form_recovery_code_hashes),
+            form,
+        ))
+        .on_conflict(account_mfa::account_id)
+        .do_update()
+        .set((
+            account_mfa::totp_secret.eq(excluded(account_mfa::totp_secret)),
+            account_mfa::recovery_code_hashes.eq(excluded(account_mfa::recovery_code_hashes)),
+        ))
+        .filter(account_mfa::confirmed_at.is_null())
+}
+
+/// Starts the enrollment and returns new recovery codes.
+///
+/// Replaces the secret and recovery codes of the pending enrollment.
+/// Returns `None` if the enrollment is already confirmed, as MFA has to be
+/// disabled first. Recovery codes are returned only once, as only their
+/// hashes are stored.
+///
+/// # Tables
+///
+/// - account_mfa
+pub async fn enroll_mfa(
+    conn: &mut AsyncPgConnection,
+    form: AccountMfaEnrollInput,
+) -> DatabaseResult<Option<AccountMfaRecoveryCodes>> {
+    let codes = AccountMfaRecoveryCodes::generate();
+    let query = enroll_mfa_query(form, codes.hashes()).execute(conn).await?;
+
+    Ok((query > 0).then_some(codes))
+}
+
+/// Returns the query used by [`view_mfa`].
+#[auto_type(no_type_alias)]
+pub fn view_mfa_query(form_account_id: AccountId) -> _ {
+    let filter_cond = account_mfa::account_id.eq(form_account_id);
+
+    let selection: AsSelect<AccountMfaViewOutput, Pg> = AccountMfaViewOutput::as_select();
+    account_mfa::table.filter(filter_cond).select(selection)
+}
+
+/// Returns the pending or confirmed enrollment.
+///
+/// # Tables
+///
+/// - account_mfa
+pub async fn view_mfa(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+) -> DatabaseResult<Option<AccountMfaViewOutput>> {
+    let query = view_mfa_query(form_account_id)
+        .get_result(conn)
+        .await
+        .optional()?;
+
+    Ok(query)
+}
+
+/// Returns the query used by [`consume_recovery_code`].
+#[auto_type(no_type_alias)]
+pub fn consume_recovery_code_query(form_account_id: AccountId, form_code_hash: TokenHash) -> _ {
+    let form_code_hashes: Vec<TokenHash> = vec![form_code_hash];
+    let filter_cond = account_mfa::account_id
+        .eq(form_account_id)
+        .and(account_mfa::confirmed_at.is_not_null())
+        .and(account_mfa::recovery_code_hashes.contains(form_code_hashes));
+
+    let recovery_code_hashes = array_remove(account_mfa::recovery_code_hashes, form_code_hash);
+    update(account_mfa::table.filter(filter_cond))
+        .set(account_mfa::recovery_code_hashes.eq(recovery_code_hashes))
+}
+
+/// Verifies and consumes the recovery code of the confirmed enrollment.
+///
+/// Returns `false` if the code is unknown or was already used.
+/// The code is removed by the same statement that checks it,
+/// so it can not be used twice by concurrent requests.
+///
+/// # Tables
+///
+/// - account_mfa
+pub async fn consume_recovery_code(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+    form_code: RecoveryCode,
+) -> DatabaseResult<bool> {
+    let query = consume_recovery_code_query(form_account_id, form_code.hash())
+        .execute(conn)
+        .await?;
+
+    Ok(query > 0)
+}
+
+/// Returns the query used by [`regenerate_recovery_codes`].
+#[auto_type(no_type_alias)]
+pub fn regenerate_recovery_codes_query(
+    form_account_id: AccountId,
+    form_recovery_code_hashes: Vec<TokenHash>,
+) -> _ {
+    let filter_cond = account_mfa::account_id
+        .eq(form_account_id)
+        .and(account_mfa::confirmed_at.is_not_null());
+
+    update(account_mfa::table.filter(filter_cond))
+        .set(account_mfa::recovery_code_hashes.eq(form_recovery_code_hashes))
+}
+
+/// Replaces all recovery codes of the confirmed enrollment and returns them.
+///
+/// Returns `None` if MFA is not enabled.
+///
+/// # Tables
+///
+/// - account_mfa
+pub async fn regenerate_recovery_codes(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+) -> DatabaseResult<Option<AccountMfaRecoveryCodes>> {
+    let codes = AccountMfaRecoveryCodes::generate();
+    let query = regenerate_recovery_codes_query(form_account_id, codes.hashes())
+        .execute(conn)
+        .await?;
+
+    Ok((query > 0).then_some(codes))
+}
+
+/// Second factor verified by [`confirm_mfa`] and [`satisfy_mfa`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "forms do nothing unless you use them"]
+pub enum MfaCode {
+    /// Code of the authenticator app, see [`TotpSecret::code`].
+    Totp(u32),
+    /// Single-use recovery code.
+    Recovery(RecoveryCode),
+}
+
+/// Negative result of [`confirm_mfa`] and [`satisfy_mfa`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "errors do nothing unless you use them"]
+pub enum MfaFailure {
+    /// No active session matches the token.
+    #[error("unknown session")]
+    UnknownSession,
+    /// The enrollment is missing, or is not yet (for [`satisfy_mfa`])
+    /// or already (for [`confirm_mfa`]) confirmed.
+    #[error("mfa not enabled")]
+    NotEnabled,
+    /// The code is wrong, was already used, or is older than the last accepted one.
+    #[error("invalid mfa code")]
+    InvalidCode,
+}
+
+/// Returns the query used by [`satisfy_mfa`] to lock the session.
+#[auto_type(no_type_alias)]
+pub fn view_mfa_session_query(form: AccountSessionToken) -> _ {
+    let form_account_id: AccountId = form.account_id;
+    let form_token_hash: TokenHash = form.token.hash();
+    let filter_cond = account_sessions::account_id
+        .eq(form_account_id)
+        .and(account_sessions::token_hash.eq(form_token_hash))
+        .and(account_sessions::expired_at.gt(now))
+        .and(account_sessions::deleted_at.is_null());
+
+    account_sessions::table
+        .filter(filter_cond)
+        .select(account_sessions::is_mfa_satisfied)
+        .for_update()
+}
+
+/// Returns the query used by [`satisfy_mfa`] to lock the confirmed enrollment.
+#[auto_type(no_type_alias)]
+pub fn view_mfa_secret_query(form_account_id: AccountId) -> _ {
+    let filter_cond = account_mfa::account_id
+        .eq(form_account_id)
+        .and(account_mfa::confirmed_at.is_not_null());
+
+    account_mfa::table
+        .filter(filter_cond)
+        .select((account_mfa::totp_secret, account_mfa::last_totp_counter))
+        .for_update()
+}
+
+/// Returns the query used by [`confirm_mfa`] to lock the pending enrollment.
+#[auto_type(no_type_alias)]
+pub fn view_pending_mfa_secret_query(form_account_id: AccountId) -> _ {
+    let filter_cond = account_mfa::account_id
+        .eq(form_account_id)
+        .and(account_mfa::confirmed_at.is_null());
+
+    account_mfa::table
+        .filter(filter_cond)
+        .select(account_mfa::totp_secret)
+        .for_update()
+}
+
+/// Returns the query used by [`confirm_mfa`] to confirm the enrollment
+/// and record the accepted TOTP code.
+#[auto_type(no_type_alias)]
+pub fn confirm_mfa_query(form_account_id: AccountId, form_counter: i64) -> _ {
+    let filter_cond = account_mfa::account_id.eq(form_account_id);
+    update(account_mfa::table.filter(filter_cond)).set((
+        account_mfa::confirmed_at.eq(now),
+        account_mfa::last_totp_counter.eq(form_counter),
+    ))
+}
+
+/// Returns the query used by [`satisfy_mfa`] to record the accepted TOTP code.
+#[auto_type(no_type_alias)]
+pub fn accept_totp_counter_query(form_account_id: AccountId, form_counter: i64) -> _ {
+    let filter_cond = account_mfa::account_id.eq(form_account_id);
+    update(account_mfa::table.filter(filter_cond))
+        .set(account_mfa::last_totp_counter.eq(form_counter))
+}
+
+/// Returns the query used by [`satisfy_mfa`] to flag the session.
+#[auto_type(no_type_alias)]
+pub fn satisfy_mfa_query(form: AccountSessionToken) -> _ {
+    let form_account_id: AccountId = form.account_id;
+    let form_token_hash: TokenHash = form.token.hash();
+    let filter_cond = account_sessions::account_id
+        .eq(form_account_id)
+        .and(account_sessions::token_hash.eq(form_token_hash));
+
+    update(account_sessions::table.filter(filter_cond))
+        .set(account_sessions::is_mfa_satisfied.eq(true))
+}
+
+/// Returns the time-step counter of the TOTP code, if it's valid at the current time.
+///
+/// Counters up to [`TOTP_SKEW`] steps away are accepted, but only if they're
+/// newer than the last accepted one, so every code can be used only once.
+fn verify_totp(secret: &TotpSecret, code: u32, last_counter: Option<i64>) -> Option<i64> {
+    let counter = TotpSecret::counter_at(OffsetDateTime::now_utc().unix_timestamp());
+    (counter - TOTP_SKEW..=counter + TOTP_SKEW)
+        .filter(|x| last_counter.is_none_or(|last| *x > last))
+        .find(|x| secret.code(*x) == code)
+}
+
+/// Verifies the first TOTP code and confirms the pending enrollment.
+///
+/// Recovery codes are rejected with [`MfaFailure::InvalidCode`], as they do not
+/// prove that the authenticator app is set up. The accepted code is recorded,
+/// so it can not be used again by [`satisfy_mfa`].
+///
+/// # Tables
+///
+/// - account_mfa
+pub async fn confirm_mfa(
+    conn: &mut AsyncPgConnection,
+    key: &EncryptionKey,
+    form_account_id: AccountId,
+    form_code: MfaCode,
+) -> DatabaseResult<Result<(), MfaFailure>> {
+    conn.transaction(|conn| {
+        async move {
+            let query: Option<EncryptedSecret> = view_pending_mfa_secret_query(form_account_id)
+                .get_result(conn)
+                .await
+                .optional()?;
+
+            let Some(totp_secret) = query else {
+                return Ok(Err(MfaFailure::NotEnabled));
+            };
+
+            let MfaCode::Totp(code) = form_code else {
+                return Ok(Err(MfaFailure::InvalidCode));
+            };
+
+            let secret = key
+                .decrypt(form_account_id, &totp_secret)
+                .map_err(|x| Error::DeserializationError(x.into()))?;
+            let Some(counter) = verify_totp(&secret, code, None) else {
+                return Ok(Err(MfaFailure::InvalidCode));
+            };
+
+            let _query = confirm_mfa_query(form_account_id, counter)
+                .execute(conn)
+                .await?;
+            Ok(Ok(()))
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Verifies the TOTP or recovery code and flags the session as MFA satisfied.
+///
+/// The code is checked and consumed in the same transaction that flags the
+/// session, so it can not be used twice by concurrent requests.
+///
+/// # Tables
+///
+/// - account_sessions
+/// - account_mfa
+pub async fn satisfy_mfa(
+    conn: &mut AsyncPgConnection,
+    key: &EncryptionKey,
+    form: AccountSessionToken,
+    form_code: MfaCode,
+) -> DatabaseResult<Result<(), MfaFailure>> {
+    conn.transaction(|conn| {
+        async move {
+            let form_account_id = form.account_id;
+            let query: Option<bool> = view_mfa_session_query(form.clone())
+                .get_result(conn)
+                .await
+                .optional()?;
+
+            if query.is_none() {
+                return Ok(Err(MfaFailure::UnknownSession));
+            }
+
+            let query: Option<(EncryptedSecret, Option<i64>)> =
+                view_mfa_secret_query(form_account_id)
+                    .get_result(conn)
+                    .await
+                    .optional()?;
+
+            let Some((totp_secret, last_counter)) = query else {
+                return Ok(Err(MfaFailure::NotEnabled));
+            };
+
+            match form_code {
+                MfaCode::Totp(code) => {
+                    let secret = key
+                        .decrypt(form_account_id, &totp_secret)
+                        .map_err(|x| Error::DeserializationError(x.into()))?;
+                    let Some(counter) = verify_totp(&secret, code, last_counter) else {
+                        return Ok(Err(MfaFailure::InvalidCode));
+                    };
+
+                    let _query = accept_totp_counter_query(form_account_id, counter)
+                        .execute(conn)
+                        .await?;
+                }
+                MfaCode::Recovery(code) => {
+                    let query = consume_recovery_code_query(form_account_id, code.hash())
+                        .execute(conn)
+                        .await?;
+                    if query == 0 {
+                        return Ok(Err(MfaFailure::InvalidCode));
+                    }
+                }
+            }
+
+            let _query = satisfy_mfa_query(form).execute(conn).await?;
+            Ok(Ok(()))
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Returns the query used by [`disable_mfa`].
+#[auto_type(no_type_alias)]
+pub fn disable_mfa_query(form_account_id: AccountId) -> _ {
+    let filter_cond = account_mfa::account_id.eq(form_account_id);
+    delete(account_mfa::table.filter(filter_cond))
+}
+
+/// Disables MFA, deleting the secret and all recovery codes.
+///
+/// # Tables
+///
+/// - account_mfa
+pub async fn disable_mfa(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+) -> DatabaseResult<()> {
+    let _query = disable_mfa_query(form_account_id).execute(conn).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use axiston_db_schema::enumerations::AccountStatus;
+    use axiston_db_schema::secrets::SessionToken;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    use crate::account_mfa::*;
+    use crate::account_sessions::{authenticate, create_session, AccountSession};
+    use crate::testing::{AccountFactory, TestDatabase};
+
+    #[test]
+    fn account_mfa_queries() {
+        let account_id = AccountId::new(Uuid::nil());
+        let key = EncryptionKey::from_bytes([0; 32]);
+        let secret = TotpSecret::from_bytes([0; 20]);
+        let form = AccountMfaEnrollInput::new(&key, account_id, &secret);
+        let code_hash = RecoveryCode::from_bytes([0; 32]).hash();
+
+        let query = enroll_mfa_query(form, vec![code_hash]);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "account_mfa" ("recovery_code_hashes", "account_id", "totp_secret") VALUES ($1, $2, $3) ON CONFLICT ("account_id") DO UPDATE SET "totp_secret" = excluded."totp_secret", "recovery_code_hashes" = excluded."recovery_code_hashes" WHERE ("account_mfa"."confirmed_at" IS NULL) -- binds: [[TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925)], AccountId(00000000-0000-0000-0000-000000000000), EncryptedSecret(..)]"#);
+
+        let query = view_pending_mfa_secret_query(account_id);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "account_mfa"."totp_secret" FROM "account_mfa" WHERE (("account_mfa"."account_id" = $1) AND ("account_mfa"."confirmed_at" IS NULL)) FOR UPDATE -- binds: [AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = confirm_mfa_query(account_id, 0);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_mfa" SET "confirmed_at" = CURRENT_TIMESTAMP, "last_totp_counter" = $1 WHERE ("account_mfa"."account_id" = $2) -- binds: [0, AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = view_mfa_query(account_id);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "account_mfa"."totp_secret", CARDINALITY("account_mfa"."recovery_code_hashes"), "account_mfa"."created_at", "account_mfa"."confirmed_at" FROM "account_mfa" WHERE ("account_mfa"."account_id" = $1) -- binds: [AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = consume_recovery_code_query(account_id, code_hash);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_mfa" SET "recovery_code_hashes" = ARRAY_REMOVE("account_mfa"."recovery_code_hashes", $1) WHERE ((("account_mfa"."account_id" = $2) AND ("account_mfa"."confirmed_at" IS NOT NULL)) AND ("account_mfa"."recovery_code_hashes" @> $3)) -- binds: [TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925), AccountId(00000000-0000-0000-0000-000000000000), [TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925)]]"#);
+
+        let query = regenerate_recovery_codes_query(account_id, vec![code_hash]);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_mfa" SET "recovery_code_hashes" = $1 WHERE (("account_mfa"."account_id" = $2) AND ("account_mfa"."confirmed_at" IS NOT NULL)) -- binds: [[TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925)], AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let token = AccountSessionToken {
+            account_id,
+            token: SessionToken::from_bytes([0; 32]),
+        };
+
+        let query = view_mfa_session_query(token.clone());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "account_sessions"."is_mfa_satisfied" FROM "account_sessions" WHERE (((("account_sessions"."account_id" = $1) AND ("account_sessions"."token_hash" = $2)) AND ("account_sessions"."expired_at" > CURRENT_TIMESTAMP)) AND ("account_sessions"."deleted_at" IS NULL)) FOR UPDATE -- binds: [AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925)]"#);
+
+        let query = view_mfa_secret_query(account_id);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "account_mfa"."totp_secret", "account_mfa"."last_totp_counter" FROM "account_mfa" WHERE (("account_mfa"."account_id" = $1) AND ("account_mfa"."confirmed_at" IS NOT NULL)) FOR UPDATE -- binds: [AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = accept_totp_counter_query(account_id, 0);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_mfa" SET "last_totp_counter" = $1 WHERE ("account_mfa"."account_id" = $2) -- binds: [0, AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = satisfy_mfa_query(token);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_sessions" SET "is_mfa_satisfied" = $1 WHERE (("account_sessions"."account_id" = $2) AND ("account_sessions"."token_hash" = $3)) -- binds: [true, AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925)]"#);
+
+        let query = disable_mfa_query(account_id);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"DELETE FROM "account_mfa" WHERE ("account_mfa"."account_id" = $1) -- binds: [AccountId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+
+    #[tokio::test]
+    async fn mfa_lifecycle() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new().create(&mut conn).await?;
+
+        let key = EncryptionKey::generate();
+        let secret = TotpSecret::generate();
+        assert!(view_mfa(&mut conn, account.id).await?.is_none());
+
+        // Recovery codes are only accepted once the enrollment is confirmed.
+        let form = AccountMfaEnrollInput::new(&key, account.id, &secret);
+        let codes = enroll_mfa(&mut conn, form).await?.unwrap();
+        assert_eq!(codes.recovery_codes.len(), RECOVERY_CODE_COUNT);
+
+        let code = codes.recovery_codes[0].clone();
+        assert!(!consume_recovery_code(&mut conn, account.id, code.clone()).await?);
+        assert!(regenerate_recovery_codes(&mut conn, account.id)
+            .await?
+            .is_none());
+
+        // Pending enrollments are replaced.
+        let secret = TotpSecret::generate();
+        let form = AccountMfaEnrollInput::new(&key, account.id, &secret);
+        let codes = enroll_mfa(&mut conn, form).await?.unwrap();
+
+        let mfa = view_mfa(&mut conn, account.id).await?.unwrap();
+        assert!(!mfa.is_confirmed());
+        assert_eq!(
+            key.decrypt(account.id, &mfa.totp_secret),
+            Ok(secret.clone())
+        );
+
+        // Wrong and recovery codes leave the enrollment pending.
+        let counter = TotpSecret::counter_at(OffsetDateTime::now_utc().unix_timestamp());
+        let wrong = MfaCode::Totp((secret.code(counter) + 1) % 1_000_000);
+        let result = confirm_mfa(&mut conn, &key, account.id, wrong).await?;
+        assert_eq!(result, Err(MfaFailure::InvalidCode));
+        let recovery = MfaCode::Recovery(codes.recovery_codes[0].clone());
+        let result = confirm_mfa(&mut conn, &key, account.id, recovery).await?;
+        assert_eq!(result, Err(MfaFailure::InvalidCode));
+        let mfa = view_mfa(&mut conn, account.id).await?.unwrap();
+        assert!(!mfa.is_confirmed());
+
+        let code = MfaCode::Totp(secret.code(counter));
+        let result = confirm_mfa(&mut conn, &key, account.id, code.clone()).await?;
+        assert_eq!(result, Ok(()));
+        let result = confirm_mfa(&mut conn, &key, account.id, code).await?;
+        assert_eq!(result, Err(MfaFailure::NotEnabled));
+
+        // Confirmed enrollments are not replaced.
+        let form = AccountMfaEnrollInput::new(&key, account.id, &TotpSecret::generate());
+        assert!(enroll_mfa(&mut conn, form).await?.is_none());
+
+        // Recovery codes are single-use.
+        let code = codes.recovery_codes[0].clone();
+        assert!(consume_recovery_code(&mut conn, account.id, code.clone()).await?);
+        assert!(!consume_recovery_code(&mut conn, account.id, code).await?);
+
+        let unknown = RecoveryCode::generate();
+        assert!(!consume_recovery_code(&mut conn, account.id, unknown).await?);
+
+        let mfa = view_mfa(&mut conn, account.id).await?.unwrap();
+        assert!(mfa.is_confirmed());
+        assert_eq!(mfa.recovery_codes_left, RECOVERY_CODE_COUNT as i32 - 1);
+
+        // Regenerated codes replace all previous ones.
+        let regenerated = regenerate_recovery_codes(&mut conn, account.id).await?;
+        let regenerated = regenerated.unwrap();
+        let code = codes.recovery_codes[1].clone();
+        assert!(!consume_recovery_code(&mut conn, account.id, code).await?);
+        let code = regenerated.recovery_codes[1].clone();
+        assert!(consume_recovery_code(&mut conn, account.id, code).await?);
+
+        disable_mfa(&mut conn, account.id).await?;
+        assert!(view_mfa(&mut conn, account.id).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn satisfy_mfa_sessions() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new()
+            .status(AccountStatus::Active)
+            .create(&mut conn)
+            .await?;
+
+        let form = AccountSession {
+            region_id: "EU".to_owned(),
+            ip_address: "127.0.0.1/32".parse().unwrap(),
+            user_agent: "Mozilla/5.0".to_owned(),
+        };
+
+        let token = create_session(&mut conn, account.id, form.clone()).await?;
+        let key = EncryptionKey::generate();
+        let secret = TotpSecret::generate();
+        let counter = TotpSecret::counter_at(OffsetDateTime::now_utc().unix_timestamp());
+        let confirm = MfaCode::Totp(secret.code(counter));
+        let code = MfaCode::Totp(secret.code(counter + 1));
+
+        // Requires the confirmed enrollment.
+        let result = satisfy_mfa(&mut conn, &key, token.clone(), code.clone()).await?;
+        assert_eq!(result, Err(MfaFailure::NotEnabled));
+
+        let form_mfa = AccountMfaEnrollInput::new(&key, account.id, &secret);
+        let codes = enroll_mfa(&mut conn, form_mfa).await?.unwrap();
+        let result = satisfy_mfa(&mut conn, &key, token.clone(), code.clone()).await?;
+        assert_eq!(result, Err(MfaFailure::NotEnabled));
+        let result = confirm_mfa(&mut conn, &key, account.id, confirm.clone()).await?;
+        assert_eq!(result, Ok(()));
+
+        // Rejects the code accepted by the confirmation.
+        let result = satisfy_mfa(&mut conn, &key, token.clone(), confirm).await?;
+        assert_eq!(result, Err(MfaFailure::InvalidCode));
+
+        let wrong = MfaCode::Totp((secret.code(counter + 1) + 1) % 1_000_000);
+        let result = satisfy_mfa(&mut conn, &key, token.clone(), wrong).await?;
+        assert_eq!(result, Err(MfaFailure::InvalidCode));
+        let context = authenticate(&mut conn, token.clone()).await?.unwrap();
+        assert!(!context.session.is_mfa_satisfied);
+
+        let result = satisfy_mfa(&mut conn, &key, token.clone(), code.clone()).await?;
+        assert_eq!(result, Ok(()));
+        let context = authenticate(&mut conn, token).await?.unwrap();
+        assert!(context.session.is_mfa_satisfied);
+
+        // Rejects replayed TOTP codes.
+        let token = create_session(&mut conn, account.id, form.clone()).await?;
+        let result = satisfy_mfa(&mut conn, &key, token.clone(), code).await?;
+        assert_eq!(result, Err(MfaFailure::InvalidCode));
+
+        // Recovery codes are single-use.
+        let code = MfaCode::Recovery(codes.recovery_codes[0].clone());
+        let result = satisfy_mfa(&mut conn, &key, token.clone(), code.clone()).await?;
+        assert_eq!(result, Ok(()));
+        let token = create_session(&mut conn, account.id, form).await?;
+        let result = satisfy_mfa(&mut conn, &key, token, code.clone()).await?;
+        assert_eq!(result, Err(MfaFailure::InvalidCode));
+
+        let unknown = AccountSessionToken {
+            account_id: account.id,
+            token: SessionToken::generate(),
+        };
+
+        let result = satisfy_mfa(&mut conn, &key, unknown, code).await?;
+        assert_eq!(result, Err(MfaFailure::UnknownSession));
+
+        Ok(())
+    }
+}
@@ -1,16 +1,20 @@
 //! Data layer for account permissions management.
 
+use axiston_db_schema::identifiers::AccountId;
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::account_permissions;
 use diesel::dsl::*;
+use diesel::pg::Pg;
 use diesel::prelude::*;
+use diesel::query_builder::{QueryFragment, QueryId};
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 use crate::DatabaseResult;
 
-#[derive(Debug, Clone, Insertable, Queryable, Selectable)]
+/// Granted permissions of the account, none by default.
+#[derive(Debug, Default, Clone, Insertable, AsChangeset, Queryable, Selectable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[diesel(table_name = schema::account_permissions)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -26,6 +30,21 @@ pub struct AccountPermissions {
     pub write_workflows: bool,
 }
 
+/// Returns the query used by [`update_permissions`].
+///
+/// Returns the opaque type instead of using `#[auto_type]`, as `diesel`
+/// doesn't export the types of `ON CONFLICT` clauses.
+pub fn update_permissions_query(
+    form_account_id: AccountId,
+    form: &AccountPermissions,
+) -> impl QueryFragment<Pg> + QueryId + Send + '_ {
+    insert_into(account_permissions::table)
+        .values((account_permissions::account_id.eq(form_account_id), form))
+        .on_conflict(account_permissions::account_id)
+        .do_update()
+        .set(form)
+}
+
 /// Automatically creates or updates permissions.
 ///
 /// # Tables
@@ -33,37 +52,26 @@ pub struct AccountPermissions {
 /// - account_permissions
 pub async fn update_permissions(
     conn: &mut AsyncPgConnection,
-    form_account_id: Uuid,
+    form_account_id: AccountId,
     form: AccountPermissions,
 ) -> DatabaseResult<()> {
-    use schema::account_permissions::dsl::*;
-
-    let _query = insert_into(account_permissions)
-        .values((
-            account_id.eq(form_account_id),
-            read_accounts.eq(form.read_accounts),
-            write_accounts.eq(form.write_accounts),
-            read_workspaces.eq(form.read_workspaces),
-            write_workspaces.eq(form.write_workspaces),
-            read_workflows.eq(form.read_workflows),
-            write_workflows.eq(form.write_workflows),
-        ))
-        .on_conflict(account_id)
-        .do_update()
-        .set((
-            read_accounts.eq(form.read_accounts),
-            read_accounts.eq(form.write_accounts),
-            read_workspaces.eq(form.read_workspaces),
-            write_workspaces.eq(form.write_workspaces),
-            read_workflows.eq(form.read_workflows),
-            write_workflows.eq(form.write_workflows),
-        ))
+    let _query = update_permissions_query(form_account_id, &form)
         .execute(conn)
         .await?;
 
     Ok(())
 }
 
+/// Returns the query used by [`find_permissions`].
+#[auto_type(no_type_alias)]
+pub fn find_permissions_query(form_account_id: AccountId) -> _ {
+    let filter_cond = account_permissions::account_id.eq(form_account_id);
+    let selection: AsSelect<AccountPermissions, Pg> = AccountPermissions::as_select();
+    account_permissions::table
+        .filter(filter_cond)
+        .select(selection)
+}
+
 /// Returns the account permissions by account ID.
 ///
 /// # Tables
@@ -71,16 +79,66 @@ pub async fn update_permissions(
 /// - account_permissions
 pub async fn find_permissions(
     conn: &mut AsyncPgConnection,
-    form_account_id: Uuid,
+    form_account_id: AccountId,
 ) -> DatabaseResult<AccountPermissions> {
-    use schema::account_permissions::dsl::*;
-
-    let filter_cond = account_id.eq(form_account_id);
-    let query = account_permissions
-        .filter(filter_cond)
-        .select(AccountPermissions::as_select())
+    let query = find_permissions_query(form_account_id)
         .get_result(conn)
         .await?;
 
     Ok(query)
 }
+
+#[cfg(test)]
+mod test {
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use uuid::Uuid;
+
+    use crate::account_permissions::*;
+    use crate::testing::{AccountFactory, TestDatabase};
+
+    #[test]
+    fn account_permissions_queries() {
+        let form = AccountPermissions {
+            read_accounts: true,
+            write_accounts: false,
+            read_workspaces: true,
+            write_workspaces: false,
+            read_workflows: true,
+            write_workflows: false,
+        };
+
+        let query = update_permissions_query(Uuid::nil().into(), &form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "account_permissions" ("account_id", "read_accounts", "write_accounts", "read_workspaces", "write_workspaces", "read_workflows", "write_workflows") VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT ("account_id") DO UPDATE SET "read_accounts" = $8, "write_accounts" = $9, "read_workspaces" = $10, "write_workspaces" = $11, "read_workflows" = $12, "write_workflows" = $13 -- binds: [AccountId(00000000-0000-0000-0000-000000000000), true, false, true, false, true, false, true, false, true, false, true, false]"#);
+
+        let query = find_permissions_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "account_permissions"."read_accounts", "account_permissions"."write_accounts", "account_permissions"."read_workspaces", "account_permissions"."write_workspaces", "account_permissions"."read_workflows", "account_permissions"."write_workflows" FROM "account_permissions" WHERE ("account_permissions"."account_id" = $1) -- binds: [AccountId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+
+    #[tokio::test]
+    async fn update_permissions_twice() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new().create(&mut conn).await?;
+
+        let form = AccountPermissions {
+            read_accounts: true,
+            ..AccountPermissions::default()
+        };
+
+        update_permissions(&mut conn, account.id, form).await?;
+        let form = AccountPermissions {
+            write_accounts: true,
+            read_workflows: true,
+            ..AccountPermissions::default()
+        };
+
+        // Conflicting insert updates every column with its own value.
+        update_permissions(&mut conn, account.id, form).await?;
+        let output = find_permissions(&mut conn, account.id).await?;
+        assert!(!output.read_accounts && output.write_accounts);
+        assert!(!output.read_workspaces && !output.write_workspaces);
+        assert!(output.read_workflows && !output.write_workflows);
+        Ok(())
+    }
+}
@@ -1,15 +1,26 @@
 //! Data layer for account sessions management.
 
+use axiston_db_schema::constraints::ConstraintViolation;
+use axiston_db_schema::enumerations::AccountStatus;
+use axiston_db_schema::identifiers::AccountId;
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::{account_permissions, account_sessions, accounts};
+use axiston_db_schema::secrets::{SessionToken, TokenHash};
 use diesel::dsl::*;
+use diesel::expression::{SqlLiteral, UncheckedBind};
+use diesel::pg::data_types::PgInterval;
+use diesel::pg::Pg;
 use diesel::prelude::*;
+use diesel::sql_types::{Interval, Timestamptz};
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use ipnet::IpNet;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use time::{Duration, OffsetDateTime};
 
-use crate::DatabaseResult;
+use crate::account_permissions::AccountPermissions;
+use crate::dsl::{interval, least};
+use crate::{DatabaseResult, Validate};
 
 #[derive(Debug, Clone, Insertable, Queryable, Selectable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -22,61 +33,243 @@ pub struct AccountSession {
     pub user_agent: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Validate for AccountSession {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        ConstraintViolation::AccountSessionsRegionAlphanumeric.check_text(&self.region_id)?;
+
+        Ok(())
+    }
+}
+
+/// Plaintext token of the session, only its hash is stored.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AccountSessionToken {
-    pub account_id: Uuid,
-    pub token_seq: Uuid,
+    pub account_id: AccountId,
+    pub token: SessionToken,
+}
+
+/// Returns the query used by [`create_session`].
+#[auto_type(no_type_alias)]
+pub fn create_session_query(
+    form_account_id: AccountId,
+    form_token_hash: TokenHash,
+    form: AccountSession,
+) -> _ {
+    insert_into(account_sessions::table).values((
+        account_sessions::account_id.eq(form_account_id),
+        account_sessions::token_hash.eq(form_token_hash),
+        form,
+    ))
 }
 
-/// Creates the new session and returns the token sequence.
+/// Creates the new session and returns its token.
+///
+/// The token is generated by the client and returned only once,
+/// as only its hash is stored. The second factor is verified afterward,
+/// see [`satisfy_mfa`].
+///
+/// [`satisfy_mfa`]: crate::account_mfa::satisfy_mfa
 ///
 /// # Tables
 ///
 /// - account_sessions
 pub async fn create_session(
     conn: &mut AsyncPgConnection,
-    form_account_id: Uuid,
+    form_account_id: AccountId,
     form: AccountSession,
 ) -> DatabaseResult<AccountSessionToken> {
-    use schema::account_sessions::dsl::*;
-
-    let query = insert_into(account_sessions)
-        .values((
-            account_id.eq(form_account_id),
-            region_id.eq(form.region_id),
-            ip_address.eq(form.ip_address),
-            user_agent.eq(form.user_agent),
-        ))
-        .returning(token_seq)
-        .get_result(conn)
+    let token = SessionToken::generate();
+    let _query = create_session_query(form_account_id, token.hash(), form)
+        .execute(conn)
         .await?;
 
     Ok(AccountSessionToken {
         account_id: form_account_id,
-        token_seq: query,
+        token,
     })
 }
 
-/// Returns the active session.
+/// Returns the query used by [`find_active_session`].
+#[auto_type(no_type_alias)]
+pub fn find_active_session_query(form: AccountSessionToken) -> _ {
+    let form_account_id: AccountId = form.account_id;
+    let form_token_hash: TokenHash = form.token.hash();
+    let active: AccountStatus = AccountStatus::Active;
+    let filter_cond = account_sessions::account_id
+        .eq(form_account_id)
+        .and(account_sessions::token_hash.eq(form_token_hash))
+        .and(account_sessions::expired_at.gt(now))
+        .and(account_sessions::deleted_at.is_null())
+        .and(accounts::status.eq(active))
+        .and(accounts::deleted_at.is_null());
+
+    let selection: AsSelect<AccountSession, Pg> = AccountSession::as_select();
+    account_sessions::table
+        .inner_join(accounts::table)
+        .filter(filter_cond)
+        .select(selection)
+}
+
+/// Returns the active session of the active account.
 ///
 /// # Tables
 ///
 /// - account_sessions
+/// - accounts
 pub async fn find_active_session(
     conn: &mut AsyncPgConnection,
     form: AccountSessionToken,
 ) -> DatabaseResult<Option<AccountSession>> {
-    use schema::account_sessions::dsl::*;
+    let query = find_active_session_query(form)
+        .get_result(conn)
+        .await
+        .optional()?;
+
+    Ok(query)
+}
 
-    let filter_cond = account_id
-        .eq(form.account_id)
-        .and(token_seq.eq(form.token_seq))
-        .and(expired_at.le(now))
-        .and(deleted_at.is_null());
+/// Returns the query used by [`view_active_sessions`].
+#[auto_type(no_type_alias)]
+pub fn view_active_sessions_query(form_account_id: AccountId) -> _ {
+    let active: AccountStatus = AccountStatus::Active;
+    let filter_cond = account_sessions::account_id
+        .eq(form_account_id)
+        .and(account_sessions::expired_at.gt(now))
+        .and(account_sessions::deleted_at.is_null())
+        .and(accounts::status.eq(active))
+        .and(accounts::deleted_at.is_null());
 
-    let query = account_sessions
+    let selection: AsSelect<AccountSession, Pg> = AccountSession::as_select();
+    account_sessions::table
+        .inner_join(accounts::table)
         .filter(filter_cond)
-        .select(AccountSession::as_select())
+        .select(selection)
+}
+
+/// Returns all active sessions of the active account.
+///
+/// # Tables
+///
+/// - account_sessions
+/// - accounts
+pub async fn view_active_sessions(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+) -> DatabaseResult<Vec<AccountSession>> {
+    let query = view_active_sessions_query(form_account_id)
+        .get_results(conn)
+        .await?;
+
+    Ok(query)
+}
+
+/// Lifetime policy of sessions, used by [`validate_session`] and [`refresh_session`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AccountSessionPolicy {
+    /// Extends `expired_at` to the current time plus the window on every use.
+    pub sliding_window: Duration,
+    /// Caps `expired_at` to `issued_at` plus the lifetime.
+    pub max_lifetime: Duration,
+}
+
+impl Default for AccountSessionPolicy {
+    fn default() -> Self {
+        Self {
+            sliding_window: Duration::days(7),
+            max_lifetime: Duration::days(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[diesel(table_name = schema::account_sessions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[must_use = "forms do nothing unless you use them"]
+pub struct AccountSessionViewOutput {
+    pub region_id: String,
+    pub ip_address: IpNet,
+    pub user_agent: String,
+    /// Whether the second factor was verified, see [`satisfy_mfa`].
+    ///
+    /// [`satisfy_mfa`]: crate::account_mfa::satisfy_mfa
+    pub is_mfa_satisfied: bool,
+
+    pub issued_at: OffsetDateTime,
+    pub expired_at: OffsetDateTime,
+    pub last_seen_at: OffsetDateTime,
+}
+
+/// Returns the extended expiration of the session, shared by
+/// [`validate_session`] and [`refresh_session`].
+///
+/// `LEAST(CURRENT_TIMESTAMP + sliding_window, issued_at + max_lifetime)`
+#[auto_type]
+fn sliding_expired_at(policy: AccountSessionPolicy) -> _ {
+    let sliding_window: PgInterval = interval(policy.sliding_window);
+    let max_lifetime: PgInterval = interval(policy.max_lifetime);
+    let extended: UncheckedBind<SqlLiteral<Timestamptz>, AsExprOf<PgInterval, Interval>> =
+        sql::<Timestamptz>("CURRENT_TIMESTAMP + ").bind::<Interval, _>(sliding_window);
+    least(extended, account_sessions::issued_at + max_lifetime)
+}
+
+/// Returns whether the account is active and not deleted, shared by
+/// [`validate_session`] and [`refresh_session`].
+///
+/// `UPDATE` can't join other tables, so the account is matched by the subquery.
+#[auto_type]
+fn is_active_account(form_account_id: AccountId) -> _ {
+    let active: AccountStatus = AccountStatus::Active;
+    let filter_cond = accounts::id
+        .eq(form_account_id)
+        .and(accounts::status.eq(active))
+        .and(accounts::deleted_at.is_null());
+    exists(accounts::table.filter(filter_cond))
+}
+
+/// Returns the query used by [`validate_session`].
+#[auto_type(no_type_alias)]
+pub fn validate_session_query(form: AccountSessionToken, policy: AccountSessionPolicy) -> _ {
+    let form_account_id: AccountId = form.account_id;
+    let form_token_hash: TokenHash = form.token.hash();
+    let is_active: is_active_account = is_active_account(form_account_id);
+    let filter_cond = account_sessions::account_id
+        .eq(form_account_id)
+        .and(account_sessions::token_hash.eq(form_token_hash))
+        .and(account_sessions::expired_at.gt(now))
+        .and(account_sessions::deleted_at.is_null())
+        .and(is_active);
+
+    let expired_at: sliding_expired_at = sliding_expired_at(policy);
+
+    let selection: AsSelect<AccountSessionViewOutput, Pg> = AccountSessionViewOutput::as_select();
+    update(account_sessions::table.filter(filter_cond))
+        .set((
+            account_sessions::last_seen_at.eq(now),
+            account_sessions::expired_at.eq(expired_at),
+        ))
+        .returning(selection)
+}
+
+/// Validates the active session, touches it and extends its expiration.
+///
+/// Returns `None` if the session is unknown, deleted or expired,
+/// or if the account is deleted or not active.
+/// The session expires after the sliding window of inactivity,
+/// but never later than the max lifetime after it was issued.
+///
+/// # Tables
+///
+/// - account_sessions
+/// - accounts
+pub async fn validate_session(
+    conn: &mut AsyncPgConnection,
+    form: AccountSessionToken,
+    policy: AccountSessionPolicy,
+) -> DatabaseResult<Option<AccountSessionViewOutput>> {
+    let query = validate_session_query(form, policy)
         .get_result(conn)
         .await
         .optional()?;
@@ -84,29 +277,167 @@ pub async fn find_active_session(
     Ok(query)
 }
 
-/// Returns all active sessions.
+/// Returns the query used by [`refresh_session`].
+#[auto_type(no_type_alias)]
+pub fn refresh_session_query(
+    form: AccountSessionToken,
+    form_new_token_hash: TokenHash,
+    policy: AccountSessionPolicy,
+) -> _ {
+    let form_account_id: AccountId = form.account_id;
+    let form_token_hash: TokenHash = form.token.hash();
+    let is_active: is_active_account = is_active_account(form_account_id);
+    let filter_cond = account_sessions::account_id
+        .eq(form_account_id)
+        .and(account_sessions::token_hash.eq(form_token_hash))
+        .and(account_sessions::expired_at.gt(now))
+        .and(account_sessions::deleted_at.is_null())
+        .and(is_active);
+
+    let expired_at: sliding_expired_at = sliding_expired_at(policy);
+
+    update(account_sessions::table.filter(filter_cond)).set((
+        account_sessions::token_hash.eq(form_new_token_hash),
+        account_sessions::last_seen_at.eq(now),
+        account_sessions::expired_at.eq(expired_at),
+    ))
+}
+
+/// Rotates the token of the active session and extends its expiration.
+///
+/// Returns `None` if the session is unknown, deleted or expired,
+/// or if the account is deleted or not active.
+/// The previous token is no longer valid afterward.
 ///
 /// # Tables
 ///
 /// - account_sessions
-pub async fn view_active_sessions(
+/// - accounts
+pub async fn refresh_session(
     conn: &mut AsyncPgConnection,
-    form_account_id: Uuid,
-) -> DatabaseResult<Vec<AccountSession>> {
-    use schema::account_sessions::dsl::*;
+    form: AccountSessionToken,
+    policy: AccountSessionPolicy,
+) -> DatabaseResult<Option<AccountSessionToken>> {
+    let form_account_id = form.account_id;
+    let token = SessionToken::generate();
+    let query = refresh_session_query(form, token.hash(), policy)
+        .execute(conn)
+        .await?;
 
-    let filter_cond = account_id
+    Ok((query > 0).then_some(AccountSessionToken {
+        account_id: form_account_id,
+        token,
+    }))
+}
+
+/// Account of the session, returned by [`authenticate`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "forms do nothing unless you use them"]
+pub struct AuthContext {
+    pub account_id: AccountId,
+    pub display_name: String,
+    pub email_address: String,
+
+    pub session: AccountSessionViewOutput,
+    pub permissions: AccountPermissions,
+}
+
+/// Negative result of [`authenticate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "errors do nothing unless you use them"]
+pub enum AuthFailure {
+    /// No session of an active account matches the token.
+    #[error("unknown session")]
+    Unknown,
+    /// The session has expired.
+    #[error("expired session")]
+    Expired,
+    /// The session was deleted, e.g. on sign out.
+    #[error("revoked session")]
+    Revoked,
+}
+
+/// Returns the query used by [`authenticate`].
+#[auto_type(no_type_alias)]
+pub fn authenticate_query(form: AccountSessionToken) -> _ {
+    let form_account_id: AccountId = form.account_id;
+    let form_token_hash: TokenHash = form.token.hash();
+    let active: AccountStatus = AccountStatus::Active;
+    let filter_cond = account_sessions::account_id
         .eq(form_account_id)
-        .and(expired_at.le(now))
-        .and(deleted_at.is_null());
+        .and(account_sessions::token_hash.eq(form_token_hash))
+        .and(accounts::status.eq(active))
+        .and(accounts::deleted_at.is_null());
 
-    let query = account_sessions
+    let join_cond = account_permissions::account_id.eq(accounts::id);
+    let session: AsSelect<AccountSessionViewOutput, Pg> = AccountSessionViewOutput::as_select();
+    let permissions: AsSelect<Option<AccountPermissions>, Pg> =
+        Option::<AccountPermissions>::as_select();
+    account_sessions::table
+        .inner_join(accounts::table)
+        .left_join(account_permissions::table.on(join_cond))
         .filter(filter_cond)
-        .select(AccountSession::as_select())
-        .get_results(conn)
-        .await?;
+        .select((
+            account_sessions::deleted_at.is_not_null(),
+            account_sessions::expired_at.le(now),
+            accounts::display_name,
+            accounts::email_address,
+            session,
+            permissions,
+        ))
+}
 
-    Ok(query)
+/// Returns the account and permissions of the active session.
+///
+/// Loads the session, the account and its permissions in one query.
+/// Sessions of deleted or not active accounts are reported as unknown,
+/// and accounts without permissions have none granted.
+///
+/// # Tables
+///
+/// - account_sessions
+/// - accounts
+/// - account_permissions
+pub async fn authenticate(
+    conn: &mut AsyncPgConnection,
+    form: AccountSessionToken,
+) -> DatabaseResult<Result<AuthContext, AuthFailure>> {
+    let form_account_id = form.account_id;
+    let query = authenticate_query(form).get_result(conn).await.optional()?;
+
+    let Some((is_revoked, is_expired, display_name, email_address, session, permissions)) = query
+    else {
+        return Ok(Err(AuthFailure::Unknown));
+    };
+
+    if is_revoked {
+        return Ok(Err(AuthFailure::Revoked));
+    } else if is_expired {
+        return Ok(Err(AuthFailure::Expired));
+    }
+
+    Ok(Ok(AuthContext {
+        account_id: form_account_id,
+        display_name,
+        email_address,
+        session,
+        permissions: permissions.unwrap_or_default(),
+    }))
+}
+
+/// Returns the query used by [`delete_session`].
+#[auto_type(no_type_alias)]
+pub fn delete_session_query(form: AccountSessionToken) -> _ {
+    let form_account_id: AccountId = form.account_id;
+    let form_token_hash: TokenHash = form.token.hash();
+    let filter_cond = account_sessions::account_id
+        .eq(form_account_id)
+        .and(account_sessions::token_hash.eq(form_token_hash))
+        .and(account_sessions::deleted_at.is_null());
+
+    update(account_sessions::table.filter(filter_cond)).set(account_sessions::deleted_at.eq(now))
 }
 
 /// Deletes a single active session.
@@ -118,19 +449,20 @@ pub async fn delete_session(
     conn: &mut AsyncPgConnection,
     form: AccountSessionToken,
 ) -> DatabaseResult<()> {
-    use schema::account_sessions::dsl::*;
-
-    let filter_cond = account_id
-        .eq(form.account_id)
-        .and(token_seq.eq(form.token_seq))
-        .and(deleted_at.is_null());
+    let _query = delete_session_query(form).execute(conn).await?;
+    Ok(())
+}
 
-    let _query = update(account_sessions.filter(filter_cond))
-        .set(deleted_at.eq(now))
-        .execute(conn)
-        .await?;
+/// Returns the query used by [`delete_sessions`].
+#[auto_type(no_type_alias)]
+pub fn delete_sessions_query(form_account_id: AccountId, form_except_token: SessionToken) -> _ {
+    let form_except_token_hash: TokenHash = form_except_token.hash();
+    let filter_cond = account_sessions::account_id
+        .eq(form_account_id)
+        .and(account_sessions::token_hash.ne(form_except_token_hash))
+        .and(account_sessions::deleted_at.is_null());
 
-    Ok(())
+    update(account_sessions::table.filter(filter_cond)).set(account_sessions::deleted_at.eq(now))
 }
 
 /// Deletes all active sessions except one.
@@ -140,20 +472,271 @@ pub async fn delete_session(
 /// - account_sessions
 pub async fn delete_sessions(
     conn: &mut AsyncPgConnection,
-    form_account_id: Uuid,
-    form_except_token_seq: Uuid,
+    form_account_id: AccountId,
+    form_except_token: SessionToken,
 ) -> DatabaseResult<()> {
-    use schema::account_sessions::dsl::*;
+    let _query = delete_sessions_query(form_account_id, form_except_token)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
 
-    let filter_cond = account_id
+/// Returns the query used by [`delete_all_sessions`].
+#[auto_type(no_type_alias)]
+pub fn delete_all_sessions_query(form_account_id: AccountId) -> _ {
+    let filter_cond = account_sessions::account_id
         .eq(form_account_id)
-        .and(token_seq.ne(form_except_token_seq))
-        .and(deleted_at.is_null());
+        .and(account_sessions::deleted_at.is_null());
+
+    update(account_sessions::table.filter(filter_cond)).set(account_sessions::deleted_at.eq(now))
+}
 
-    let _query = update(account_sessions.filter(filter_cond))
-        .set(deleted_at.eq(now))
+/// Deletes all active sessions, e.g. once the account is no longer active.
+///
+/// # Tables
+///
+/// - account_sessions
+pub async fn delete_all_sessions(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+) -> DatabaseResult<()> {
+    let _query = delete_all_sessions_query(form_account_id)
         .execute(conn)
         .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use time::Duration;
+    use uuid::Uuid;
+
+    use crate::account_permissions::{update_permissions, AccountPermissions};
+    use crate::account_sessions::*;
+    use crate::accounts::{update_account_status, AccountStatusUpdateInput};
+    use crate::testing::{AccountFactory, TestDatabase};
+    use crate::DatabaseResult;
+
+    #[test]
+    fn account_sessions_queries() {
+        let form = AccountSession {
+            region_id: "EU".to_owned(),
+            ip_address: "127.0.0.1/32".parse().unwrap(),
+            user_agent: "Mozilla/5.0".to_owned(),
+        };
+
+        let token = AccountSessionToken {
+            account_id: Uuid::nil().into(),
+            token: SessionToken::from_bytes([0; 32]),
+        };
+
+        let query = create_session_query(Uuid::nil().into(), token.token.hash(), form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "account_sessions" ("account_id", "token_hash", "region_id", "ip_address", "user_agent") VALUES ($1, $2, $3, $4, $5) -- binds: [AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925), "EU", 127.0.0.1/32, "Mozilla/5.0"]"#);
+
+        let query = find_active_session_query(token.clone());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "account_sessions"."region_id", "account_sessions"."ip_address", "account_sessions"."user_agent" FROM ("account_sessions" INNER JOIN "accounts" ON ("account_sessions"."account_id" = "accounts"."id")) WHERE (((((("account_sessions"."account_id" = $1) AND ("account_sessions"."token_hash" = $2)) AND ("account_sessions"."expired_at" > CURRENT_TIMESTAMP)) AND ("account_sessions"."deleted_at" IS NULL)) AND ("accounts"."status" = $3)) AND ("accounts"."deleted_at" IS NULL)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925), Active]"#);
+
+        let query = view_active_sessions_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "account_sessions"."region_id", "account_sessions"."ip_address", "account_sessions"."user_agent" FROM ("account_sessions" INNER JOIN "accounts" ON ("account_sessions"."account_id" = "accounts"."id")) WHERE ((((("account_sessions"."account_id" = $1) AND ("account_sessions"."expired_at" > CURRENT_TIMESTAMP)) AND ("account_sessions"."deleted_at" IS NULL)) AND ("accounts"."status" = $2)) AND ("accounts"."deleted_at" IS NULL)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000), Active]"#);
+
+        let policy = AccountSessionPolicy::default();
+        let query = validate_session_query(token.clone(), policy);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_sessions" SET "last_seen_at" = CURRENT_TIMESTAMP, "expired_at" = LEAST(CURRENT_TIMESTAMP + $1, ("account_sessions"."issued_at" + $2)) WHERE ((((("account_sessions"."account_id" = $3) AND ("account_sessions"."token_hash" = $4)) AND ("account_sessions"."expired_at" > CURRENT_TIMESTAMP)) AND ("account_sessions"."deleted_at" IS NULL)) AND EXISTS (SELECT "accounts"."id", "accounts"."display_name", "accounts"."email_address", "accounts"."password_hash", "accounts"."created_at", "accounts"."updated_at", "accounts"."deleted_at", "accounts"."last_login_at", "accounts"."status", "accounts"."status_reason", "accounts"."status_changed_at" FROM "accounts" WHERE ((("accounts"."id" = $5) AND ("accounts"."status" = $6)) AND ("accounts"."deleted_at" IS NULL)))) RETURNING "account_sessions"."region_id", "account_sessions"."ip_address", "account_sessions"."user_agent", "account_sessions"."is_mfa_satisfied", "account_sessions"."issued_at", "account_sessions"."expired_at", "account_sessions"."last_seen_at" -- binds: [PgInterval { microseconds: 604800000000, days: 0, months: 0 }, PgInterval { microseconds: 2592000000000, days: 0, months: 0 }, AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925), AccountId(00000000-0000-0000-0000-000000000000), Active]"#);
+
+        let query = refresh_session_query(token.clone(), token.token.hash(), policy);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_sessions" SET "token_hash" = $1, "last_seen_at" = CURRENT_TIMESTAMP, "expired_at" = LEAST(CURRENT_TIMESTAMP + $2, ("account_sessions"."issued_at" + $3)) WHERE ((((("account_sessions"."account_id" = $4) AND ("account_sessions"."token_hash" = $5)) AND ("account_sessions"."expired_at" > CURRENT_TIMESTAMP)) AND ("account_sessions"."deleted_at" IS NULL)) AND EXISTS (SELECT "accounts"."id", "accounts"."display_name", "accounts"."email_address", "accounts"."password_hash", "accounts"."created_at", "accounts"."updated_at", "accounts"."deleted_at", "accounts"."last_login_at", "accounts"."status", "accounts"."status_reason", "accounts"."status_changed_at" FROM "accounts" WHERE ((("accounts"."id" = $6) AND ("accounts"."status" = $7)) AND ("accounts"."deleted_at" IS NULL)))) -- binds: [TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925), PgInterval { microseconds: 604800000000, days: 0, months: 0 }, PgInterval { microseconds: 2592000000000, days: 0, months: 0 }, AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925), AccountId(00000000-0000-0000-0000-000000000000), Active]"#);
+
+        let query = authenticate_query(token.clone());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT ("account_sessions"."deleted_at" IS NOT NULL), ("account_sessions"."expired_at" <= CURRENT_TIMESTAMP), "accounts"."display_name", "accounts"."email_address", "account_sessions"."region_id", "account_sessions"."ip_address", "account_sessions"."user_agent", "account_sessions"."is_mfa_satisfied", "account_sessions"."issued_at", "account_sessions"."expired_at", "account_sessions"."last_seen_at", "account_permissions"."read_accounts", "account_permissions"."write_accounts", "account_permissions"."read_workspaces", "account_permissions"."write_workspaces", "account_permissions"."read_workflows", "account_permissions"."write_workflows" FROM (("account_sessions" INNER JOIN "accounts" ON ("account_sessions"."account_id" = "accounts"."id")) LEFT OUTER JOIN "account_permissions" ON ("account_permissions"."account_id" = "accounts"."id")) WHERE (((("account_sessions"."account_id" = $1) AND ("account_sessions"."token_hash" = $2)) AND ("accounts"."status" = $3)) AND ("accounts"."deleted_at" IS NULL)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925), Active]"#);
+
+        let query = delete_session_query(token.clone());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_sessions" SET "deleted_at" = CURRENT_TIMESTAMP WHERE ((("account_sessions"."account_id" = $1) AND ("account_sessions"."token_hash" = $2)) AND ("account_sessions"."deleted_at" IS NULL)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925)]"#);
+
+        let query = delete_sessions_query(Uuid::nil().into(), token.token);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_sessions" SET "deleted_at" = CURRENT_TIMESTAMP WHERE ((("account_sessions"."account_id" = $1) AND ("account_sessions"."token_hash" != $2)) AND ("account_sessions"."deleted_at" IS NULL)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925)]"#);
+
+        let query = delete_all_sessions_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_sessions" SET "deleted_at" = CURRENT_TIMESTAMP WHERE (("account_sessions"."account_id" = $1) AND ("account_sessions"."deleted_at" IS NULL)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+
+    #[tokio::test]
+    async fn session_lifecycle() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new()
+            .status(AccountStatus::Active)
+            .create(&mut conn)
+            .await?;
+
+        let form = AccountSession {
+            region_id: "EU".to_owned(),
+            ip_address: "127.0.0.1/32".parse().unwrap(),
+            user_agent: "Mozilla/5.0".to_owned(),
+        };
+
+        let token = create_session(&mut conn, account.id, form).await?;
+        let session = find_active_session(&mut conn, token.clone()).await?;
+        assert_eq!(session.expect("session should be active").region_id, "EU");
+        assert_eq!(view_active_sessions(&mut conn, account.id).await?.len(), 1);
+
+        // Extends the expiration up to the max lifetime.
+        let policy = AccountSessionPolicy {
+            sliding_window: Duration::days(7),
+            max_lifetime: Duration::days(1),
+        };
+
+        let session = validate_session(&mut conn, token.clone(), policy).await?;
+        let session = session.expect("session should be active");
+        assert_eq!(session.expired_at, session.issued_at + Duration::days(1));
+        assert!(session.last_seen_at >= session.issued_at);
+
+        // Rotates the token sequence and invalidates the previous one.
+        let refreshed = refresh_session(&mut conn, token.clone(), policy).await?;
+        let refreshed = refreshed.expect("session should be active");
+        assert_ne!(refreshed.token, token.token);
+        assert!(validate_session(&mut conn, token, policy).await?.is_none());
+
+        // Expires the session once the max lifetime has passed.
+        let policy = AccountSessionPolicy {
+            sliding_window: Duration::days(7),
+            max_lifetime: Duration::ZERO,
+        };
+
+        let session = validate_session(&mut conn, refreshed.clone(), policy).await?;
+        assert!(session.is_some());
+        assert!(validate_session(&mut conn, refreshed, policy)
+            .await?
+            .is_none());
+        assert!(view_active_sessions(&mut conn, account.id)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn suspended_account_sessions() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new()
+            .status(AccountStatus::Active)
+            .create(&mut conn)
+            .await?;
+
+        let form = AccountSession {
+            region_id: "EU".to_owned(),
+            ip_address: "127.0.0.1/32".parse().unwrap(),
+            user_agent: "Mozilla/5.0".to_owned(),
+        };
+
+        let token = create_session(&mut conn, account.id, form).await?;
+        let form_status = AccountStatusUpdateInput {
+            status: AccountStatus::Suspended,
+            status_reason: None,
+        };
+
+        update_account_status(&mut conn, account.id, form_status)
+            .await?
+            .unwrap();
+
+        // Rejects sessions of not active accounts.
+        let policy = AccountSessionPolicy::default();
+        assert!(validate_session(&mut conn, token.clone(), policy)
+            .await?
+            .is_none());
+        assert!(refresh_session(&mut conn, token.clone(), policy)
+            .await?
+            .is_none());
+        assert!(find_active_session(&mut conn, token.clone())
+            .await?
+            .is_none());
+        assert!(view_active_sessions(&mut conn, account.id)
+            .await?
+            .is_empty());
+
+        // Keeps sessions revoked once the account is reactivated.
+        let form_status = AccountStatusUpdateInput {
+            status: AccountStatus::Active,
+            status_reason: None,
+        };
+
+        update_account_status(&mut conn, account.id, form_status)
+            .await?
+            .unwrap();
+        assert!(validate_session(&mut conn, token.clone(), policy)
+            .await?
+            .is_none());
+        let result = authenticate(&mut conn, token).await?;
+        assert_eq!(result.unwrap_err(), AuthFailure::Revoked);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn authenticate_sessions() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new().create(&mut conn).await?;
+
+        let form = AccountSession {
+            region_id: "EU".to_owned(),
+            ip_address: "127.0.0.1/32".parse().unwrap(),
+            user_agent: "Mozilla/5.0".to_owned(),
+        };
+
+        // Rejects sessions of not active accounts.
+        let token = create_session(&mut conn, account.id, form.clone()).await?;
+        let result = authenticate(&mut conn, token.clone()).await?;
+        assert_eq!(result.unwrap_err(), AuthFailure::Unknown);
+
+        let form_status = AccountStatusUpdateInput {
+            status: AccountStatus::Active,
+            status_reason: None,
+        };
+
+        update_account_status(&mut conn, account.id, form_status)
+            .await?
+            .unwrap();
+        let context = authenticate(&mut conn, token.clone()).await?.unwrap();
+        assert_eq!(context.account_id, account.id);
+        assert_eq!(context.session.region_id, "EU");
+        assert!(!context.session.is_mfa_satisfied);
+        assert!(!context.permissions.read_accounts);
+
+        let permissions = AccountPermissions {
+            read_accounts: true,
+            ..AccountPermissions::default()
+        };
+
+        update_permissions(&mut conn, account.id, permissions).await?;
+        let context = authenticate(&mut conn, token.clone()).await?.unwrap();
+        assert!(context.permissions.read_accounts);
+
+        let unknown = AccountSessionToken {
+            account_id: account.id,
+            token: SessionToken::generate(),
+        };
+
+        let result = authenticate(&mut conn, unknown).await?;
+        assert_eq!(result.unwrap_err(), AuthFailure::Unknown);
+
+        let policy = AccountSessionPolicy {
+            sliding_window: Duration::days(7),
+            max_lifetime: Duration::ZERO,
+        };
+
+        let _ = validate_session(&mut conn, token.clone(), policy).await?;
+        let result = authenticate(&mut conn, token.clone()).await?;
+        assert_eq!(result.unwrap_err(), AuthFailure::Expired);
+
+        delete_session(&mut conn, token.clone()).await?;
+        let result = authenticate(&mut conn, token).await?;
+        assert_eq!(result.unwrap_err(), AuthFailure::Revoked);
+
+        Ok(())
+    }
+}
@@ -1,102 +1,436 @@
 //! Data layer for account tokens management.
 
+use axiston_db_schema::constraints::ConstraintViolation;
 use axiston_db_schema::enumerations::TokenAction;
+use axiston_db_schema::identifiers::AccountId;
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::account_tokens;
+use axiston_db_schema::secrets::{ActionToken, TokenHash};
 use diesel::dsl::*;
 use diesel::prelude::*;
-use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use diesel::result::Error;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 use ipnet::IpNet;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use serde_json::{json, Value};
 
-use crate::DatabaseResult;
+use crate::{jsonb, DatabaseResult, Validate};
 
-#[derive(Debug, Clone, Insertable)]
+/// `token_data` of the [`TokenAction::UpdateEmail`] token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UpdateEmailData {
+    /// New email address, applied once the token is consumed.
+    pub email_address: String,
+}
+
+/// Typed `token_data` of the action token, one per [`TokenAction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TokenPayload {
+    ActivateAccount,
+    DeactivateAccount,
+    UpdateEmail(UpdateEmailData),
+    ResetPassword,
+    PendingInvite,
+}
+
+impl TokenPayload {
+    /// Returns the action type of the payload.
+    pub fn action_type(&self) -> TokenAction {
+        match self {
+            Self::ActivateAccount => TokenAction::ActivateAccount,
+            Self::DeactivateAccount => TokenAction::DeactivateAccount,
+            Self::UpdateEmail(_) => TokenAction::UpdateEmail,
+            Self::ResetPassword => TokenAction::ResetPassword,
+            Self::PendingInvite => TokenAction::PendingInvite,
+        }
+    }
+
+    /// Returns the payload as the `token_data` column.
+    pub fn to_data(&self) -> Value {
+        match self {
+            Self::UpdateEmail(x) => json!({ "email_address": x.email_address }),
+            _ => json!({}),
+        }
+    }
+
+    /// Returns the payload of the action type from the `token_data` column.
+    pub fn from_data(action_type: TokenAction, data: Value) -> serde_json::Result<Self> {
+        let payload = match action_type {
+            TokenAction::ActivateAccount => Self::ActivateAccount,
+            TokenAction::DeactivateAccount => Self::DeactivateAccount,
+            TokenAction::UpdateEmail => Self::UpdateEmail(UpdateEmailData {
+                email_address: serde_json::from_value(data["email_address"].clone())?,
+            }),
+            TokenAction::ResetPassword => Self::ResetPassword,
+            TokenAction::PendingInvite => Self::PendingInvite,
+        };
+
+        Ok(payload)
+    }
+}
+
+/// Action token form, constructed only from the typed [`TokenPayload`].
+#[derive(Debug, Clone, Insertable)]
 #[diesel(table_name = schema::account_tokens)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct AccountTokenCreateInput {
-    pub account_id: Uuid,
-    pub action_type: TokenAction,
-    pub token_data: serde_json::Value,
-    pub ip_address: IpNet,
-    pub user_agent: String,
+    account_id: AccountId,
+    action_type: TokenAction,
+    token_data: Value,
+    ip_address: IpNet,
+    user_agent: String,
+}
+
+impl AccountTokenCreateInput {
+    /// Returns a new [`AccountTokenCreateInput`] with the typed payload.
+    pub fn new(
+        account_id: AccountId,
+        payload: &TokenPayload,
+        ip_address: IpNet,
+        user_agent: impl Into<String>,
+    ) -> Self {
+        Self {
+            account_id,
+            action_type: payload.action_type(),
+            token_data: payload.to_data(),
+            ip_address,
+            user_agent: user_agent.into(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Queryable, Selectable)]
+impl Validate for AccountTokenCreateInput {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        ConstraintViolation::AccountTokensTokenDataLimit
+            .check_length(jsonb::text_length(&self.token_data))?;
+
+        Ok(())
+    }
+}
+
+/// Plaintext action token, only its hash is stored.
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[diesel(table_name = schema::account_tokens)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct AccountTokenCreateOutput {
-    pub action_token: Uuid,
+    pub action_token: ActionToken,
+}
+
+/// Returns the query used by [`create_action_token`].
+#[auto_type(no_type_alias)]
+pub fn create_action_token_query(form: AccountTokenCreateInput, form_token_hash: TokenHash) -> _ {
+    insert_into(account_tokens::table)
+        .values((account_tokens::token_hash.eq(form_token_hash), form))
 }
 
 /// Creates and returns the new action token.
 ///
+/// The token is generated by the client and returned only once,
+/// as only its hash is stored.
+///
 /// # Tables
 ///
-/// - account_emails
+/// - account_tokens
 pub async fn create_action_token(
     conn: &mut AsyncPgConnection,
     form: AccountTokenCreateInput,
 ) -> DatabaseResult<AccountTokenCreateOutput> {
-    use schema::account_tokens::dsl::*;
-
-    let query = insert_into(account_tokens)
-        .values(form)
-        .returning(action_token)
-        .get_result(conn)
+    let action_token = ActionToken::generate();
+    let _query = create_action_token_query(form, action_token.hash())
+        .execute(conn)
         .await?;
 
-    Ok(AccountTokenCreateOutput {
-        action_token: query,
-    })
+    Ok(AccountTokenCreateOutput { action_token })
 }
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct AccountTokenViewInput {
-    pub account_id: Uuid,
-    pub action_token: Uuid,
+    pub account_id: AccountId,
+    pub action_token: ActionToken,
 }
 
-#[derive(Debug, Clone, Queryable, Selectable)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[diesel(table_name = schema::account_tokens)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct AccountTokenViewOutput {
-    pub action_type: TokenAction,
-    pub token_data: serde_json::Value,
+    pub payload: TokenPayload,
     pub ip_address: IpNet,
     pub user_agent: String,
 }
 
-/// Flags the action token as used and returns the action data.
+/// Negative result of [`consume_action_token`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "errors do nothing unless you use them"]
+pub enum TokenFailure {
+    /// No unused token of the expected action type matches.
+    #[error("unknown action token")]
+    Unknown,
+    /// The token has expired.
+    #[error("expired action token")]
+    Expired,
+    /// The token was revoked, e.g. superseded by a newer one.
+    #[error("revoked action token")]
+    Revoked,
+}
+
+/// Returns the query used by [`consume_action_token`].
+#[auto_type(no_type_alias)]
+pub fn consume_action_token_query(form: AccountTokenViewInput, form_action_type: TokenAction) -> _ {
+    let form_account_id: AccountId = form.account_id;
+    let form_token_hash: TokenHash = form.action_token.hash();
+    let filter_cond = account_tokens::account_id
+        .eq(form_account_id)
+        .and(account_tokens::token_hash.eq(form_token_hash))
+        .and(account_tokens::action_type.eq(form_action_type))
+        .and(account_tokens::used_at.is_null())
+        .and(account_tokens::deleted_at.is_null())
+        .and(account_tokens::expired_at.gt(now));
+
+    update(account_tokens::table.filter(filter_cond))
+        .set(account_tokens::used_at.eq(now))
+        .returning((
+            account_tokens::action_type,
+            account_tokens::token_data,
+            account_tokens::ip_address,
+            account_tokens::user_agent,
+        ))
+}
+
+/// Returns the query used by [`consume_action_token`] to report revoked or expired tokens.
+#[auto_type(no_type_alias)]
+pub fn find_rejected_action_token_query(
+    form: AccountTokenViewInput,
+    form_action_type: TokenAction,
+) -> _ {
+    let form_account_id: AccountId = form.account_id;
+    let form_token_hash: TokenHash = form.action_token.hash();
+    let filter_cond = account_tokens::account_id
+        .eq(form_account_id)
+        .and(account_tokens::token_hash.eq(form_token_hash))
+        .and(account_tokens::action_type.eq(form_action_type))
+        .and(account_tokens::used_at.is_null());
+
+    account_tokens::table.filter(filter_cond).select((
+        account_tokens::deleted_at.is_not_null(),
+        account_tokens::expired_at.le(now),
+    ))
+}
+
+/// Flags the action token of the expected type as used and returns the action data.
+///
+/// Also revokes all other unused tokens of the same type for the account,
+/// so only the most recently consumed token takes effect.
 ///
 /// # Tables
 ///
-/// - account_emails
+/// - account_tokens
 pub async fn consume_action_token(
     conn: &mut AsyncPgConnection,
     form: AccountTokenViewInput,
-) -> DatabaseResult<AccountTokenViewOutput> {
-    use schema::account_tokens::dsl::*;
-
-    let filter_cond = account_id
-        .eq(form.account_id)
-        .and(action_token.eq(form.action_token))
-        .and(used_at.is_null());
-
-    let query = update(account_tokens.filter(filter_cond))
-        .set(used_at.eq(now))
-        .returning((action_type, token_data, ip_address, user_agent))
-        .get_result(conn)
+    form_action_type: TokenAction,
+) -> DatabaseResult<Result<AccountTokenViewOutput, TokenFailure>> {
+    conn.transaction(|conn| {
+        async move {
+            let form_account_id = form.account_id;
+            let query = consume_action_token_query(form.clone(), form_action_type)
+                .get_result(conn)
+                .await
+                .optional()?;
+
+            let Some((action_type, token_data, ip_address, user_agent)) = query else {
+                let rejected = find_rejected_action_token_query(form, form_action_type)
+                    .get_result(conn)
+                    .await
+                    .optional()?;
+
+                return match rejected {
+                    Some((true, _)) => Ok(Err(TokenFailure::Revoked)),
+                    Some((false, true)) => Ok(Err(TokenFailure::Expired)),
+                    _ => Ok(Err(TokenFailure::Unknown)),
+                };
+            };
+
+            let payload = TokenPayload::from_data(action_type, token_data)
+                .map_err(|x| Error::DeserializationError(x.into()))?;
+            invalidate_action_tokens(conn, form_account_id, form_action_type).await?;
+
+            Ok(Ok(AccountTokenViewOutput {
+                payload,
+                ip_address,
+                user_agent,
+            }))
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Returns the query used by [`invalidate_action_tokens`].
+#[auto_type(no_type_alias)]
+pub fn invalidate_action_tokens_query(
+    form_account_id: AccountId,
+    form_action_type: TokenAction,
+) -> _ {
+    let filter_cond = account_tokens::account_id
+        .eq(form_account_id)
+        .and(account_tokens::action_type.eq(form_action_type))
+        .and(account_tokens::used_at.is_null())
+        .and(account_tokens::deleted_at.is_null())
+        .and(account_tokens::expired_at.gt(now));
+
+    update(account_tokens::table.filter(filter_cond)).set(account_tokens::deleted_at.eq(now))
+}
+
+/// Revokes all unused action tokens of the type for the account.
+///
+/// # Tables
+///
+/// - account_tokens
+pub async fn invalidate_action_tokens(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+    form_action_type: TokenAction,
+) -> DatabaseResult<()> {
+    let _query = invalidate_action_tokens_query(form_account_id, form_action_type)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the query used by [`invalidate_all_action_tokens`].
+#[auto_type(no_type_alias)]
+pub fn invalidate_all_action_tokens_query(form_account_id: AccountId) -> _ {
+    let filter_cond = account_tokens::account_id
+        .eq(form_account_id)
+        .and(account_tokens::used_at.is_null())
+        .and(account_tokens::deleted_at.is_null())
+        .and(account_tokens::expired_at.gt(now));
+
+    update(account_tokens::table.filter(filter_cond)).set(account_tokens::deleted_at.eq(now))
+}
+
+/// Revokes all unused action tokens of the account.
+///
+/// # Tables
+///
+/// - account_tokens
+pub async fn invalidate_all_action_tokens(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+) -> DatabaseResult<()> {
+    let _query = invalidate_all_action_tokens_query(form_account_id)
+        .execute(conn)
         .await?;
 
-    Ok(query)
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use axiston_db_schema::enumerations::TokenAction;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use crate::account_tokens::*;
+    use crate::testing::{AccountFactory, TestDatabase};
+    use crate::DatabaseResult;
+
+    #[test]
+    fn account_tokens_queries() {
+        let ip_address = "127.0.0.1/32".parse().unwrap();
+        let payload = TokenPayload::ActivateAccount;
+        let form =
+            AccountTokenCreateInput::new(Uuid::nil().into(), &payload, ip_address, "Mozilla/5.0");
+
+        let action_token = ActionToken::from_bytes([0; 32]);
+        let query = create_action_token_query(form, action_token.hash());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "account_tokens" ("token_hash", "account_id", "action_type", "token_data", "ip_address", "user_agent") VALUES ($1, $2, $3, $4, $5, $6) -- binds: [TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925), AccountId(00000000-0000-0000-0000-000000000000), ActivateAccount, Object {}, 127.0.0.1/32, "Mozilla/5.0"]"#);
+
+        let form = AccountTokenViewInput {
+            account_id: Uuid::nil().into(),
+            action_token,
+        };
+
+        let query = consume_action_token_query(form.clone(), TokenAction::UpdateEmail);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_tokens" SET "used_at" = CURRENT_TIMESTAMP WHERE (((((("account_tokens"."account_id" = $1) AND ("account_tokens"."token_hash" = $2)) AND ("account_tokens"."action_type" = $3)) AND ("account_tokens"."used_at" IS NULL)) AND ("account_tokens"."deleted_at" IS NULL)) AND ("account_tokens"."expired_at" > CURRENT_TIMESTAMP)) RETURNING "account_tokens"."action_type", "account_tokens"."token_data", "account_tokens"."ip_address", "account_tokens"."user_agent" -- binds: [AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925), UpdateEmail]"#);
+
+        let query = find_rejected_action_token_query(form, TokenAction::UpdateEmail);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT ("account_tokens"."deleted_at" IS NOT NULL), ("account_tokens"."expired_at" <= CURRENT_TIMESTAMP) FROM "account_tokens" WHERE (((("account_tokens"."account_id" = $1) AND ("account_tokens"."token_hash" = $2)) AND ("account_tokens"."action_type" = $3)) AND ("account_tokens"."used_at" IS NULL)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000), TokenHash(66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925), UpdateEmail]"#);
+
+        let query = invalidate_action_tokens_query(Uuid::nil().into(), TokenAction::UpdateEmail);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_tokens" SET "deleted_at" = CURRENT_TIMESTAMP WHERE ((((("account_tokens"."account_id" = $1) AND ("account_tokens"."action_type" = $2)) AND ("account_tokens"."used_at" IS NULL)) AND ("account_tokens"."deleted_at" IS NULL)) AND ("account_tokens"."expired_at" > CURRENT_TIMESTAMP)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000), UpdateEmail]"#);
+
+        let query = invalidate_all_action_tokens_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "account_tokens" SET "deleted_at" = CURRENT_TIMESTAMP WHERE (((("account_tokens"."account_id" = $1) AND ("account_tokens"."used_at" IS NULL)) AND ("account_tokens"."deleted_at" IS NULL)) AND ("account_tokens"."expired_at" > CURRENT_TIMESTAMP)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+
+    #[test]
+    fn token_payloads() {
+        let payload = TokenPayload::UpdateEmail(UpdateEmailData {
+            email_address: "account@example.com".to_owned(),
+        });
+
+        let data = payload.to_data();
+        assert_eq!(data, json!({ "email_address": "account@example.com" }));
+        let parsed = TokenPayload::from_data(TokenAction::UpdateEmail, data).unwrap();
+        assert_eq!(parsed, payload);
+
+        assert!(TokenPayload::from_data(TokenAction::UpdateEmail, json!({})).is_err());
+        let parsed = TokenPayload::from_data(TokenAction::ResetPassword, json!({})).unwrap();
+        assert_eq!(parsed.action_type(), TokenAction::ResetPassword);
+    }
+
+    #[tokio::test]
+    async fn consume_action_tokens() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new().create(&mut conn).await?;
+
+        let payload = TokenPayload::UpdateEmail(UpdateEmailData {
+            email_address: "account@example.com".to_owned(),
+        });
+
+        let ip_address = "127.0.0.1/32".parse().unwrap();
+        let form = AccountTokenCreateInput::new(account.id, &payload, ip_address, "Mozilla/5.0");
+        let superseded = create_action_token(&mut conn, form.clone()).await?;
+        let consumed = create_action_token(&mut conn, form).await?;
+
+        let form = AccountTokenViewInput {
+            account_id: account.id,
+            action_token: consumed.action_token,
+        };
+
+        // Rejects tokens of another action type.
+        let result = consume_action_token(&mut conn, form.clone(), TokenAction::ResetPassword);
+        assert_eq!(result.await?.unwrap_err(), TokenFailure::Unknown);
+
+        let result = consume_action_token(&mut conn, form.clone(), TokenAction::UpdateEmail);
+        assert_eq!(result.await?.unwrap().payload, payload);
+
+        let result = consume_action_token(&mut conn, form, TokenAction::UpdateEmail);
+        assert_eq!(result.await?.unwrap_err(), TokenFailure::Unknown);
+
+        // Revokes all other tokens of the same action type.
+        let form = AccountTokenViewInput {
+            account_id: account.id,
+            action_token: superseded.action_token,
+        };
+
+        let result = consume_action_token(&mut conn, form, TokenAction::UpdateEmail);
+        assert_eq!(result.await?.unwrap_err(), TokenFailure::Revoked);
+
+        Ok(())
+    }
 }
@@ -1,15 +1,27 @@
 //! Data layer for account management.
 
+use std::borrow::Cow;
+
+use axiston_db_schema::constraints::ConstraintViolation;
+use axiston_db_schema::enumerations::{AccountStatus, TokenAction};
+use axiston_db_schema::identifiers::AccountId;
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::accounts;
 use diesel::dsl::*;
+use diesel::pg::Pg;
 use diesel::prelude::*;
-use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use diesel::result::Error;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
-use uuid::Uuid;
 
-use crate::DatabaseResult;
+use crate::account_sessions::delete_all_sessions_query;
+use crate::account_tokens::{
+    consume_action_token, invalidate_all_action_tokens_query, AccountTokenViewInput, TokenFailure,
+};
+use crate::{DatabaseResult, Validate};
 
 #[derive(Debug, Clone, Insertable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -17,9 +29,43 @@ use crate::DatabaseResult;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct AccountCreateInput<'a> {
-    pub display_name: &'a str,
-    pub email_address: &'a str,
-    pub password_hash: &'a str,
+    pub display_name: Cow<'a, str>,
+    pub email_address: Cow<'a, str>,
+    pub password_hash: Cow<'a, str>,
+}
+
+/// Returns the email address trimmed and lower-cased, as stored in the database.
+///
+/// Only ASCII whitespace and letters are normalized, matching `btrim` and `lower`
+/// under the `C` collation in the migration, see `accounts_email_address_idx`.
+pub fn normalize_email(email_address: &str) -> Cow<'_, str> {
+    let email_address = email_address.trim_ascii();
+    if email_address.bytes().any(|x| x.is_ascii_uppercase()) {
+        Cow::Owned(email_address.to_ascii_lowercase())
+    } else {
+        Cow::Borrowed(email_address)
+    }
+}
+
+impl AccountCreateInput<'_> {
+    /// Normalizes the email address, see [`normalize_email`].
+    pub fn normalize(&mut self) {
+        let email_address = normalize_email(&self.email_address);
+        if email_address != self.email_address {
+            self.email_address = Cow::Owned(email_address.into_owned());
+        }
+    }
+}
+
+impl Validate for AccountCreateInput<'_> {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        ConstraintViolation::AccountsNonEmptyDisplayName.check_text(&self.display_name)?;
+        let email_address = normalize_email(&self.email_address);
+        ConstraintViolation::AccountsNonEmptyEmailAddress.check_text(&email_address)?;
+        ConstraintViolation::AccountsNonEmptyPasswordHash.check_text(&self.password_hash)?;
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Queryable)]
@@ -28,15 +74,28 @@ pub struct AccountCreateInput<'a> {
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct AccountCreateOutput {
-    pub id: Uuid,
+    pub id: AccountId,
 
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
     pub deleted_at: Option<OffsetDateTime>,
 }
 
+/// Returns the query used by [`create_account`].
+#[auto_type(no_type_alias)]
+pub fn create_account_query<'a>(form: &'a AccountCreateInput<'a>) -> _ {
+    insert_into(accounts::table).values(form).returning((
+        accounts::id,
+        accounts::created_at,
+        accounts::updated_at,
+        accounts::deleted_at,
+    ))
+}
+
 /// Creates the new account and returns its ID.
 ///
+/// The email address is normalized, see [`normalize_email`].
+///
 /// # Tables
 ///
 /// - accounts
@@ -44,14 +103,10 @@ pub async fn create_account(
     conn: &mut AsyncPgConnection,
     form: &AccountCreateInput<'_>,
 ) -> DatabaseResult<AccountCreateOutput> {
-    use schema::accounts::dsl::*;
-
-    let query = insert_into(accounts)
-        .values(form)
-        .returning((id, created_at, updated_at, deleted_at))
-        .get_result(conn)
-        .await?;
+    let mut form = form.clone();
+    form.normalize();
 
+    let query = create_account_query(&form).get_result(conn).await?;
     Ok(query)
 }
 
@@ -64,13 +119,26 @@ pub struct AccountViewOutput {
     pub display_name: String,
     pub email_address: String,
     pub password_hash: String,
-    pub is_activated: bool,
+
+    pub status: AccountStatus,
+    pub status_reason: Option<String>,
+    pub status_changed_at: OffsetDateTime,
 
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
     pub deleted_at: Option<OffsetDateTime>,
 }
 
+/// Returns the query used by [`view_account`].
+#[auto_type(no_type_alias)]
+pub fn view_account_query(form_account_id: AccountId) -> _ {
+    let filter_cond = accounts::id
+        .eq(form_account_id)
+        .and(accounts::deleted_at.is_null());
+    let selection: AsSelect<AccountViewOutput, Pg> = AccountViewOutput::as_select();
+    accounts::table.filter(filter_cond).select(selection)
+}
+
 /// Returns the account data by its ID.
 ///
 /// # Tables
@@ -78,53 +146,361 @@ pub struct AccountViewOutput {
 /// - accounts
 pub async fn view_account(
     conn: &mut AsyncPgConnection,
-    form_account_id: Uuid,
+    form_account_id: AccountId,
 ) -> DatabaseResult<AccountViewOutput> {
-    use schema::accounts::dsl::*;
+    let query = view_account_query(form_account_id).get_result(conn).await?;
 
-    let filter_cond = id.eq(form_account_id).and(deleted_at.is_null());
-    let query = accounts
-        .filter(filter_cond)
-        .select(AccountViewOutput::as_select())
+    Ok(query)
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[diesel(table_name = schema::accounts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[must_use = "forms do nothing unless you use them"]
+pub struct AccountCredentialsOutput {
+    pub id: AccountId,
+    pub password_hash: String,
+    pub status: AccountStatus,
+}
+
+/// Returns the query used by [`find_account_by_email`].
+#[auto_type(no_type_alias)]
+pub fn find_account_by_email_query<'a>(form_email_address: &'a str) -> _ {
+    let form_email_address: Cow<'a, str> = normalize_email(form_email_address);
+    let filter_cond = accounts::email_address
+        .eq(form_email_address)
+        .and(accounts::deleted_at.is_null());
+
+    let selection: AsSelect<AccountCredentialsOutput, Pg> = AccountCredentialsOutput::as_select();
+    accounts::table.filter(filter_cond).select(selection)
+}
+
+/// Returns credentials of the account by its normalized email address, see [`normalize_email`].
+///
+/// Returns `None` if no account matches the email address, or if it was deleted.
+/// Accounts of any status are returned, so callers can tell them apart.
+///
+/// # Tables
+///
+/// - accounts
+pub async fn find_account_by_email(
+    conn: &mut AsyncPgConnection,
+    form_email_address: &str,
+) -> DatabaseResult<Option<AccountCredentialsOutput>> {
+    let query = find_account_by_email_query(form_email_address)
         .get_result(conn)
-        .await?;
+        .await
+        .optional()?;
 
     Ok(query)
 }
 
+/// Returns the query used by [`mark_login`].
+#[auto_type(no_type_alias)]
+pub fn mark_login_query(form_account_id: AccountId) -> _ {
+    let active: AccountStatus = AccountStatus::Active;
+    let filter_cond = accounts::id
+        .eq(form_account_id)
+        .and(accounts::status.eq(active))
+        .and(accounts::deleted_at.is_null());
+    update(accounts::table.filter(filter_cond)).set(accounts::last_login_at.eq(now))
+}
+
+/// Sets the last sign-in of the active account to the current time.
+///
+/// Fails with [`Error::NotFound`] if the account is not active or was deleted.
+///
+/// # Tables
+///
+/// - accounts
+pub async fn mark_login(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+) -> DatabaseResult<()> {
+    let query = mark_login_query(form_account_id).execute(conn).await?;
+    if query == 0 {
+        return Err(Error::NotFound.into());
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Clone, AsChangeset)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[diesel(table_name = schema::accounts)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct AccountUpdateInput<'a> {
-    pub display_name: Option<&'a str>,
-    pub email_address: Option<&'a str>,
-    pub password_hash: Option<&'a str>,
-    pub is_activated: Option<bool>,
+    pub display_name: Option<Cow<'a, str>>,
+    pub email_address: Option<Cow<'a, str>>,
+    pub password_hash: Option<Cow<'a, str>>,
+}
+
+impl AccountUpdateInput<'_> {
+    /// Normalizes the email address, if any, see [`normalize_email`].
+    pub fn normalize(&mut self) {
+        let Some(email_address) = &self.email_address else {
+            return;
+        };
+
+        let normalized = normalize_email(email_address);
+        if normalized != *email_address {
+            self.email_address = Some(Cow::Owned(normalized.into_owned()));
+        }
+    }
+}
+
+impl Validate for AccountUpdateInput<'_> {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        if let Some(x) = &self.display_name {
+            ConstraintViolation::AccountsNonEmptyDisplayName.check_text(x)?;
+        }
+        if let Some(x) = &self.email_address {
+            ConstraintViolation::AccountsNonEmptyEmailAddress.check_text(&normalize_email(x))?;
+        }
+        if let Some(x) = &self.password_hash {
+            ConstraintViolation::AccountsNonEmptyPasswordHash.check_text(x)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the query used by [`update_account`].
+#[auto_type(no_type_alias)]
+pub fn update_account_query<'a>(form_account_id: AccountId, form: AccountUpdateInput<'a>) -> _ {
+    let filter_cond = accounts::id
+        .eq(form_account_id)
+        .and(accounts::deleted_at.is_null());
+    update(accounts::table.filter(filter_cond)).set(form)
 }
 
 /// Updates the account with provided data.
 ///
+/// The email address is normalized, see [`normalize_email`].
+///
 /// # Tables
 ///
 /// - accounts
 pub async fn update_account(
     conn: &mut AsyncPgConnection,
-    form_account_id: Uuid,
-    form: AccountUpdateInput<'_>,
+    form_account_id: AccountId,
+    mut form: AccountUpdateInput<'_>,
 ) -> DatabaseResult<()> {
-    use schema::accounts::dsl::*;
+    form.normalize();
+    let _query = update_account_query(form_account_id, form)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, AsChangeset)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[diesel(table_name = schema::accounts, treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[must_use = "forms do nothing unless you use them"]
+pub struct AccountStatusUpdateInput<'a> {
+    pub status: AccountStatus,
+    pub status_reason: Option<Cow<'a, str>>,
+}
+
+impl Validate for AccountStatusUpdateInput<'_> {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        if let Some(x) = &self.status_reason {
+            ConstraintViolation::AccountsStatusReasonLimit.check_text(x)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Negative result of [`update_account_status`] and [`confirm_account_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "errors do nothing unless you use them"]
+pub enum StatusFailure {
+    /// No account matches the ID, or it was deleted.
+    #[error("unknown account")]
+    Unknown,
+    /// The current status of the account can't transition into the new one.
+    #[error("forbidden status transition from {0:?}")]
+    Forbidden(AccountStatus),
+    /// The action token can't be consumed.
+    #[error(transparent)]
+    Token(#[from] TokenFailure),
+}
+
+/// Returns the query used by [`update_account_status`] to lock the account.
+#[auto_type(no_type_alias)]
+pub fn view_account_status_query(form_account_id: AccountId) -> _ {
+    let filter_cond = accounts::id
+        .eq(form_account_id)
+        .and(accounts::deleted_at.is_null());
+    accounts::table
+        .filter(filter_cond)
+        .select(accounts::status)
+        .for_update()
+}
+
+/// Returns the query used by [`update_account_status`].
+///
+/// Does not validate the transition, see [`AccountStatus::can_transition_to`].
+#[auto_type(no_type_alias)]
+pub fn update_account_status_query<'a>(
+    form_account_id: AccountId,
+    form: AccountStatusUpdateInput<'a>,
+) -> _ {
+    let filter_cond = accounts::id
+        .eq(form_account_id)
+        .and(accounts::deleted_at.is_null());
+    update(accounts::table.filter(filter_cond)).set((form, accounts::status_changed_at.eq(now)))
+}
+
+/// Locks the account and checks it may transition from its current status.
+///
+/// Returns the current status of the account.
+async fn lock_account_status(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+    is_allowed: impl FnOnce(AccountStatus) -> bool,
+) -> DatabaseResult<Result<AccountStatus, StatusFailure>> {
+    let query: Option<AccountStatus> = view_account_status_query(form_account_id)
+        .get_result(conn)
+        .await
+        .optional()?;
+
+    let Some(status) = query else {
+        return Ok(Err(StatusFailure::Unknown));
+    };
+
+    if !is_allowed(status) {
+        return Ok(Err(StatusFailure::Forbidden(status)));
+    }
+
+    Ok(Ok(status))
+}
 
-    let filter_cond = id.eq(form_account_id).and(deleted_at.is_null());
-    let _query = update(accounts.filter(filter_cond))
-        .set(form)
+/// Updates the status of the locked account, see [`lock_account_status`].
+///
+/// Deletes all sessions and revokes all unused action tokens of the account
+/// once it's no longer active, so they can't be used again if the account
+/// is reactivated.
+async fn apply_account_status(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+    status: AccountStatus,
+    form: AccountStatusUpdateInput<'_>,
+) -> DatabaseResult<()> {
+    let is_deactivated = status.is_active() && !form.status.is_active();
+    let _query = update_account_status_query(form_account_id, form)
         .execute(conn)
         .await?;
 
+    if is_deactivated {
+        let _query = delete_all_sessions_query(form_account_id)
+            .execute(conn)
+            .await?;
+        let _query = invalidate_all_action_tokens_query(form_account_id)
+            .execute(conn)
+            .await?;
+    }
+
     Ok(())
 }
 
+/// Transitions the account into the new status, replacing the reason.
+///
+/// Fails with [`StatusFailure::Forbidden`] if the current status can't
+/// transition into the new one, see [`AccountStatus::can_transition_to`].
+/// Deletes all sessions and revokes all unused action tokens of the account
+/// if it's no longer active.
+///
+/// # Tables
+///
+/// - accounts
+/// - account_sessions
+/// - account_tokens
+pub async fn update_account_status(
+    conn: &mut AsyncPgConnection,
+    form_account_id: AccountId,
+    form: AccountStatusUpdateInput<'_>,
+) -> DatabaseResult<Result<(), StatusFailure>> {
+    conn.transaction(|conn| {
+        async move {
+            let is_allowed = |x: AccountStatus| x.can_transition_to(form.status);
+            let status = match lock_account_status(conn, form_account_id, is_allowed).await? {
+                Ok(status) => status,
+                Err(failure) => return Ok(Err(failure)),
+            };
+
+            apply_account_status(conn, form_account_id, status, form).await?;
+            Ok(Ok(()))
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Consumes the action token and transitions the account into its status,
+/// see [`TokenAction::account_status`].
+///
+/// Only the transitions of [`TokenAction::can_transition_from`] are allowed,
+/// others are reserved for [`update_account_status`]. The transition is checked
+/// before the token is consumed, so the token remains unused if it fails
+/// with [`StatusFailure::Forbidden`].
+/// Fails with [`TokenFailure::Unknown`] for action types without a status.
+///
+/// # Tables
+///
+/// - accounts
+/// - account_tokens
+/// - account_sessions
+pub async fn confirm_account_status(
+    conn: &mut AsyncPgConnection,
+    form: AccountTokenViewInput,
+    form_action_type: TokenAction,
+) -> DatabaseResult<Result<(), StatusFailure>> {
+    let Some(form_status) = form_action_type.account_status() else {
+        return Ok(Err(StatusFailure::Token(TokenFailure::Unknown)));
+    };
+
+    conn.transaction(|conn| {
+        async move {
+            let form_account_id = form.account_id;
+            let is_allowed = |x| form_action_type.can_transition_from(x);
+            let status = match lock_account_status(conn, form_account_id, is_allowed).await? {
+                Ok(status) => status,
+                Err(failure) => return Ok(Err(failure)),
+            };
+
+            if let Err(failure) = consume_action_token(conn, form, form_action_type).await? {
+                return Ok(Err(StatusFailure::Token(failure)));
+            }
+
+            let form = AccountStatusUpdateInput {
+                status: form_status,
+                status_reason: None,
+            };
+
+            apply_account_status(conn, form_account_id, status, form).await?;
+            Ok(Ok(()))
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Returns the query used by [`delete_account`].
+#[auto_type(no_type_alias)]
+pub fn delete_account_query(form_account_id: AccountId) -> _ {
+    let filter_cond = accounts::id
+        .eq(form_account_id)
+        .and(accounts::deleted_at.is_null());
+    update(accounts::table.filter(filter_cond)).set(accounts::deleted_at.eq(now))
+}
+
 /// Flags the account as deleted.
 ///
 /// # Tables
@@ -132,15 +508,348 @@ pub async fn update_account(
 /// - accounts
 pub async fn delete_account(
     conn: &mut AsyncPgConnection,
-    form_account_id: Uuid,
+    form_account_id: AccountId,
 ) -> DatabaseResult<()> {
-    use schema::accounts::dsl::*;
-
-    let filter_cond = id.eq(form_account_id).and(deleted_at.is_null());
-    let _query = update(accounts.filter(filter_cond))
-        .set(deleted_at.eq(now))
-        .execute(conn)
-        .await?;
+    let _query = delete_account_query(form_account_id).execute(conn).await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use axiston_db_schema::schema::account_sessions;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use uuid::Uuid;
+
+    use crate::account_sessions::{create_session, AccountSession};
+    use crate::account_tokens::{create_action_token, AccountTokenCreateInput, TokenPayload};
+    use crate::accounts::*;
+    use crate::testing::{AccountFactory, TestDatabase};
+    use crate::DatabaseError;
+
+    #[test]
+    fn account_queries() {
+        let form = AccountCreateInput {
+            display_name: "Account".into(),
+            email_address: "account@example.com".into(),
+            password_hash: "hash".into(),
+        };
+
+        let query = create_account_query(&form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "accounts" ("display_name", "email_address", "password_hash") VALUES ($1, $2, $3) RETURNING "accounts"."id", "accounts"."created_at", "accounts"."updated_at", "accounts"."deleted_at" -- binds: ["Account", "account@example.com", "hash"]"#);
+
+        let query = view_account_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "accounts"."display_name", "accounts"."email_address", "accounts"."password_hash", "accounts"."status", "accounts"."status_reason", "accounts"."status_changed_at", "accounts"."created_at", "accounts"."updated_at", "accounts"."deleted_at" FROM "accounts" WHERE (("accounts"."id" = $1) AND ("accounts"."deleted_at" IS NULL)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = find_account_by_email_query("Account@Example.com");
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "accounts"."id", "accounts"."password_hash", "accounts"."status" FROM "accounts" WHERE (("accounts"."email_address" = $1) AND ("accounts"."deleted_at" IS NULL)) -- binds: ["account@example.com"]"#);
+
+        let query = mark_login_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "accounts" SET "last_login_at" = CURRENT_TIMESTAMP WHERE ((("accounts"."id" = $1) AND ("accounts"."status" = $2)) AND ("accounts"."deleted_at" IS NULL)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000), Active]"#);
+
+        let form = AccountUpdateInput {
+            display_name: Some("Account".into()),
+            email_address: None,
+            password_hash: None,
+        };
+
+        let query = update_account_query(Uuid::nil().into(), form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "accounts" SET "display_name" = $1 WHERE (("accounts"."id" = $2) AND ("accounts"."deleted_at" IS NULL)) -- binds: ["Account", AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let form = AccountStatusUpdateInput {
+            status: AccountStatus::Suspended,
+            status_reason: Some("Suspicious activity".into()),
+        };
+
+        let query = view_account_status_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "accounts"."status" FROM "accounts" WHERE (("accounts"."id" = $1) AND ("accounts"."deleted_at" IS NULL)) FOR UPDATE -- binds: [AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = update_account_status_query(Uuid::nil().into(), form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "accounts" SET "status" = $1, "status_reason" = $2, "status_changed_at" = CURRENT_TIMESTAMP WHERE (("accounts"."id" = $3) AND ("accounts"."deleted_at" IS NULL)) -- binds: [Suspended, Some("Suspicious activity"), AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = delete_account_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "accounts" SET "deleted_at" = CURRENT_TIMESTAMP WHERE (("accounts"."id" = $1) AND ("accounts"."deleted_at" IS NULL)) -- binds: [AccountId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+
+    #[test]
+    fn normalize_emails() {
+        assert!(matches!(normalize_email("a@example.com"), Cow::Borrowed(_)));
+        assert_eq!(normalize_email(" A@Example.com\n"), "a@example.com");
+        // Only ASCII letters are lower-cased, like `lower` under the `C` collation.
+        assert_eq!(
+            normalize_email("\tÄccount@Exämple.COM "),
+            "Äccount@exämple.com"
+        );
+
+        let mut form = AccountUpdateInput {
+            display_name: None,
+            email_address: Some("A@Example.com".into()),
+            password_hash: None,
+        };
+
+        form.normalize();
+        assert_eq!(form.email_address.as_deref(), Some("a@example.com"));
+    }
+
+    #[tokio::test]
+    async fn find_accounts_by_email() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+
+        let account = AccountFactory::new()
+            .email_address("Account@Example.com")
+            .create(&mut conn)
+            .await?;
+
+        let credentials = find_account_by_email(&mut conn, "account@EXAMPLE.com").await?;
+        let credentials = credentials.expect("should match case-insensitively");
+        assert_eq!(credentials.id, account.id);
+        assert_eq!(credentials.status, AccountStatus::Pending);
+
+        let padded = find_account_by_email(&mut conn, " ACCOUNT@example.com\n").await?;
+        let padded = padded.expect("should match with surrounding whitespace");
+        assert_eq!(padded.id, account.id);
+
+        let non_ascii = AccountFactory::new()
+            .email_address("\tÄccount@Exämple.com ")
+            .create(&mut conn)
+            .await?;
+
+        let found = find_account_by_email(&mut conn, " Äccount@EXäMPLE.com").await?;
+        let found = found.expect("should match non-ASCII email addresses");
+        assert_eq!(found.id, non_ascii.id);
+
+        // Non-ASCII letters are not lower-cased.
+        let found = find_account_by_email(&mut conn, "äccount@exämple.com").await?;
+        assert!(found.is_none());
+
+        let view = view_account(&mut conn, account.id).await?;
+        assert_eq!(credentials.password_hash, view.password_hash);
+
+        // Only active accounts may sign in.
+        let result = mark_login(&mut conn, account.id).await;
+        assert!(matches!(result, Err(DatabaseError::Query(Error::NotFound))));
+        let query = accounts::table
+            .find(account.id)
+            .select(accounts::last_login_at)
+            .get_result::<Option<OffsetDateTime>>(&mut conn)
+            .await?;
+        assert!(query.is_none());
+
+        let form = AccountStatusUpdateInput {
+            status: AccountStatus::Active,
+            status_reason: None,
+        };
+
+        update_account_status(&mut conn, account.id, form)
+            .await?
+            .unwrap();
+        mark_login(&mut conn, account.id).await?;
+        let query = accounts::table
+            .find(account.id)
+            .select(accounts::last_login_at)
+            .get_result::<Option<OffsetDateTime>>(&mut conn)
+            .await?;
+        assert!(query.is_some());
+
+        delete_account(&mut conn, account.id).await?;
+        let credentials = find_account_by_email(&mut conn, "account@example.com").await?;
+        assert!(credentials.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn account_status_transitions() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new().create(&mut conn).await?;
+
+        let form = AccountStatusUpdateInput {
+            status: AccountStatus::Suspended,
+            status_reason: Some("Suspicious activity".into()),
+        };
+
+        let result = update_account_status(&mut conn, account.id, form.clone()).await?;
+        assert_eq!(
+            result,
+            Err(StatusFailure::Forbidden(AccountStatus::Pending))
+        );
+
+        let activate = AccountStatusUpdateInput {
+            status: AccountStatus::Active,
+            status_reason: None,
+        };
+
+        update_account_status(&mut conn, account.id, activate.clone())
+            .await?
+            .unwrap();
+        update_account_status(&mut conn, account.id, form)
+            .await?
+            .unwrap();
+
+        let view = view_account(&mut conn, account.id).await?;
+        assert_eq!(view.status, AccountStatus::Suspended);
+        assert_eq!(view.status_reason.as_deref(), Some("Suspicious activity"));
+        assert!(view.status_changed_at >= view.created_at);
+
+        // Replaces the reason of the previous status.
+        update_account_status(&mut conn, account.id, activate.clone())
+            .await?
+            .unwrap();
+        let view = view_account(&mut conn, account.id).await?;
+        assert_eq!(view.status, AccountStatus::Active);
+        assert_eq!(view.status_reason, None);
+
+        delete_account(&mut conn, account.id).await?;
+        let result = update_account_status(&mut conn, account.id, activate).await?;
+        assert_eq!(result, Err(StatusFailure::Unknown));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn confirm_account_statuses() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let account = AccountFactory::new().create(&mut conn).await?;
+
+        let ip_address = "127.0.0.1/32".parse().unwrap();
+        let payload = TokenPayload::ActivateAccount;
+        let form = AccountTokenCreateInput::new(account.id, &payload, ip_address, "Mozilla/5.0");
+        let activate = create_action_token(&mut conn, form).await?;
+        let activate = AccountTokenViewInput {
+            account_id: account.id,
+            action_token: activate.action_token,
+        };
+
+        // Rejects action types without a status.
+        let result = confirm_account_status(&mut conn, activate.clone(), TokenAction::UpdateEmail);
+        assert_eq!(result.await?, Err(TokenFailure::Unknown.into()));
+
+        let result =
+            confirm_account_status(&mut conn, activate.clone(), TokenAction::ActivateAccount);
+        assert_eq!(result.await?, Ok(()));
+        let view = view_account(&mut conn, account.id).await?;
+        assert_eq!(view.status, AccountStatus::Active);
+
+        // Keeps the token unused if the transition is forbidden.
+        let form = AccountTokenCreateInput::new(account.id, &payload, ip_address, "Mozilla/5.0");
+        let reactivate = create_action_token(&mut conn, form).await?;
+        let reactivate = AccountTokenViewInput {
+            account_id: account.id,
+            action_token: reactivate.action_token,
+        };
+
+        let action_type = TokenAction::ActivateAccount;
+        let result = confirm_account_status(&mut conn, reactivate.clone(), action_type);
+        assert_eq!(
+            result.await?,
+            Err(StatusFailure::Forbidden(AccountStatus::Active))
+        );
+
+        let payload = TokenPayload::DeactivateAccount;
+        let form = AccountTokenCreateInput::new(account.id, &payload, ip_address, "Mozilla/5.0");
+        let deactivate = create_action_token(&mut conn, form).await?;
+        let deactivate = AccountTokenViewInput {
+            account_id: account.id,
+            action_token: deactivate.action_token,
+        };
+
+        let suspend = AccountStatusUpdateInput {
+            status: AccountStatus::Suspended,
+            status_reason: None,
+        };
+
+        update_account_status(&mut conn, account.id, suspend)
+            .await?
+            .unwrap();
+
+        // Reactivation is reserved for administrators.
+        let result = confirm_account_status(&mut conn, reactivate, action_type);
+        assert_eq!(
+            result.await?,
+            Err(StatusFailure::Forbidden(AccountStatus::Suspended))
+        );
+
+        let action_type = TokenAction::DeactivateAccount;
+        let result = confirm_account_status(&mut conn, deactivate.clone(), action_type);
+        assert_eq!(
+            result.await?,
+            Err(StatusFailure::Forbidden(AccountStatus::Suspended))
+        );
+
+        // Revokes unused tokens once the account is no longer active.
+        let unsuspend = AccountStatusUpdateInput {
+            status: AccountStatus::Active,
+            status_reason: None,
+        };
+
+        update_account_status(&mut conn, account.id, unsuspend)
+            .await?
+            .unwrap();
+        let result = confirm_account_status(&mut conn, deactivate, action_type);
+        assert_eq!(result.await?, Err(TokenFailure::Revoked.into()));
+
+        let form = AccountTokenCreateInput::new(account.id, &payload, ip_address, "Mozilla/5.0");
+        let deactivate = create_action_token(&mut conn, form).await?;
+        let deactivate = AccountTokenViewInput {
+            account_id: account.id,
+            action_token: deactivate.action_token,
+        };
+
+        let form = AccountSession {
+            region_id: "EU".to_owned(),
+            ip_address,
+            user_agent: "Mozilla/5.0".to_owned(),
+        };
+
+        let _ = create_session(&mut conn, account.id, form).await?;
+        let result = confirm_account_status(&mut conn, deactivate.clone(), action_type);
+        assert_eq!(result.await?, Ok(()));
+        let view = view_account(&mut conn, account.id).await?;
+        assert_eq!(view.status, AccountStatus::PendingDeletion);
+
+        // Deletes sessions once the account is no longer active.
+        let query = account_sessions::table
+            .filter(account_sessions::account_id.eq(account.id))
+            .filter(account_sessions::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(&mut conn)
+            .await?;
+        assert_eq!(query, 0);
+
+        let result = confirm_account_status(&mut conn, deactivate, action_type);
+        assert_eq!(
+            result.await?,
+            Err(StatusFailure::Forbidden(AccountStatus::PendingDeletion))
+        );
+
+        // Banned accounts can't be activated with tokens.
+        let account = AccountFactory::new().create(&mut conn).await?;
+        let payload = TokenPayload::ActivateAccount;
+        let form = AccountTokenCreateInput::new(account.id, &payload, ip_address, "Mozilla/5.0");
+        let activate = create_action_token(&mut conn, form).await?;
+        let activate = AccountTokenViewInput {
+            account_id: account.id,
+            action_token: activate.action_token,
+        };
+
+        let ban = AccountStatusUpdateInput {
+            status: AccountStatus::Banned,
+            status_reason: None,
+        };
+
+        update_account_status(&mut conn, account.id, ban)
+            .await?
+            .unwrap();
+        let result = confirm_account_status(&mut conn, activate, TokenAction::ActivateAccount);
+        assert_eq!(
+            result.await?,
+            Err(StatusFailure::Forbidden(AccountStatus::Banned))
+        );
+
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 //! Data layer (queries and forms).
 
+use axiston_db_schema::constraints::ConstraintViolation;
 use serde::{Deserialize, Serialize};
 
+pub mod account_login_attempts;
+pub mod account_mfa;
 pub mod account_permissions;
 pub mod account_sessions;
 pub mod account_tokens;
@@ -28,3 +31,238 @@ pub enum QueryOrderBy {
     #[cfg_attr(feature = "serde", serde(rename = "desc"))]
     Descending,
 }
+
+/// Client-side validation of input forms.
+///
+/// Mirrors `CHECK` constraints with [`ConstraintViolation::rule`], so forms
+/// can be rejected before the round trip to the database.
+pub trait Validate {
+    /// Returns the first constraint the database would report as violated.
+    ///
+    /// Constraints are checked in the order of their names, like in Postgres.
+    fn validate(&self) -> Result<(), ConstraintViolation>;
+}
+
+#[cfg(test)]
+mod test {
+    use axiston_db_schema::constraints::ConstraintViolation;
+    use diesel::dsl::sql;
+    use diesel::result::{DatabaseErrorKind, Error};
+    use diesel::sql_types::{Integer, Jsonb};
+    use diesel_async::RunQueryDsl;
+    use serde_json::{json, Value};
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    use crate::account_login_attempts::{record_login_attempt, AccountLoginAttempt};
+    use crate::account_sessions::{create_session, AccountSession};
+    use crate::account_tokens::{
+        create_action_token, AccountTokenCreateInput, TokenPayload, UpdateEmailData,
+    };
+    use crate::accounts::{create_account, update_account, AccountCreateInput, AccountUpdateInput};
+    use crate::testing::{AccountFactory, TestDatabase, WorkflowFactory, WorkspaceFactory};
+    use crate::workflow_executions::{create_workflow_execution, WorkflowExecutionCreateInput};
+    use crate::workflows::{create_workflow, WorkflowCreateInput, WorkflowUpdateInput};
+    use crate::workspace_schedules::{create_workspace_schedule, WorkspaceScheduleCreateInput};
+    use crate::workspace_webhooks::{create_workspace_webhook, WorkspaceWebhookCreateInput};
+    use crate::workspaces::{create_workspace, WorkspaceCreateInput, WorkspaceUpdateInput};
+    use crate::{jsonb, DatabaseError, DatabaseResult, Validate};
+
+    /// Panics if the form is valid or the database reports another violation.
+    #[track_caller]
+    fn assert_violation<T>(form: &impl Validate, result: DatabaseResult<T>) {
+        let reported = match result {
+            Err(DatabaseError::Query(Error::DatabaseError(
+                DatabaseErrorKind::CheckViolation,
+                x,
+            ))) => x.constraint_name().and_then(ConstraintViolation::new),
+            _ => None,
+        };
+
+        let validated = form.validate().err();
+        assert!(validated.is_some(), "expected the form to be invalid");
+        assert_eq!(validated, reported);
+    }
+
+    /// Deserializes the form from the owned request body, like JSON extractors do.
+    #[cfg(feature = "serde")]
+    fn from_body<T>(body: Value) -> T
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let body = serde_json::to_vec(&body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn owned_forms() {
+        let body = json!({
+            "display_name": "Account",
+            "email_address": "account@example.com",
+            "password_hash": "hash",
+        });
+
+        let form: AccountCreateInput<'static> = from_body(body);
+        assert_eq!(form.display_name, "Account");
+
+        let body = json!({ "display_name": "Account" });
+        let form: AccountUpdateInput<'static> = from_body(body);
+        assert_eq!(form.display_name.as_deref(), Some("Account"));
+
+        let body = json!({ "display_name": "Workspace", "metadata": {} });
+        let form: WorkspaceCreateInput<'static> = from_body(body);
+        assert_eq!(form.display_name, "Workspace");
+
+        let body = json!({ "display_name": "Workspace", "metadata": {} });
+        let form: WorkspaceUpdateInput<'static> = from_body(body);
+        assert_eq!(form.display_name.as_deref(), Some("Workspace"));
+
+        let body = json!({ "workspace_id": Uuid::nil(), "display_name": "Workflow" });
+        let form: WorkflowCreateInput<'static> = from_body(body);
+        assert_eq!(form.display_name.as_deref(), Some("Workflow"));
+
+        let body = json!({ "display_name": "Workflow" });
+        let form: WorkflowUpdateInput<'static> = from_body(body);
+        assert_eq!(form.display_name.as_deref(), Some("Workflow"));
+    }
+
+    #[tokio::test]
+    async fn jsonb_text_length() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+
+        let values = [
+            Value::Null,
+            json!([]),
+            json!({}),
+            json!([1, 2.5, -3, true, false, null]),
+            json!({ "a": { "b": ["c", "d"] }, "": "\"\\\n\t\u{1}" }),
+        ];
+
+        for value in values {
+            let query = sql::<Integer>("length(")
+                .bind::<Jsonb, _>(value.clone())
+                .sql("::TEXT)");
+            let length: i32 = diesel::select(query).get_result(&mut conn).await?;
+            assert_eq!(jsonb::text_length(&value), length as usize, "{value}");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn validate_forms() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let metadata = json!({ "data": "x".repeat(4096) });
+
+        let form = AccountCreateInput {
+            display_name: "".into(),
+            email_address: "".into(),
+            password_hash: "hash".into(),
+        };
+
+        assert_violation(&form, create_account(&mut conn, &form).await);
+
+        // Whitespace-only addresses are empty once normalized.
+        let form = AccountCreateInput {
+            display_name: "Account".into(),
+            email_address: "  ".into(),
+            password_hash: "hash".into(),
+        };
+
+        assert_violation(&form, create_account(&mut conn, &form).await);
+        let account = AccountFactory::new().create(&mut conn).await?;
+
+        let form = AccountUpdateInput {
+            display_name: None,
+            email_address: Some(" \t".into()),
+            password_hash: None,
+        };
+
+        assert_violation(
+            &form,
+            update_account(&mut conn, account.id, form.clone()).await,
+        );
+
+        let form = AccountSession {
+            region_id: "eu".to_owned(),
+            ip_address: "127.0.0.1/32".parse().unwrap(),
+            user_agent: "Mozilla/5.0".to_owned(),
+        };
+
+        let result = create_session(&mut conn, account.id, form.clone()).await;
+        assert_violation(&form, result);
+
+        let form = AccountLoginAttempt {
+            account_id: Some(account.id),
+            region_id: "eu".to_owned(),
+            ip_address: "127.0.0.1/32".parse().unwrap(),
+            user_agent: "Mozilla/5.0".to_owned(),
+            is_successful: false,
+        };
+
+        let result = record_login_attempt(&mut conn, form.clone()).await;
+        assert_violation(&form, result);
+
+        let payload = TokenPayload::UpdateEmail(UpdateEmailData {
+            email_address: "x".repeat(4096),
+        });
+
+        let ip_address = "127.0.0.1/32".parse().unwrap();
+        let form = AccountTokenCreateInput::new(account.id, &payload, ip_address, "Mozilla/5.0");
+
+        assert_violation(&form, create_action_token(&mut conn, form.clone()).await);
+
+        let form = WorkspaceCreateInput {
+            display_name: "".into(),
+            metadata: metadata.clone(),
+        };
+
+        assert_violation(&form, create_workspace(&mut conn, &form).await);
+        let workspace = WorkspaceFactory::new().create(&mut conn).await?;
+
+        let form = WorkspaceScheduleCreateInput {
+            workspace_id: workspace.id,
+            metadata: metadata.clone(),
+        };
+
+        assert_violation(&form, create_workspace_schedule(&mut conn, &form).await);
+
+        let form = WorkspaceWebhookCreateInput {
+            workspace_id: workspace.id,
+            metadata: metadata.clone(),
+        };
+
+        assert_violation(&form, create_workspace_webhook(&mut conn, &form).await);
+
+        let form = WorkflowCreateInput {
+            workspace_id: workspace.id,
+            display_name: Some("".into()),
+            metadata: None,
+            input_graph: None,
+            rt_metadata: Some(metadata.clone()),
+        };
+
+        assert_violation(&form, create_workflow(&mut conn, &form).await);
+        let workflow = WorkflowFactory::new()
+            .workspace_id(workspace.id)
+            .create(&mut conn)
+            .await?;
+
+        let started_at = OffsetDateTime::now_utc();
+        let form = WorkflowExecutionCreateInput {
+            workflow_id: workflow.id,
+            output_graph: Value::default(),
+            rt_metadata: metadata,
+            started_at,
+            ended_at: started_at - Duration::seconds(1),
+        };
+
+        let result = create_workflow_execution(&mut conn, form.clone()).await;
+        assert_violation(&form, result);
+
+        Ok(())
+    }
+}
@@ -1,17 +1,20 @@
 //! Data layer for workflow executions management.
 
+use axiston_db_schema::constraints::ConstraintViolation;
+use axiston_db_schema::identifiers::{ExecutionId, WorkflowId};
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::workflow_executions;
 use diesel::dsl::*;
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use time::OffsetDateTime;
-use uuid::Uuid;
 
 use crate::dsl::*;
-use crate::{DatabaseResult, QueryOrderBy};
+use crate::{jsonb, DatabaseResult, QueryOrderBy, Validate};
 
 #[derive(Debug, Clone, Insertable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -19,7 +22,7 @@ use crate::{DatabaseResult, QueryOrderBy};
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowExecutionCreateInput {
-    pub workflow_id: Uuid,
+    pub workflow_id: WorkflowId,
     pub output_graph: Value,
     pub rt_metadata: Value,
 
@@ -27,13 +30,34 @@ pub struct WorkflowExecutionCreateInput {
     pub ended_at: OffsetDateTime,
 }
 
+impl Validate for WorkflowExecutionCreateInput {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        ConstraintViolation::WorkflowExecutionsEndedAfterStarted
+            .check_order(&self.started_at, &self.ended_at)?;
+        ConstraintViolation::WorkflowExecutionsOutputGraphLimit
+            .check_length(jsonb::text_length(&self.output_graph))?;
+        ConstraintViolation::WorkflowExecutionsRtMetadataLimit
+            .check_length(jsonb::text_length(&self.rt_metadata))?;
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Queryable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[diesel(table_name = schema::workflow_executions)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowExecutionCreateOutput {
-    pub execution_id: Uuid,
+    pub execution_id: ExecutionId,
+}
+
+/// Returns the query used by [`create_workflow_execution`].
+#[auto_type(no_type_alias)]
+pub fn create_workflow_execution_query(form: WorkflowExecutionCreateInput) -> _ {
+    insert_into(workflow_executions::table)
+        .values(form)
+        .returning(workflow_executions::execution_id)
 }
 
 /// Creates the new workflow execution and returns its ID.
@@ -45,11 +69,7 @@ pub async fn create_workflow_execution(
     conn: &mut AsyncPgConnection,
     form: WorkflowExecutionCreateInput,
 ) -> DatabaseResult<WorkflowExecutionCreateOutput> {
-    use schema::workflow_executions::dsl::*;
-
-    let query = insert_into(workflow_executions)
-        .values(form)
-        .returning(execution_id)
+    let query = create_workflow_execution_query(form)
         .get_result(conn)
         .await?;
 
@@ -62,7 +82,7 @@ pub async fn create_workflow_execution(
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowExecutionListInput {
-    pub workflow_id: Uuid,
+    pub workflow_id: WorkflowId,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
 
@@ -85,7 +105,39 @@ pub enum WorkflowsSortBy {
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowExecutionListOutput {
-    pub workflow_id: Uuid,
+    pub workflow_id: WorkflowId,
+    pub execution_id: ExecutionId,
+}
+
+/// Returns the query used by [`list_workflow_executions`].
+pub fn list_workflow_executions_query(
+    form: WorkflowExecutionListInput,
+) -> workflow_executions::BoxedQuery<
+    'static,
+    Pg,
+    SqlTypeOf<AsSelect<WorkflowExecutionListOutput, Pg>>,
+> {
+    use schema::workflow_executions::dsl::*;
+
+    let query = workflow_executions
+        .filter(workflow_id.eq(form.workflow_id).and(deleted_at.is_null()))
+        .select(WorkflowExecutionListOutput::as_select())
+        .offset(form.offset.unwrap_or_default())
+        .limit(form.limit.unwrap_or(20))
+        .into_boxed();
+
+    match (form.sort_by, form.sort_order) {
+        (WorkflowsSortBy::StartedAt, QueryOrderBy::Ascending) => query.order_by(started_at.asc()),
+        (WorkflowsSortBy::StartedAt, QueryOrderBy::Descending) => query.order_by(started_at.desc()),
+        (WorkflowsSortBy::EndedAt, QueryOrderBy::Ascending) => query.order_by(ended_at.asc()),
+        (WorkflowsSortBy::EndedAt, QueryOrderBy::Descending) => query.order_by(ended_at.desc()),
+        (WorkflowsSortBy::TimeSpent, QueryOrderBy::Ascending) => {
+            query.order_by(age(ended_at, started_at).asc())
+        }
+        (WorkflowsSortBy::TimeSpent, QueryOrderBy::Descending) => {
+            query.order_by(age(ended_at, started_at).desc())
+        }
+    }
 }
 
 /// Returns a set of workflow executions IDs.
@@ -97,34 +149,7 @@ pub async fn list_workflow_executions(
     conn: &mut AsyncPgConnection,
     form: WorkflowExecutionListInput,
 ) -> DatabaseResult<Vec<WorkflowExecutionListOutput>> {
-    use schema::workflow_executions::dsl::*;
-
-    let query = match (form.sort_by, form.sort_order) {
-        (WorkflowsSortBy::StartedAt, QueryOrderBy::Ascending) => {
-            workflow_executions.order_by(started_at.asc()).into_boxed()
-        }
-        (WorkflowsSortBy::StartedAt, QueryOrderBy::Descending) => {
-            workflow_executions.order_by(started_at.desc()).into_boxed()
-        }
-        (WorkflowsSortBy::EndedAt, QueryOrderBy::Ascending) => {
-            workflow_executions.order_by(ended_at.desc()).into_boxed()
-        }
-        (WorkflowsSortBy::EndedAt, QueryOrderBy::Descending) => {
-            workflow_executions.order_by(ended_at.desc()).into_boxed()
-        }
-        (WorkflowsSortBy::TimeSpent, QueryOrderBy::Ascending) => workflow_executions
-            .order_by(age(ended_at, started_at).asc())
-            .into_boxed(),
-        (WorkflowsSortBy::TimeSpent, QueryOrderBy::Descending) => workflow_executions
-            .order_by(age(ended_at, started_at).desc())
-            .into_boxed(),
-    };
-
-    let query = query
-        .filter(workflow_id.eq(form.workflow_id).and(deleted_at.is_null()))
-        .select(WorkflowExecutionListOutput::as_select())
-        .offset(form.offset.unwrap_or_default())
-        .limit(form.limit.unwrap_or(20))
+    let query = list_workflow_executions_query(form)
         .get_results(conn)
         .await?;
 
@@ -135,7 +160,7 @@ pub async fn list_workflow_executions(
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowExecutionViewInput {
-    pub execution_id: Uuid,
+    pub execution_id: ExecutionId,
 }
 
 #[derive(Debug, Clone, Queryable, Selectable)]
@@ -144,8 +169,8 @@ pub struct WorkflowExecutionViewInput {
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowExecutionViewOutput {
-    pub workflow_id: Uuid,
-    pub execution_id: Uuid,
+    pub workflow_id: WorkflowId,
+    pub execution_id: ExecutionId,
     pub output_graph: Value,
     pub rt_metadata: Value,
 
@@ -153,6 +178,22 @@ pub struct WorkflowExecutionViewOutput {
     pub ended_at: OffsetDateTime,
 }
 
+/// Returns the query used by [`view_workflow_execution`].
+#[auto_type(no_type_alias)]
+pub fn view_workflow_execution_query(form: WorkflowExecutionViewInput) -> _ {
+    let form_execution_id: ExecutionId = form.execution_id;
+    let filter_cond = workflow_executions::execution_id
+        .eq(form_execution_id)
+        .and(workflow_executions::deleted_at.is_null());
+
+    let selection: AsSelect<WorkflowExecutionViewOutput, Pg> =
+        WorkflowExecutionViewOutput::as_select();
+    workflow_executions::table
+        .filter(filter_cond)
+        .select(selection)
+        .limit(1i64)
+}
+
 /// Returns the details of the specified workflow execution.
 ///
 /// # Tables
@@ -162,14 +203,7 @@ pub async fn view_workflow_execution(
     conn: &mut AsyncPgConnection,
     form: WorkflowExecutionViewInput,
 ) -> DatabaseResult<WorkflowExecutionViewOutput> {
-    use schema::workflow_executions::dsl::*;
-
-    let query = workflow_executions
-        .filter(execution_id.eq(form.execution_id).and(deleted_at.is_null()))
-        .select(WorkflowExecutionViewOutput::as_select())
-        .limit(1)
-        .get_result(conn)
-        .await?;
+    let query = view_workflow_execution_query(form).get_result(conn).await?;
 
     Ok(query)
 }
@@ -178,7 +212,19 @@ pub async fn view_workflow_execution(
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowExecutionDeleteInput {
-    pub execution_id: Uuid,
+    pub execution_id: ExecutionId,
+}
+
+/// Returns the query used by [`delete_workflow_execution`].
+#[auto_type(no_type_alias)]
+pub fn delete_workflow_execution_query(form: WorkflowExecutionDeleteInput) -> _ {
+    let form_execution_id: ExecutionId = form.execution_id;
+    let filter_cond = workflow_executions::execution_id
+        .eq(form_execution_id)
+        .and(workflow_executions::deleted_at.is_null());
+
+    update(workflow_executions::table.filter(filter_cond))
+        .set(workflow_executions::deleted_at.eq(now))
 }
 
 /// Flags the specified workflow execution as deleted.
@@ -190,15 +236,109 @@ pub async fn delete_workflow_execution(
     conn: &mut AsyncPgConnection,
     form: WorkflowExecutionDeleteInput,
 ) -> DatabaseResult<()> {
-    use schema::workflow_executions::dsl::*;
+    let _query = delete_workflow_execution_query(form).execute(conn).await?;
 
-    let filter_cond =
-        workflow_executions.filter(execution_id.eq(form.execution_id).and(deleted_at.is_null()));
+    Ok(())
+}
 
-    let _query = update(filter_cond)
-        .set(deleted_at.eq(now))
-        .execute(conn)
-        .await?;
+#[cfg(test)]
+mod test {
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use serde_json::Value;
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
 
-    Ok(())
+    use crate::testing::{ExecutionFactory, TestDatabase, WorkflowFactory};
+    use crate::workflow_executions::*;
+    use crate::{DatabaseResult, QueryOrderBy};
+
+    #[test]
+    fn workflow_executions_queries() {
+        let form = WorkflowExecutionCreateInput {
+            workflow_id: Uuid::nil().into(),
+            output_graph: Value::default(),
+            rt_metadata: Value::default(),
+            started_at: OffsetDateTime::UNIX_EPOCH,
+            ended_at: OffsetDateTime::UNIX_EPOCH,
+        };
+
+        let query = create_workflow_execution_query(form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "workflow_executions" ("workflow_id", "output_graph", "rt_metadata", "started_at", "ended_at") VALUES ($1, $2, $3, $4, $5) RETURNING "workflow_executions"."execution_id" -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000), Null, Null, 1970-01-01 0:00:00.0 +00:00:00, 1970-01-01 0:00:00.0 +00:00:00]"#);
+
+        let form = WorkflowExecutionViewInput {
+            execution_id: Uuid::nil().into(),
+        };
+
+        let query = view_workflow_execution_query(form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "workflow_executions"."workflow_id", "workflow_executions"."execution_id", "workflow_executions"."output_graph", "workflow_executions"."rt_metadata", "workflow_executions"."started_at", "workflow_executions"."ended_at" FROM "workflow_executions" WHERE (("workflow_executions"."execution_id" = $1) AND ("workflow_executions"."deleted_at" IS NULL)) LIMIT $2 -- binds: [ExecutionId(00000000-0000-0000-0000-000000000000), 1]"#);
+
+        let form = WorkflowExecutionDeleteInput {
+            execution_id: Uuid::nil().into(),
+        };
+
+        let query = delete_workflow_execution_query(form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workflow_executions" SET "deleted_at" = CURRENT_TIMESTAMP WHERE (("workflow_executions"."execution_id" = $1) AND ("workflow_executions"."deleted_at" IS NULL)) -- binds: [ExecutionId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+
+    #[test]
+    fn list_workflow_executions_ordering() {
+        let list = |sort_by, sort_order| {
+            let form = WorkflowExecutionListInput {
+                workflow_id: Uuid::nil().into(),
+                limit: None,
+                offset: None,
+                sort_by,
+                sort_order,
+            };
+
+            debug_query::<Pg, _>(&list_workflow_executions_query(form)).to_string()
+        };
+
+        let query = list(WorkflowsSortBy::StartedAt, QueryOrderBy::Ascending);
+        insta::assert_snapshot!(query, @r#"SELECT "workflow_executions"."workflow_id", "workflow_executions"."execution_id" FROM "workflow_executions" WHERE (("workflow_executions"."workflow_id" = $1) AND ("workflow_executions"."deleted_at" IS NULL)) ORDER BY "workflow_executions"."started_at" ASC LIMIT $2 OFFSET $3 -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000), 20, 0]"#);
+        let query = list(WorkflowsSortBy::StartedAt, QueryOrderBy::Descending);
+        insta::assert_snapshot!(query, @r#"SELECT "workflow_executions"."workflow_id", "workflow_executions"."execution_id" FROM "workflow_executions" WHERE (("workflow_executions"."workflow_id" = $1) AND ("workflow_executions"."deleted_at" IS NULL)) ORDER BY "workflow_executions"."started_at" DESC LIMIT $2 OFFSET $3 -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000), 20, 0]"#);
+        let query = list(WorkflowsSortBy::EndedAt, QueryOrderBy::Ascending);
+        insta::assert_snapshot!(query, @r#"SELECT "workflow_executions"."workflow_id", "workflow_executions"."execution_id" FROM "workflow_executions" WHERE (("workflow_executions"."workflow_id" = $1) AND ("workflow_executions"."deleted_at" IS NULL)) ORDER BY "workflow_executions"."ended_at" ASC LIMIT $2 OFFSET $3 -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000), 20, 0]"#);
+        let query = list(WorkflowsSortBy::EndedAt, QueryOrderBy::Descending);
+        insta::assert_snapshot!(query, @r#"SELECT "workflow_executions"."workflow_id", "workflow_executions"."execution_id" FROM "workflow_executions" WHERE (("workflow_executions"."workflow_id" = $1) AND ("workflow_executions"."deleted_at" IS NULL)) ORDER BY "workflow_executions"."ended_at" DESC LIMIT $2 OFFSET $3 -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000), 20, 0]"#);
+        let query = list(WorkflowsSortBy::TimeSpent, QueryOrderBy::Ascending);
+        insta::assert_snapshot!(query, @r#"SELECT "workflow_executions"."workflow_id", "workflow_executions"."execution_id" FROM "workflow_executions" WHERE (("workflow_executions"."workflow_id" = $1) AND ("workflow_executions"."deleted_at" IS NULL)) ORDER BY AGE("workflow_executions"."ended_at", "workflow_executions"."started_at") ASC LIMIT $2 OFFSET $3 -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000), 20, 0]"#);
+        let query = list(WorkflowsSortBy::TimeSpent, QueryOrderBy::Descending);
+        insta::assert_snapshot!(query, @r#"SELECT "workflow_executions"."workflow_id", "workflow_executions"."execution_id" FROM "workflow_executions" WHERE (("workflow_executions"."workflow_id" = $1) AND ("workflow_executions"."deleted_at" IS NULL)) ORDER BY AGE("workflow_executions"."ended_at", "workflow_executions"."started_at") DESC LIMIT $2 OFFSET $3 -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000), 20, 0]"#);
+    }
+
+    #[tokio::test]
+    async fn list_workflow_executions_by_ended_at() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let workflow = WorkflowFactory::new().create(&mut conn).await?;
+
+        // Started in order 0, 1, 2, but ended in order 1, 2, 0.
+        let started_at = OffsetDateTime::now_utc() - Duration::hours(1);
+        let mut executions = Vec::new();
+        for (offset, time_spent) in [(0, 30), (10, 5), (20, 1)] {
+            let execution = ExecutionFactory::new()
+                .workflow_id(workflow.id)
+                .started_at(started_at + Duration::seconds(offset))
+                .time_spent(Duration::seconds(time_spent))
+                .create(&mut conn)
+                .await?;
+            executions.push(execution.execution_id);
+        }
+
+        let form = WorkflowExecutionListInput {
+            workflow_id: workflow.id,
+            limit: None,
+            offset: None,
+            sort_by: WorkflowsSortBy::EndedAt,
+            sort_order: QueryOrderBy::Ascending,
+        };
+
+        let output = list_workflow_executions(&mut conn, form).await?;
+        let output: Vec<_> = output.into_iter().map(|x| x.execution_id).collect();
+        assert_eq!(output, vec![executions[1], executions[2], executions[0]]);
+        Ok(())
+    }
 }
@@ -1,12 +1,13 @@
 //! Data layer for workflow schedules management.
 
+use axiston_db_schema::identifiers::{ScheduleId, WorkflowId};
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::workflow_schedules;
 use diesel::dsl::*;
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 use crate::DatabaseResult;
 
@@ -16,8 +17,14 @@ use crate::DatabaseResult;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowScheduleCreateInput {
-    pub workflow_id: Uuid,
-    pub schedule_id: Uuid,
+    pub workflow_id: WorkflowId,
+    pub schedule_id: ScheduleId,
+}
+
+/// Returns the query used by [`create_all_workflow_schedules`].
+#[auto_type(no_type_alias)]
+pub fn create_all_workflow_schedules_query<'a>(form: &'a [WorkflowScheduleCreateInput]) -> _ {
+    insert_into(workflow_schedules::table).values(form)
 }
 
 /// Creates all workflow schedules associated with a workflow.
@@ -29,11 +36,9 @@ pub struct WorkflowScheduleCreateInput {
 /// - workflow_schedules
 pub async fn create_all_workflow_schedules(
     conn: &mut AsyncPgConnection,
-    form_workflow_id: Uuid,
-    form_schedule_ids: Vec<Uuid>,
+    form_workflow_id: WorkflowId,
+    form_schedule_ids: Vec<ScheduleId>,
 ) -> DatabaseResult<()> {
-    use schema::workflow_schedules::dsl::*;
-
     let new_workflow_schedules: Vec<_> = form_schedule_ids
         .into_iter()
         .map(|id| WorkflowScheduleCreateInput {
@@ -42,14 +47,20 @@ pub async fn create_all_workflow_schedules(
         })
         .collect();
 
-    insert_into(workflow_schedules)
-        .values(&new_workflow_schedules)
+    create_all_workflow_schedules_query(&new_workflow_schedules)
         .execute(conn)
         .await?;
 
     Ok(())
 }
 
+/// Returns the query used by [`delete_all_workflow_schedules`].
+#[auto_type(no_type_alias)]
+pub fn delete_all_workflow_schedules_query(form_workflow_id: WorkflowId) -> _ {
+    let filter_cond = workflow_schedules::workflow_id.eq(form_workflow_id);
+    delete(workflow_schedules::table.filter(filter_cond))
+}
+
 /// Deletes all workflow schedules associated with a workflow.
 ///
 /// Executed when the associated workflow is updated.
@@ -59,12 +70,34 @@ pub async fn create_all_workflow_schedules(
 /// - workflow_schedules
 pub async fn delete_all_workflow_schedules(
     conn: &mut AsyncPgConnection,
-    form_workflow_id: Uuid,
+    form_workflow_id: WorkflowId,
 ) -> DatabaseResult<()> {
-    use schema::workflow_schedules::dsl::*;
-
-    let filter_cond = workflow_schedules.filter(workflow_id.eq(form_workflow_id));
-    let _query = delete(filter_cond).execute(conn).await?;
+    let _query = delete_all_workflow_schedules_query(form_workflow_id)
+        .execute(conn)
+        .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use uuid::Uuid;
+
+    use crate::workflow_schedules::*;
+
+    #[test]
+    fn workflow_schedules_queries() {
+        let form = [WorkflowScheduleCreateInput {
+            workflow_id: Uuid::nil().into(),
+            schedule_id: Uuid::nil().into(),
+        }];
+
+        let query = create_all_workflow_schedules_query(&form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "workflow_schedules" ("workflow_id", "schedule_id") VALUES ($1, $2) -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000), ScheduleId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = delete_all_workflow_schedules_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"DELETE FROM "workflow_schedules" WHERE ("workflow_schedules"."workflow_id" = $1) -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+}
@@ -1,12 +1,14 @@
 //! Data layer for workflow webhooks management.
 
+use axiston_db_schema::identifiers::{WebhookId, WorkflowId};
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::{workflow_webhooks, workflows};
 use diesel::dsl::*;
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 use crate::workflows::WorkflowViewOutput;
 use crate::DatabaseResult;
@@ -17,8 +19,14 @@ use crate::DatabaseResult;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowWebhookCreateInput {
-    pub workflow_id: Uuid,
-    pub webhook_id: Uuid,
+    pub workflow_id: WorkflowId,
+    pub webhook_id: WebhookId,
+}
+
+/// Returns the query used by [`create_all_workflow_webhooks`].
+#[auto_type(no_type_alias)]
+pub fn create_all_workflow_webhooks_query<'a>(form: &'a [WorkflowWebhookCreateInput]) -> _ {
+    insert_into(workflow_webhooks::table).values(form)
 }
 
 /// Creates all workflow webhooks associated with a workflow.
@@ -30,11 +38,9 @@ pub struct WorkflowWebhookCreateInput {
 /// - workflow_webhooks
 pub async fn create_all_workflow_webhooks(
     conn: &mut AsyncPgConnection,
-    form_workflow_id: Uuid,
-    form_webhook_ids: Vec<Uuid>,
+    form_workflow_id: WorkflowId,
+    form_webhook_ids: Vec<WebhookId>,
 ) -> DatabaseResult<()> {
-    use schema::workflow_webhooks::dsl::*;
-
     let new_workflow_webhooks: Vec<_> = form_webhook_ids
         .into_iter()
         .map(|id| WorkflowWebhookCreateInput {
@@ -43,14 +49,24 @@ pub async fn create_all_workflow_webhooks(
         })
         .collect();
 
-    insert_into(workflow_webhooks)
-        .values(&new_workflow_webhooks)
+    create_all_workflow_webhooks_query(&new_workflow_webhooks)
         .execute(conn)
         .await?;
 
     Ok(())
 }
 
+/// Returns the query used by [`view_workflows_by_webhook`].
+#[auto_type(no_type_alias)]
+pub fn view_workflows_by_webhook_query(webhook_id: WebhookId) -> _ {
+    let join_cond = workflows::id.eq(workflow_webhooks::workflow_id);
+    let selection: AsSelect<WorkflowViewOutput, Pg> = WorkflowViewOutput::as_select();
+    workflows::table
+        .inner_join(workflow_webhooks::table.on(join_cond))
+        .filter(workflow_webhooks::webhook_id.eq(webhook_id))
+        .select(selection)
+}
+
 /// Returns all workflows that use the webhook.
 ///
 /// # Tables
@@ -59,21 +75,22 @@ pub async fn create_all_workflow_webhooks(
 /// - workflow_webhooks
 pub async fn view_workflows_by_webhook(
     conn: &mut AsyncPgConnection,
-    webhook_id: Uuid,
+    webhook_id: WebhookId,
 ) -> DatabaseResult<Vec<WorkflowViewOutput>> {
-    use schema::workflow_webhooks::dsl as wfh_dsl;
-    use schema::workflows::dsl as wf_dsl;
-
-    let query = wf_dsl::workflows
-        .inner_join(wfh_dsl::workflow_webhooks.on(wf_dsl::workspace_id.eq(wfh_dsl::workflow_id)))
-        .filter(wfh_dsl::webhook_id.eq(webhook_id))
-        .select(WorkflowViewOutput::as_select())
+    let query = view_workflows_by_webhook_query(webhook_id)
         .get_results(conn)
         .await?;
 
     Ok(query)
 }
 
+/// Returns the query used by [`delete_all_workflow_webhooks`].
+#[auto_type(no_type_alias)]
+pub fn delete_all_workflow_webhooks_query(form_workflow_id: WorkflowId) -> _ {
+    let filter_cond = workflow_webhooks::workflow_id.eq(form_workflow_id);
+    delete(workflow_webhooks::table.filter(filter_cond))
+}
+
 /// Deletes all workflow webhooks associated with a workflow.
 ///
 /// Executed when the associated workflow is updated.
@@ -83,12 +100,59 @@ pub async fn view_workflows_by_webhook(
 /// - workflow_webhooks
 pub async fn delete_all_workflow_webhooks(
     conn: &mut AsyncPgConnection,
-    form_workflow_id: Uuid,
+    form_workflow_id: WorkflowId,
 ) -> DatabaseResult<()> {
-    use schema::workflow_webhooks::dsl::*;
-
-    let filter_cond = workflow_webhooks.filter(workflow_id.eq(form_workflow_id));
-    let _query = delete(filter_cond).execute(conn).await?;
+    let _query = delete_all_workflow_webhooks_query(form_workflow_id)
+        .execute(conn)
+        .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use uuid::Uuid;
+
+    use crate::testing::{TestDatabase, WebhookFactory, WorkflowFactory, WorkspaceFactory};
+    use crate::workflow_webhooks::*;
+
+    #[test]
+    fn workflow_webhooks_queries() {
+        let form = [WorkflowWebhookCreateInput {
+            workflow_id: Uuid::nil().into(),
+            webhook_id: Uuid::nil().into(),
+        }];
+
+        let query = create_all_workflow_webhooks_query(&form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "workflow_webhooks" ("workflow_id", "webhook_id") VALUES ($1, $2) -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000), WebhookId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = view_workflows_by_webhook_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "workflows"."id", "workflows"."display_name", "workflows"."metadata", "workflows"."input_graph", "workflows"."rt_metadata", "workflows"."created_at", "workflows"."updated_at", "workflows"."deleted_at" FROM ("workflows" INNER JOIN "workflow_webhooks" ON ("workflows"."id" = "workflow_webhooks"."workflow_id")) WHERE ("workflow_webhooks"."webhook_id" = $1) -- binds: [WebhookId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = delete_all_workflow_webhooks_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"DELETE FROM "workflow_webhooks" WHERE ("workflow_webhooks"."workflow_id" = $1) -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+
+    #[tokio::test]
+    async fn view_workflows_by_webhook_join() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let workspace = WorkspaceFactory::new().create(&mut conn).await?;
+        let workflow = WorkflowFactory::new()
+            .workspace_id(workspace.id)
+            .create(&mut conn)
+            .await?;
+        let webhook = WebhookFactory::new()
+            .workspace_id(workspace.id)
+            .create(&mut conn)
+            .await?;
+
+        create_all_workflow_webhooks(&mut conn, workflow.id, vec![webhook.id]).await?;
+        let output = view_workflows_by_webhook(&mut conn, webhook.id).await?;
+        let output: Vec<_> = output.into_iter().map(|x| x.id).collect();
+        assert_eq!(output, vec![workflow.id]);
+        Ok(())
+    }
+}
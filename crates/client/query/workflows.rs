@@ -1,16 +1,21 @@
 //! Data layer for workflow management.
 
+use std::borrow::Cow;
+
+use axiston_db_schema::constraints::ConstraintViolation;
+use axiston_db_schema::identifiers::{WorkflowId, WorkspaceId};
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::workflows;
 use diesel::dsl::*;
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use time::OffsetDateTime;
-use uuid::Uuid;
 
-use crate::DatabaseResult;
+use crate::{jsonb, DatabaseResult, Validate};
 
 #[derive(Debug, Clone, Insertable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -18,25 +23,54 @@ use crate::DatabaseResult;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowCreateInput<'a> {
-    pub workspace_id: Uuid,
-    pub display_name: Option<&'a str>,
+    pub workspace_id: WorkspaceId,
+    pub display_name: Option<Cow<'a, str>>,
     pub metadata: Option<Value>,
     pub input_graph: Option<Value>,
     pub rt_metadata: Option<Value>,
 }
 
+impl Validate for WorkflowCreateInput<'_> {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        if let Some(x) = &self.input_graph {
+            ConstraintViolation::WorkflowsInputGraphLimit.check_length(jsonb::text_length(x))?;
+        }
+        if let Some(x) = &self.metadata {
+            ConstraintViolation::WorkflowsMetadataLimit.check_length(jsonb::text_length(x))?;
+        }
+        if let Some(x) = &self.display_name {
+            ConstraintViolation::WorkflowsNonEmptyDisplayName.check_text(x)?;
+        }
+        if let Some(x) = &self.rt_metadata {
+            ConstraintViolation::WorkflowsRtMetadataLimit.check_length(jsonb::text_length(x))?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Queryable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[diesel(table_name = schema::workflows)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowCreateOutput {
-    pub id: Uuid,
+    pub id: WorkflowId,
 
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
 }
 
+/// Returns the query used by [`create_workflow`].
+#[auto_type(no_type_alias)]
+pub fn create_workflow_query<'a>(form: &'a WorkflowCreateInput<'a>) -> _ {
+    insert_into(workflows::table).values(form).returning((
+        workflows::id,
+        workflows::created_at,
+        workflows::updated_at,
+    ))
+}
+
 /// Creates a new workflow in the database.
 ///
 /// # Tables
@@ -46,13 +80,7 @@ pub async fn create_workflow(
     conn: &mut AsyncPgConnection,
     form: &WorkflowCreateInput<'_>,
 ) -> DatabaseResult<WorkflowCreateOutput> {
-    use schema::workflows::dsl::*;
-
-    let query = diesel::insert_into(workflows)
-        .values(form)
-        .returning((id, created_at, updated_at))
-        .get_result(conn)
-        .await?;
+    let query = create_workflow_query(form).get_result(conn).await?;
 
     Ok(query)
 }
@@ -63,12 +91,40 @@ pub async fn create_workflow(
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowUpdateInput<'a> {
-    pub display_name: Option<&'a str>,
+    pub display_name: Option<Cow<'a, str>>,
     pub metadata: Option<Value>,
     pub input_graph: Option<Value>,
     pub rt_metadata: Option<Value>,
 }
 
+impl Validate for WorkflowUpdateInput<'_> {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        if let Some(x) = &self.input_graph {
+            ConstraintViolation::WorkflowsInputGraphLimit.check_length(jsonb::text_length(x))?;
+        }
+        if let Some(x) = &self.metadata {
+            ConstraintViolation::WorkflowsMetadataLimit.check_length(jsonb::text_length(x))?;
+        }
+        if let Some(x) = &self.display_name {
+            ConstraintViolation::WorkflowsNonEmptyDisplayName.check_text(x)?;
+        }
+        if let Some(x) = &self.rt_metadata {
+            ConstraintViolation::WorkflowsRtMetadataLimit.check_length(jsonb::text_length(x))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the query used by [`update_workflow`].
+#[auto_type(no_type_alias)]
+pub fn update_workflow_query<'a>(form_workflow_id: WorkflowId, form: WorkflowUpdateInput<'a>) -> _ {
+    let filter_cond = workflows::id
+        .eq(form_workflow_id)
+        .and(workflows::deleted_at.is_null());
+    update(workflows::table.filter(filter_cond)).set(form)
+}
+
 /// Updates the workflow with provided data.
 ///
 /// # Tables
@@ -76,14 +132,10 @@ pub struct WorkflowUpdateInput<'a> {
 /// - workflows
 pub async fn update_workflow(
     conn: &mut AsyncPgConnection,
-    form_workflow_id: Uuid,
+    form_workflow_id: WorkflowId,
     form: WorkflowUpdateInput<'_>,
 ) -> DatabaseResult<()> {
-    use schema::workflows::dsl::*;
-
-    let filter_cond = id.eq(form_workflow_id).and(deleted_at.is_null());
-    let _query = update(workflows.filter(filter_cond))
-        .set(form)
+    let _query = update_workflow_query(form_workflow_id, form)
         .execute(conn)
         .await?;
 
@@ -96,7 +148,7 @@ pub async fn update_workflow(
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkflowViewOutput {
-    pub id: Uuid,
+    pub id: WorkflowId,
     pub display_name: String,
     pub metadata: Value,
 
@@ -108,6 +160,19 @@ pub struct WorkflowViewOutput {
     pub deleted_at: Option<OffsetDateTime>,
 }
 
+/// Returns the query used by [`view_workflows_by_id`].
+#[auto_type(no_type_alias)]
+pub fn view_workflows_by_id_query(form_workflow_id: WorkflowId) -> _ {
+    let filter_cond = workflows::id
+        .eq(form_workflow_id)
+        .and(workflows::deleted_at.is_null());
+    let selection: AsSelect<WorkflowViewOutput, Pg> = WorkflowViewOutput::as_select();
+    workflows::table
+        .filter(filter_cond)
+        .select(selection)
+        .limit(1i64)
+}
+
 /// Returns the workflow data by its ID.
 ///
 /// # Tables
@@ -115,21 +180,24 @@ pub struct WorkflowViewOutput {
 /// - workflows
 pub async fn view_workflows_by_id(
     conn: &mut AsyncPgConnection,
-    form_workflow_id: Uuid,
+    form_workflow_id: WorkflowId,
 ) -> DatabaseResult<WorkflowViewOutput> {
-    use schema::workflows::dsl::*;
-
-    let filter_cond = id.eq(form_workflow_id).and(deleted_at.is_null());
-    let workflow = workflows
-        .filter(filter_cond)
-        .select(WorkflowViewOutput::as_select())
-        .limit(1)
+    let workflow = view_workflows_by_id_query(form_workflow_id)
         .get_result(conn)
         .await?;
 
     Ok(workflow)
 }
 
+/// Returns the query used by [`delete_workflow`].
+#[auto_type(no_type_alias)]
+pub fn delete_workflow_query(form_workflow_id: WorkflowId) -> _ {
+    let filter_cond = workflows::id
+        .eq(form_workflow_id)
+        .and(workflows::deleted_at.is_null());
+    update(workflows::table.filter(filter_cond)).set(workflows::deleted_at.eq(now))
+}
+
 /// Flags a specified workflow as deleted.
 ///
 /// # Tables
@@ -137,15 +205,51 @@ pub async fn view_workflows_by_id(
 /// - workflows
 pub async fn delete_workflow(
     conn: &mut AsyncPgConnection,
-    form_workflow_id: Uuid,
+    form_workflow_id: WorkflowId,
 ) -> DatabaseResult<()> {
-    use schema::workflows::dsl::*;
-
-    let filter_cond = id.eq(form_workflow_id).and(deleted_at.is_null());
-    update(workflows.filter(filter_cond))
-        .set(deleted_at.eq(now))
+    delete_workflow_query(form_workflow_id)
         .execute(conn)
         .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use crate::workflows::*;
+
+    #[test]
+    fn workflows_queries() {
+        let form = WorkflowCreateInput {
+            workspace_id: Uuid::nil().into(),
+            display_name: Some("Workflow".into()),
+            metadata: None,
+            input_graph: None,
+            rt_metadata: None,
+        };
+
+        let query = create_workflow_query(&form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "workflows" ("workspace_id", "display_name", "metadata", "input_graph", "rt_metadata") VALUES ($1, $2, DEFAULT, DEFAULT, DEFAULT) RETURNING "workflows"."id", "workflows"."created_at", "workflows"."updated_at" -- binds: [WorkspaceId(00000000-0000-0000-0000-000000000000), "Workflow"]"#);
+
+        let form = WorkflowUpdateInput {
+            display_name: Some("Workflow".into()),
+            metadata: Some(Value::default()),
+            input_graph: None,
+            rt_metadata: None,
+        };
+
+        let query = update_workflow_query(Uuid::nil().into(), form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workflows" SET "display_name" = $1, "metadata" = $2 WHERE (("workflows"."id" = $3) AND ("workflows"."deleted_at" IS NULL)) -- binds: ["Workflow", Null, WorkflowId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = view_workflows_by_id_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "workflows"."id", "workflows"."display_name", "workflows"."metadata", "workflows"."input_graph", "workflows"."rt_metadata", "workflows"."created_at", "workflows"."updated_at", "workflows"."deleted_at" FROM "workflows" WHERE (("workflows"."id" = $1) AND ("workflows"."deleted_at" IS NULL)) LIMIT $2 -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000), 1]"#);
+
+        let query = delete_workflow_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workflows" SET "deleted_at" = CURRENT_TIMESTAMP WHERE (("workflows"."id" = $1) AND ("workflows"."deleted_at" IS NULL)) -- binds: [WorkflowId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+}
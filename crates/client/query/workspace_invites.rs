@@ -1,14 +1,16 @@
 //! Data layer for workspace invitations.
 
 use axiston_db_schema::enumerations::InviteStatus;
+use axiston_db_schema::identifiers::{AccountId, InviteId, WorkspaceId};
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::workspace_invites;
 use diesel::dsl::*;
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
-use uuid::Uuid;
 
 use crate::DatabaseResult;
 
@@ -18,9 +20,9 @@ use crate::DatabaseResult;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceInviteCreateInput {
-    pub workspace_id: Uuid,
-    pub account_id: Uuid,
-    pub created_by: Uuid,
+    pub workspace_id: WorkspaceId,
+    pub account_id: AccountId,
+    pub created_by: AccountId,
 }
 
 #[derive(Debug, Clone, Queryable)]
@@ -29,8 +31,8 @@ pub struct WorkspaceInviteCreateInput {
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceInviteCreateOutput {
-    pub workspace_id: Uuid,
-    pub invite_id: Uuid,
+    pub workspace_id: WorkspaceId,
+    pub invite_id: InviteId,
     pub status: InviteStatus,
 
     pub created_at: OffsetDateTime,
@@ -43,11 +45,11 @@ pub struct WorkspaceInviteCreateOutput {
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceInviteViewOutput {
-    pub workspace_id: Uuid,
-    pub invite_id: Uuid,
+    pub workspace_id: WorkspaceId,
+    pub invite_id: InviteId,
     pub invite_status: InviteStatus,
-    pub created_by: Uuid,
-    pub updated_by: Uuid,
+    pub created_by: AccountId,
+    pub updated_by: AccountId,
 
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
@@ -60,7 +62,21 @@ pub struct WorkspaceInviteViewOutput {
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceInviteUpdateInput {
     pub invite_status: InviteStatus,
-    pub updated_by: Uuid,
+    pub updated_by: AccountId,
+}
+
+/// Returns the query used by [`create_workspace_invite`].
+#[auto_type(no_type_alias)]
+pub fn create_workspace_invite_query<'a>(invite_form: &'a WorkspaceInviteCreateInput) -> _ {
+    insert_into(workspace_invites::table)
+        .values(invite_form)
+        .returning((
+            workspace_invites::workspace_id,
+            workspace_invites::invite_id,
+            workspace_invites::invite_status,
+            workspace_invites::created_at,
+            workspace_invites::updated_at,
+        ))
 }
 
 /// Creates a new workspace invitation.
@@ -72,23 +88,26 @@ pub async fn create_workspace_invite(
     conn: &mut AsyncPgConnection,
     invite_form: &WorkspaceInviteCreateInput,
 ) -> DatabaseResult<WorkspaceInviteCreateOutput> {
-    use schema::workspace_invites::dsl::*;
-
-    let query = insert_into(workspace_invites)
-        .values(invite_form)
-        .returning((
-            workspace_id,
-            invite_id,
-            invite_status,
-            created_at,
-            updated_at,
-        ))
+    let query = create_workspace_invite_query(invite_form)
         .get_result(conn)
         .await?;
 
     Ok(query)
 }
 
+/// Returns the query used by [`update_workspace_invite`].
+#[auto_type(no_type_alias)]
+pub fn update_workspace_invite_query(
+    workspace_id_val: WorkspaceId,
+    invite_id_val: InviteId,
+    form: WorkspaceInviteUpdateInput,
+) -> _ {
+    let filter_cond = workspace_invites::workspace_id
+        .eq(workspace_id_val)
+        .and(workspace_invites::invite_id.eq(invite_id_val));
+    update(workspace_invites::table.filter(filter_cond)).set(form)
+}
+
 /// Updates the status of a workspace invitation.
 ///
 /// # Tables
@@ -96,23 +115,29 @@ pub async fn create_workspace_invite(
 /// - workspace_invites
 pub async fn update_workspace_invite(
     conn: &mut AsyncPgConnection,
-    workspace_id_val: Uuid,
-    invite_id_val: Uuid,
+    workspace_id_val: WorkspaceId,
+    invite_id_val: InviteId,
     form: WorkspaceInviteUpdateInput,
 ) -> DatabaseResult<()> {
-    use schema::workspace_invites::dsl::*;
-
-    let filter_cond = workspace_id
-        .eq(workspace_id_val)
-        .and(invite_id.eq(invite_id_val));
-    let _query = update(workspace_invites.filter(filter_cond))
-        .set(form)
+    let _query = update_workspace_invite_query(workspace_id_val, invite_id_val, form)
         .execute(conn)
         .await?;
 
     Ok(())
 }
 
+/// Returns the query used by [`view_workspace_invite`].
+#[auto_type(no_type_alias)]
+pub fn view_workspace_invite_query(form_workspace_id: WorkspaceId, invite_id_val: InviteId) -> _ {
+    let filter_cond = workspace_invites::workspace_id
+        .eq(form_workspace_id)
+        .and(workspace_invites::invite_id.eq(invite_id_val));
+    let selection: AsSelect<WorkspaceInviteViewOutput, Pg> = WorkspaceInviteViewOutput::as_select();
+    workspace_invites::table
+        .filter(filter_cond)
+        .select(selection)
+}
+
 /// Retrieves an invitation by workspace and invite ID.
 ///
 /// # Tables
@@ -120,19 +145,45 @@ pub async fn update_workspace_invite(
 /// - workspace_invites
 pub async fn view_workspace_invite(
     conn: &mut AsyncPgConnection,
-    form_workspace_id: Uuid,
-    invite_id_val: Uuid,
+    form_workspace_id: WorkspaceId,
+    invite_id_val: InviteId,
 ) -> DatabaseResult<WorkspaceInviteViewOutput> {
-    use schema::workspace_invites::dsl::*;
-
-    let filter_cond = workspace_id
-        .eq(form_workspace_id)
-        .and(invite_id.eq(invite_id_val));
-    let query = workspace_invites
-        .filter(filter_cond)
-        .select(WorkspaceInviteViewOutput::as_select())
+    let query = view_workspace_invite_query(form_workspace_id, invite_id_val)
         .get_result(conn)
         .await?;
 
     Ok(query)
 }
+
+#[cfg(test)]
+mod test {
+    use axiston_db_schema::enumerations::InviteStatus;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use uuid::Uuid;
+
+    use crate::workspace_invites::*;
+
+    #[test]
+    fn workspace_invites_queries() {
+        let form = WorkspaceInviteCreateInput {
+            workspace_id: Uuid::nil().into(),
+            account_id: Uuid::nil().into(),
+            created_by: Uuid::nil().into(),
+        };
+
+        let query = create_workspace_invite_query(&form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "workspace_invites" ("workspace_id", "account_id", "created_by") VALUES ($1, $2, $3) RETURNING "workspace_invites"."workspace_id", "workspace_invites"."invite_id", "workspace_invites"."invite_status", "workspace_invites"."created_at", "workspace_invites"."updated_at" -- binds: [WorkspaceId(00000000-0000-0000-0000-000000000000), AccountId(00000000-0000-0000-0000-000000000000), AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let form = WorkspaceInviteUpdateInput {
+            invite_status: InviteStatus::Accepted,
+            updated_by: Uuid::nil().into(),
+        };
+
+        let query = update_workspace_invite_query(Uuid::nil().into(), Uuid::nil().into(), form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workspace_invites" SET "invite_status" = $1, "updated_by" = $2 WHERE (("workspace_invites"."workspace_id" = $3) AND ("workspace_invites"."invite_id" = $4)) -- binds: [Accepted, AccountId(00000000-0000-0000-0000-000000000000), WorkspaceId(00000000-0000-0000-0000-000000000000), InviteId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = view_workspace_invite_query(Uuid::nil().into(), Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "workspace_invites"."workspace_id", "workspace_invites"."invite_id", "workspace_invites"."invite_status", "workspace_invites"."created_by", "workspace_invites"."updated_by", "workspace_invites"."created_at", "workspace_invites"."updated_at" FROM "workspace_invites" WHERE (("workspace_invites"."workspace_id" = $1) AND ("workspace_invites"."invite_id" = $2)) -- binds: [WorkspaceId(00000000-0000-0000-0000-000000000000), InviteId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+}
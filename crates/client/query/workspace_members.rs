@@ -1,14 +1,16 @@
 //! Data layer for workspace member management.
 
 use axiston_db_schema::enumerations::ProjectRole;
+use axiston_db_schema::identifiers::{AccountId, WorkspaceId};
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::workspace_members;
 use diesel::dsl::*;
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
-use uuid::Uuid;
 
 use crate::DatabaseResult;
 
@@ -18,10 +20,10 @@ use crate::DatabaseResult;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceMemberCreateInput {
-    pub workspace_id: Uuid,
-    pub account_id: Uuid,
-    pub created_by: Uuid,
-    pub updated_by: Uuid,
+    pub workspace_id: WorkspaceId,
+    pub account_id: AccountId,
+    pub created_by: AccountId,
+    pub updated_by: AccountId,
 }
 
 #[derive(Debug, Clone, Queryable, Selectable)]
@@ -30,13 +32,13 @@ pub struct WorkspaceMemberCreateInput {
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceMemberOutput {
-    pub workspace_id: Uuid,
-    pub account_id: Uuid,
+    pub workspace_id: WorkspaceId,
+    pub account_id: AccountId,
     pub show_order: i32,
     pub is_pinned: bool,
     pub is_hidden: bool,
-    pub created_by: Uuid,
-    pub updated_by: Uuid,
+    pub created_by: AccountId,
+    pub updated_by: AccountId,
 
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
@@ -52,7 +54,13 @@ pub struct WorkspaceMemberUpdateInput {
     pub is_pinned: Option<bool>,
     pub is_hidden: Option<bool>,
     pub account_role: Option<ProjectRole>,
-    pub updated_by: Option<Uuid>,
+    pub updated_by: Option<AccountId>,
+}
+
+/// Returns the query used by [`create_workspace_member`].
+#[auto_type(no_type_alias)]
+pub fn create_workspace_member_query<'a>(member_form: &'a WorkspaceMemberCreateInput) -> _ {
+    insert_into(workspace_members::table).values(member_form)
 }
 
 /// Adds a new member to a workspace.
@@ -64,16 +72,26 @@ pub async fn create_workspace_member(
     conn: &mut AsyncPgConnection,
     member_form: &WorkspaceMemberCreateInput,
 ) -> DatabaseResult<()> {
-    use schema::workspace_members::dsl::*;
-
-    let _query = insert_into(workspace_members)
-        .values(member_form)
+    let _query = create_workspace_member_query(member_form)
         .execute(conn)
         .await?;
 
     Ok(())
 }
 
+/// Returns the query used by [`get_workspace_member`].
+#[auto_type(no_type_alias)]
+pub fn get_workspace_member_query(form_workspace_id: WorkspaceId, form_account_id: AccountId) -> _ {
+    let filter_cond = workspace_members::workspace_id
+        .eq(form_workspace_id)
+        .and(workspace_members::account_id.eq(form_account_id));
+
+    let selection: AsSelect<WorkspaceMemberOutput, Pg> = WorkspaceMemberOutput::as_select();
+    workspace_members::table
+        .filter(filter_cond)
+        .select(selection)
+}
+
 /// Retrieves a member of a workspace.
 ///
 /// # Tables
@@ -81,24 +99,30 @@ pub async fn create_workspace_member(
 /// - workspace_members
 pub async fn get_workspace_member(
     conn: &mut AsyncPgConnection,
-    form_workspace_id: Uuid,
-    form_account_id: Uuid,
+    form_workspace_id: WorkspaceId,
+    form_account_id: AccountId,
 ) -> DatabaseResult<WorkspaceMemberOutput> {
-    use schema::workspace_members::dsl::*;
-
-    let filter_cond = workspace_id
-        .eq(form_workspace_id)
-        .and(account_id.eq(form_account_id));
-
-    let query = workspace_members
-        .filter(filter_cond)
-        .select(WorkspaceMemberOutput::as_select())
+    let query = get_workspace_member_query(form_workspace_id, form_account_id)
         .get_result(conn)
         .await?;
 
     Ok(query)
 }
 
+/// Returns the query used by [`update_workspace_member`].
+#[auto_type(no_type_alias)]
+pub fn update_workspace_member_query(
+    form_workspace_id: WorkspaceId,
+    form_account_id: AccountId,
+    form: WorkspaceMemberUpdateInput,
+) -> _ {
+    let filter_cond = workspace_members::workspace_id
+        .eq(form_workspace_id)
+        .and(workspace_members::account_id.eq(form_account_id));
+
+    update(workspace_members::table.filter(filter_cond)).set(form)
+}
+
 /// Updates a member's details in a workspace.
 ///
 /// # Tables
@@ -106,24 +130,30 @@ pub async fn get_workspace_member(
 /// - workspace_members
 pub async fn update_workspace_member(
     conn: &mut AsyncPgConnection,
-    form_workspace_id: Uuid,
-    form_account_id: Uuid,
+    form_workspace_id: WorkspaceId,
+    form_account_id: AccountId,
     form: WorkspaceMemberUpdateInput,
 ) -> DatabaseResult<()> {
-    use schema::workspace_members::dsl::*;
-
-    let filter_cond = workspace_id
-        .eq(form_workspace_id)
-        .and(account_id.eq(form_account_id));
-
-    let _query = update(workspace_members.filter(filter_cond))
-        .set(form)
+    let _query = update_workspace_member_query(form_workspace_id, form_account_id, form)
         .execute(conn)
         .await?;
 
     Ok(())
 }
 
+/// Returns the query used by [`remove_workspace_member`].
+#[auto_type(no_type_alias)]
+pub fn remove_workspace_member_query(
+    form_workspace_id: WorkspaceId,
+    form_account_id: AccountId,
+) -> _ {
+    let filter_cond = workspace_members::workspace_id
+        .eq(form_workspace_id)
+        .and(workspace_members::account_id.eq(form_account_id));
+
+    delete(workspace_members::table.filter(filter_cond))
+}
+
 /// Deletes a member from a workspace.
 ///
 /// # Tables
@@ -131,18 +161,50 @@ pub async fn update_workspace_member(
 /// - workspace_members
 pub async fn remove_workspace_member(
     conn: &mut AsyncPgConnection,
-    form_workspace_id: Uuid,
-    form_account_id: Uuid,
+    form_workspace_id: WorkspaceId,
+    form_account_id: AccountId,
 ) -> DatabaseResult<()> {
-    use schema::workspace_members::dsl::*;
-
-    let filter_cond = workspace_id
-        .eq(form_workspace_id)
-        .and(account_id.eq(form_account_id));
-
-    let _query = delete(workspace_members.filter(filter_cond))
+    let _query = remove_workspace_member_query(form_workspace_id, form_account_id)
         .execute(conn)
         .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use axiston_db_schema::enumerations::ProjectRole;
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use uuid::Uuid;
+
+    use crate::workspace_members::*;
+
+    #[test]
+    fn workspace_members_queries() {
+        let form = WorkspaceMemberCreateInput {
+            workspace_id: Uuid::nil().into(),
+            account_id: Uuid::nil().into(),
+            created_by: Uuid::nil().into(),
+            updated_by: Uuid::nil().into(),
+        };
+
+        let query = create_workspace_member_query(&form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "workspace_members" ("workspace_id", "account_id", "created_by", "updated_by") VALUES ($1, $2, $3, $4) -- binds: [WorkspaceId(00000000-0000-0000-0000-000000000000), AccountId(00000000-0000-0000-0000-000000000000), AccountId(00000000-0000-0000-0000-000000000000), AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = get_workspace_member_query(Uuid::nil().into(), Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "workspace_members"."workspace_id", "workspace_members"."account_id", "workspace_members"."show_order", "workspace_members"."is_pinned", "workspace_members"."is_hidden", "workspace_members"."created_by", "workspace_members"."updated_by", "workspace_members"."created_at", "workspace_members"."updated_at" FROM "workspace_members" WHERE (("workspace_members"."workspace_id" = $1) AND ("workspace_members"."account_id" = $2)) -- binds: [WorkspaceId(00000000-0000-0000-0000-000000000000), AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let form = WorkspaceMemberUpdateInput {
+            is_pinned: Some(true),
+            account_role: Some(ProjectRole::Member),
+            ..WorkspaceMemberUpdateInput::default()
+        };
+
+        let query = update_workspace_member_query(Uuid::nil().into(), Uuid::nil().into(), form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workspace_members" SET "is_pinned" = $1, "account_role" = $2 WHERE (("workspace_members"."workspace_id" = $3) AND ("workspace_members"."account_id" = $4)) -- binds: [true, Member, WorkspaceId(00000000-0000-0000-0000-000000000000), AccountId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = remove_workspace_member_query(Uuid::nil().into(), Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"DELETE FROM "workspace_members" WHERE (("workspace_members"."workspace_id" = $1) AND ("workspace_members"."account_id" = $2)) -- binds: [WorkspaceId(00000000-0000-0000-0000-000000000000), AccountId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+}
@@ -1,7 +1,12 @@
 //! Data layer for managing workspace schedules.
 
+use axiston_db_schema::constraints::ConstraintViolation;
+use axiston_db_schema::identifiers::{ScheduleId, WorkspaceId};
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::{workflow_schedules, workflows, workspace_schedules};
 use diesel::dsl::*;
+use diesel::expression::{SqlLiteral, UncheckedBind};
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel::sql_types::{Bool, Timestamptz};
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
@@ -9,9 +14,8 @@ use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use time::OffsetDateTime;
-use uuid::Uuid;
 
-use crate::DatabaseResult;
+use crate::{jsonb, DatabaseResult, Validate};
 
 #[derive(Debug, Clone, Insertable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -19,17 +23,34 @@ use crate::DatabaseResult;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceScheduleCreateInput {
-    pub workspace_id: Uuid,
+    pub workspace_id: WorkspaceId,
     pub metadata: Value,
 }
 
+impl Validate for WorkspaceScheduleCreateInput {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        ConstraintViolation::WorkspaceSchedulesMetadataLimit
+            .check_length(jsonb::text_length(&self.metadata))?;
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Queryable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[diesel(table_name = schema::workspace_schedules)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceScheduleCreateOutput {
-    pub id: Uuid,
+    pub id: ScheduleId,
+}
+
+/// Returns the query used by [`create_workspace_schedule`].
+#[auto_type(no_type_alias)]
+pub fn create_workspace_schedule_query<'a>(schedule_form: &'a WorkspaceScheduleCreateInput) -> _ {
+    insert_into(workspace_schedules::table)
+        .values(schedule_form)
+        .returning((workspace_schedules::id,))
 }
 
 /// Creates a new workspace schedule.
@@ -41,11 +62,7 @@ pub async fn create_workspace_schedule(
     conn: &mut AsyncPgConnection,
     schedule_form: &WorkspaceScheduleCreateInput,
 ) -> DatabaseResult<WorkspaceScheduleCreateOutput> {
-    use schema::workspace_schedules::dsl::*;
-
-    let query = insert_into(workspace_schedules)
-        .values(schedule_form)
-        .returning((id,))
+    let query = create_workspace_schedule_query(schedule_form)
         .get_result(conn)
         .await?;
 
@@ -58,8 +75,8 @@ pub async fn create_workspace_schedule(
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceScheduleViewOutput {
-    pub id: Uuid,
-    pub workspace_id: Uuid,
+    pub id: ScheduleId,
+    pub workspace_id: WorkspaceId,
     pub update_interval: i32,
     pub metadata: Value,
 
@@ -68,6 +85,15 @@ pub struct WorkspaceScheduleViewOutput {
     pub deleted_at: Option<OffsetDateTime>,
 }
 
+/// Returns the query used by [`view_workspace_schedule`].
+#[auto_type(no_type_alias)]
+pub fn view_workspace_schedule_query(schedule_id: ScheduleId) -> _ {
+    let filter_cond = workspace_schedules::id
+        .eq(schedule_id)
+        .and(workspace_schedules::deleted_at.is_null());
+    workspace_schedules::table.filter(filter_cond)
+}
+
 /// Retrieves a workspace schedule by its ID.
 ///
 /// # Tables
@@ -75,19 +101,54 @@ pub struct WorkspaceScheduleViewOutput {
 ///  - workspace_schedules
 pub async fn view_workspace_schedule(
     conn: &mut AsyncPgConnection,
-    schedule_id: Uuid,
+    schedule_id: ScheduleId,
 ) -> DatabaseResult<WorkspaceScheduleViewOutput> {
-    use schema::workspace_schedules::dsl::*;
-
-    let filter_cond = id.eq(schedule_id).and(deleted_at.is_null());
-    let query = workspace_schedules
-        .filter(filter_cond)
+    let query = view_workspace_schedule_query(schedule_id)
         .get_result(conn)
         .await?;
 
     Ok(query)
 }
 
+/// Returns the query used by [`view_workflows_by_interval`] to lock the batch.
+#[auto_type(no_type_alias)]
+pub fn view_workflows_by_interval_query(max_batch_size: i64, max_timeout: OffsetDateTime) -> _ {
+    let filter_future_schedules: UncheckedBind<
+        SqlLiteral<Bool>,
+        AsExprOf<OffsetDateTime, Timestamptz>,
+    > = sql::<Bool>(
+        "(workflows.updated_at + workspace_schedules.update_interval * interval '1 second') <= ",
+    )
+    .bind::<Timestamptz, _>(max_timeout);
+
+    let order_by_interval: SqlLiteral<Timestamptz> = sql::<Timestamptz>(
+        "workflows.updated_at + workspace_schedules.update_interval * interval '1 second' ASC",
+    );
+
+    let selection: AsSelect<WorkspaceScheduleViewOutput, Pg> =
+        WorkspaceScheduleViewOutput::as_select();
+    workflows::table
+        .inner_join(workflow_schedules::table.on(workflow_schedules::workflow_id.eq(workflows::id)))
+        .inner_join(
+            workspace_schedules::table
+                .on(workspace_schedules::id.eq(workflow_schedules::schedule_id)),
+        )
+        .filter(workflows::deleted_at.is_null())
+        .filter(filter_future_schedules)
+        .order_by(order_by_interval)
+        .select(selection)
+        .limit(max_batch_size)
+        .for_update()
+}
+
+/// Returns the query used by [`view_workflows_by_interval`] to touch the batch.
+#[auto_type(no_type_alias)]
+pub fn touch_workspace_schedules_query(schedule_ids: Vec<ScheduleId>) -> _ {
+    update(workspace_schedules::table)
+        .filter(workspace_schedules::id.eq_any(schedule_ids))
+        .set(workspace_schedules::updated_at.eq(now))
+}
+
 /// Retrieves a batch of workflow schedules.
 ///
 /// The batch contains the first n rows in the table sorted
@@ -103,35 +164,12 @@ pub async fn view_workflows_by_interval(
     max_batch_size: i64,
     max_timeout: OffsetDateTime,
 ) -> DatabaseResult<Vec<WorkspaceScheduleViewOutput>> {
-    use schema::workflow_schedules::dsl as wfs_dsl;
-    use schema::workflows::dsl as wf_dsl;
-    use schema::workspace_schedules::dsl as wss_dsl;
-
-    let filter_future_schedules = sql::<Bool>(
-        "(workflows.updated_at + workspace_schedules.update_interval * interval '1 second') <= $1",
-    )
-    .bind::<Timestamptz, _>(max_timeout);
-
-    let order_by_interval = sql::<Timestamptz>(
-        "workflows.updated_at + workspace_schedules.update_interval * interval '1 second' ASC",
-    );
-
-    let queries = wf_dsl::workflows
-        .inner_join(wfs_dsl::workflow_schedules.on(wfs_dsl::workflow_id.eq(wf_dsl::id)))
-        .inner_join(wss_dsl::workspace_schedules.on(wss_dsl::id.eq(wfs_dsl::schedule_id)))
-        .filter(wf_dsl::deleted_at.is_null())
-        .filter(filter_future_schedules)
-        .order_by(order_by_interval)
-        .select(WorkspaceScheduleViewOutput::as_select())
-        .limit(max_batch_size)
-        .for_update()
+    let queries = view_workflows_by_interval_query(max_batch_size, max_timeout)
         .get_results(conn)
         .await?;
 
     let schedule_ids: Vec<_> = queries.iter().map(|ws| ws.id).collect();
-    let _query = update(wss_dsl::workspace_schedules)
-        .filter(wss_dsl::id.eq_any(schedule_ids))
-        .set(wss_dsl::updated_at.eq(now))
+    let _query = touch_workspace_schedules_query(schedule_ids)
         .execute(conn)
         .await?;
 
@@ -147,6 +185,29 @@ pub struct WorkspaceScheduleUpdateInput {
     pub metadata: Option<Value>,
 }
 
+impl Validate for WorkspaceScheduleUpdateInput {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        if let Some(x) = &self.metadata {
+            ConstraintViolation::WorkspaceSchedulesMetadataLimit
+                .check_length(jsonb::text_length(x))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the query used by [`update_workspace_schedule`].
+#[auto_type(no_type_alias)]
+pub fn update_workspace_schedule_query(
+    schedule_id: ScheduleId,
+    form: WorkspaceScheduleUpdateInput,
+) -> _ {
+    let filter_cond = workspace_schedules::id
+        .eq(schedule_id)
+        .and(workspace_schedules::deleted_at.is_null());
+    update(workspace_schedules::table.filter(filter_cond)).set(form)
+}
+
 /// Updates a workspace schedule.
 ///
 /// # Tables
@@ -154,20 +215,26 @@ pub struct WorkspaceScheduleUpdateInput {
 ///  - workspace_schedules
 pub async fn update_workspace_schedule(
     conn: &mut AsyncPgConnection,
-    schedule_id: Uuid,
+    schedule_id: ScheduleId,
     form: WorkspaceScheduleUpdateInput,
 ) -> DatabaseResult<()> {
-    use schema::workspace_schedules::dsl::*;
-
-    let filter_cond = id.eq(schedule_id).and(deleted_at.is_null());
-    update(workspace_schedules.filter(filter_cond))
-        .set(form)
+    update_workspace_schedule_query(schedule_id, form)
         .execute(conn)
         .await?;
 
     Ok(())
 }
 
+/// Returns the query used by [`delete_workspace_schedule`].
+#[auto_type(no_type_alias)]
+pub fn delete_workspace_schedule_query(schedule_id: ScheduleId) -> _ {
+    let filter_cond = workspace_schedules::id
+        .eq(schedule_id)
+        .and(workspace_schedules::deleted_at.is_null());
+    update(workspace_schedules::table.filter(filter_cond))
+        .set(workspace_schedules::deleted_at.eq(now))
+}
+
 /// Flags the workspace schedule as deleted.
 ///
 /// # Tables
@@ -175,15 +242,76 @@ pub async fn update_workspace_schedule(
 ///  - workspace_schedules
 pub async fn delete_workspace_schedule(
     conn: &mut AsyncPgConnection,
-    schedule_id: Uuid,
+    schedule_id: ScheduleId,
 ) -> DatabaseResult<()> {
-    use schema::workspace_schedules::dsl::*;
-
-    let filter_cond = id.eq(schedule_id).and(deleted_at.is_null());
-    update(workspace_schedules.filter(filter_cond))
-        .set(deleted_at.eq(now))
+    delete_workspace_schedule_query(schedule_id)
         .execute(conn)
         .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use serde_json::Value;
+    use time::{Duration, OffsetDateTime};
+    use uuid::Uuid;
+
+    use crate::testing::{ScheduleFactory, TestDatabase, WorkflowFactory, WorkspaceFactory};
+    use crate::workflow_schedules::create_all_workflow_schedules;
+    use crate::workspace_schedules::*;
+
+    #[test]
+    fn workspace_schedules_queries() {
+        let form = WorkspaceScheduleCreateInput {
+            workspace_id: Uuid::nil().into(),
+            metadata: Value::default(),
+        };
+
+        let query = create_workspace_schedule_query(&form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "workspace_schedules" ("workspace_id", "metadata") VALUES ($1, $2) RETURNING "workspace_schedules"."id" -- binds: [WorkspaceId(00000000-0000-0000-0000-000000000000), Null]"#);
+
+        let query = view_workspace_schedule_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "workspace_schedules"."id", "workspace_schedules"."workspace_id", "workspace_schedules"."update_interval", "workspace_schedules"."metadata", "workspace_schedules"."created_at", "workspace_schedules"."updated_at", "workspace_schedules"."deleted_at" FROM "workspace_schedules" WHERE (("workspace_schedules"."id" = $1) AND ("workspace_schedules"."deleted_at" IS NULL)) -- binds: [ScheduleId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = view_workflows_by_interval_query(10, OffsetDateTime::UNIX_EPOCH);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "workspace_schedules"."id", "workspace_schedules"."workspace_id", "workspace_schedules"."update_interval", "workspace_schedules"."metadata", "workspace_schedules"."created_at", "workspace_schedules"."updated_at", "workspace_schedules"."deleted_at" FROM (("workflows" INNER JOIN "workflow_schedules" ON ("workflow_schedules"."workflow_id" = "workflows"."id")) INNER JOIN "workspace_schedules" ON ("workspace_schedules"."id" = "workflow_schedules"."schedule_id")) WHERE (("workflows"."deleted_at" IS NULL) AND (workflows.updated_at + workspace_schedules.update_interval * interval '1 second') <= $1) ORDER BY workflows.updated_at + workspace_schedules.update_interval * interval '1 second' ASC LIMIT $2 FOR UPDATE -- binds: [1970-01-01 0:00:00.0 +00:00:00, 10]"#);
+
+        let query = touch_workspace_schedules_query(vec![Uuid::nil().into()]);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workspace_schedules" SET "updated_at" = CURRENT_TIMESTAMP WHERE ("workspace_schedules"."id" = ANY($1)) -- binds: [[ScheduleId(00000000-0000-0000-0000-000000000000)]]"#);
+
+        let form = WorkspaceScheduleUpdateInput {
+            metadata: Some(Value::default()),
+        };
+
+        let query = update_workspace_schedule_query(Uuid::nil().into(), form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workspace_schedules" SET "metadata" = $1 WHERE (("workspace_schedules"."id" = $2) AND ("workspace_schedules"."deleted_at" IS NULL)) -- binds: [Null, ScheduleId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = delete_workspace_schedule_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workspace_schedules" SET "deleted_at" = CURRENT_TIMESTAMP WHERE (("workspace_schedules"."id" = $1) AND ("workspace_schedules"."deleted_at" IS NULL)) -- binds: [ScheduleId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+
+    #[tokio::test]
+    async fn view_workflows_by_interval_batch() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let workspace = WorkspaceFactory::new().create(&mut conn).await?;
+        let workflow = WorkflowFactory::new()
+            .workspace_id(workspace.id)
+            .create(&mut conn)
+            .await?;
+        let schedule = ScheduleFactory::new()
+            .workspace_id(workspace.id)
+            .create(&mut conn)
+            .await?;
+
+        create_all_workflow_schedules(&mut conn, workflow.id, vec![schedule.id]).await?;
+        let max_timeout = OffsetDateTime::now_utc() + Duration::days(365);
+        let output = view_workflows_by_interval(&mut conn, 10, max_timeout).await?;
+        let output: Vec<_> = output.into_iter().map(|x| x.id).collect();
+        assert_eq!(output, vec![schedule.id]);
+        Ok(())
+    }
+}
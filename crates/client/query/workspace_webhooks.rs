@@ -1,6 +1,9 @@
 //! Data layer for managing workspace webhooks.
 
+use axiston_db_schema::constraints::ConstraintViolation;
+use axiston_db_schema::identifiers::{WebhookId, WorkspaceId};
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::workspace_webhooks;
 use diesel::dsl::*;
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
@@ -8,9 +11,8 @@ use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use time::OffsetDateTime;
-use uuid::Uuid;
 
-use crate::DatabaseResult;
+use crate::{jsonb, DatabaseResult, Validate};
 
 #[derive(Debug, Clone, Insertable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -18,18 +20,27 @@ use crate::DatabaseResult;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceWebhookCreateInput {
-    pub workspace_id: Uuid,
+    pub workspace_id: WorkspaceId,
     pub metadata: Value,
 }
 
+impl Validate for WorkspaceWebhookCreateInput {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        ConstraintViolation::WorkspaceWebhooksMetadataLimit
+            .check_length(jsonb::text_length(&self.metadata))?;
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Queryable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[diesel(table_name = schema::workspace_webhooks)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceWebhookCreateOutput {
-    pub id: Uuid,
-    pub workspace_id: Uuid,
+    pub id: WebhookId,
+    pub workspace_id: WorkspaceId,
     pub metadata: Value,
 
     pub created_at: OffsetDateTime,
@@ -37,6 +48,21 @@ pub struct WorkspaceWebhookCreateOutput {
     pub deleted_at: Option<OffsetDateTime>,
 }
 
+/// Returns the query used by [`create_workspace_webhook`].
+#[auto_type(no_type_alias)]
+pub fn create_workspace_webhook_query<'a>(form: &'a WorkspaceWebhookCreateInput) -> _ {
+    insert_into(workspace_webhooks::table)
+        .values(form)
+        .returning((
+            workspace_webhooks::id,
+            workspace_webhooks::workspace_id,
+            workspace_webhooks::metadata,
+            workspace_webhooks::created_at,
+            workspace_webhooks::updated_at,
+            workspace_webhooks::deleted_at,
+        ))
+}
+
 /// Creates a new workspace webhook.
 ///
 /// # Tables
@@ -46,24 +72,22 @@ pub async fn create_workspace_webhook(
     conn: &mut AsyncPgConnection,
     form: &WorkspaceWebhookCreateInput,
 ) -> DatabaseResult<WorkspaceWebhookCreateOutput> {
-    use schema::workspace_webhooks::dsl::*;
-
-    let query = insert_into(workspace_webhooks)
-        .values(form)
-        .returning((
-            id,
-            workspace_id,
-            metadata,
-            created_at,
-            updated_at,
-            deleted_at,
-        ))
+    let query = create_workspace_webhook_query(form)
         .get_result(conn)
         .await?;
 
     Ok(query)
 }
 
+/// Returns the query used by [`view_workspace_webhook`].
+#[auto_type(no_type_alias)]
+pub fn view_workspace_webhook_query(webhook_id: WebhookId) -> _ {
+    let filter_cond = workspace_webhooks::id
+        .eq(webhook_id)
+        .and(workspace_webhooks::deleted_at.is_null());
+    workspace_webhooks::table.filter(filter_cond)
+}
+
 /// Retrieves a workspace webhook by its ID.
 ///
 /// # Tables
@@ -71,13 +95,9 @@ pub async fn create_workspace_webhook(
 ///  - workspace_webhooks
 pub async fn view_workspace_webhook(
     conn: &mut AsyncPgConnection,
-    webhook_id: Uuid,
+    webhook_id: WebhookId,
 ) -> DatabaseResult<WorkspaceWebhookCreateOutput> {
-    use schema::workspace_webhooks::dsl::*;
-
-    let filter_cond = id.eq(webhook_id).and(deleted_at.is_null());
-    let query = workspace_webhooks
-        .filter(filter_cond)
+    let query = view_workspace_webhook_query(webhook_id)
         .get_result(conn)
         .await?;
 
@@ -93,6 +113,29 @@ pub struct WorkspaceWebhookUpdateInput {
     pub metadata: Option<Value>,
 }
 
+impl Validate for WorkspaceWebhookUpdateInput {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        if let Some(x) = &self.metadata {
+            ConstraintViolation::WorkspaceWebhooksMetadataLimit
+                .check_length(jsonb::text_length(x))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the query used by [`update_workspace_webhook`].
+#[auto_type(no_type_alias)]
+pub fn update_workspace_webhook_query(
+    webhook_id: WebhookId,
+    form: WorkspaceWebhookUpdateInput,
+) -> _ {
+    let filter_cond = workspace_webhooks::id
+        .eq(webhook_id)
+        .and(workspace_webhooks::deleted_at.is_null());
+    update(workspace_webhooks::table.filter(filter_cond)).set(form)
+}
+
 /// Updates a workspace webhook.
 ///
 /// # Tables
@@ -100,20 +143,26 @@ pub struct WorkspaceWebhookUpdateInput {
 ///  - workspace_webhooks
 pub async fn update_workspace_webhook(
     conn: &mut AsyncPgConnection,
-    webhook_id: Uuid,
+    webhook_id: WebhookId,
     form: WorkspaceWebhookUpdateInput,
 ) -> DatabaseResult<()> {
-    use schema::workspace_webhooks::dsl::*;
-
-    let filter_cond = id.eq(webhook_id).and(deleted_at.is_null());
-    update(workspace_webhooks.filter(filter_cond))
-        .set(form)
+    update_workspace_webhook_query(webhook_id, form)
         .execute(conn)
         .await?;
 
     Ok(())
 }
 
+/// Returns the query used by [`delete_workspace_webhook`].
+#[auto_type(no_type_alias)]
+pub fn delete_workspace_webhook_query(webhook_id: WebhookId) -> _ {
+    let filter_cond = workspace_webhooks::id
+        .eq(webhook_id)
+        .and(workspace_webhooks::deleted_at.is_null());
+    update(workspace_webhooks::table.filter(filter_cond))
+        .set(workspace_webhooks::deleted_at.eq(now))
+}
+
 /// Flags the workspace webhook as deleted.
 ///
 /// # Tables
@@ -121,15 +170,45 @@ pub async fn update_workspace_webhook(
 ///  - workspace_webhooks
 pub async fn delete_workspace_webhook(
     conn: &mut AsyncPgConnection,
-    webhook_id: Uuid,
+    webhook_id: WebhookId,
 ) -> DatabaseResult<()> {
-    use schema::workspace_webhooks::dsl::*;
-
-    let filter_cond = id.eq(webhook_id).and(deleted_at.is_null());
-    update(workspace_webhooks.filter(filter_cond))
-        .set(deleted_at.eq(now))
+    delete_workspace_webhook_query(webhook_id)
         .execute(conn)
         .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use crate::workspace_webhooks::*;
+
+    #[test]
+    fn workspace_webhooks_queries() {
+        let form = WorkspaceWebhookCreateInput {
+            workspace_id: Uuid::nil().into(),
+            metadata: Value::default(),
+        };
+
+        let query = create_workspace_webhook_query(&form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "workspace_webhooks" ("workspace_id", "metadata") VALUES ($1, $2) RETURNING "workspace_webhooks"."id", "workspace_webhooks"."workspace_id", "workspace_webhooks"."metadata", "workspace_webhooks"."created_at", "workspace_webhooks"."updated_at", "workspace_webhooks"."deleted_at" -- binds: [WorkspaceId(00000000-0000-0000-0000-000000000000), Null]"#);
+
+        let query = view_workspace_webhook_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "workspace_webhooks"."id", "workspace_webhooks"."workspace_id", "workspace_webhooks"."metadata", "workspace_webhooks"."created_at", "workspace_webhooks"."updated_at", "workspace_webhooks"."deleted_at" FROM "workspace_webhooks" WHERE (("workspace_webhooks"."id" = $1) AND ("workspace_webhooks"."deleted_at" IS NULL)) -- binds: [WebhookId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let form = WorkspaceWebhookUpdateInput {
+            metadata: Some(Value::default()),
+        };
+
+        let query = update_workspace_webhook_query(Uuid::nil().into(), form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workspace_webhooks" SET "metadata" = $1 WHERE (("workspace_webhooks"."id" = $2) AND ("workspace_webhooks"."deleted_at" IS NULL)) -- binds: [Null, WebhookId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = delete_workspace_webhook_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workspace_webhooks" SET "deleted_at" = CURRENT_TIMESTAMP WHERE (("workspace_webhooks"."id" = $1) AND ("workspace_webhooks"."deleted_at" IS NULL)) -- binds: [WebhookId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+}
@@ -1,16 +1,21 @@
 //! Data layer for workspace management.
 
+use std::borrow::Cow;
+
+use axiston_db_schema::constraints::ConstraintViolation;
+use axiston_db_schema::identifiers::WorkspaceId;
 use axiston_db_schema::schema;
+use axiston_db_schema::schema::workspaces;
 use diesel::dsl::*;
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use time::OffsetDateTime;
-use uuid::Uuid;
 
-use crate::DatabaseResult;
+use crate::{jsonb, DatabaseResult, Validate};
 
 #[derive(Debug, Clone, Insertable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -18,23 +23,46 @@ use crate::DatabaseResult;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceCreateInput<'a> {
-    pub display_name: &'a str,
+    pub display_name: Cow<'a, str>,
     pub metadata: Value,
 }
 
+impl Validate for WorkspaceCreateInput<'_> {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        ConstraintViolation::WorkspacesMetadataLimit
+            .check_length(jsonb::text_length(&self.metadata))?;
+        ConstraintViolation::WorkspacesNonEmptyDisplayName.check_text(&self.display_name)?;
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Queryable)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[diesel(table_name = schema::workspaces)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceCreateOutput {
-    pub id: Uuid,
+    pub id: WorkspaceId,
 
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
     pub deleted_at: Option<OffsetDateTime>,
 }
 
+/// Returns the query used by [`create_workspace`].
+#[auto_type(no_type_alias)]
+pub fn create_workspace_query<'a>(workspace_form: &'a WorkspaceCreateInput<'a>) -> _ {
+    insert_into(workspaces::table)
+        .values(workspace_form)
+        .returning((
+            workspaces::id,
+            workspaces::created_at,
+            workspaces::updated_at,
+            workspaces::deleted_at,
+        ))
+}
+
 /// Creates a new workspace and returns its details.
 ///
 /// # Tables
@@ -44,11 +72,7 @@ pub async fn create_workspace(
     conn: &mut AsyncPgConnection,
     workspace_form: &WorkspaceCreateInput<'_>,
 ) -> DatabaseResult<WorkspaceCreateOutput> {
-    use schema::workspaces::dsl::*;
-
-    let query = insert_into(workspaces)
-        .values(workspace_form)
-        .returning((id, created_at, updated_at, deleted_at))
+    let query = create_workspace_query(workspace_form)
         .get_result(conn)
         .await?;
 
@@ -61,7 +85,7 @@ pub async fn create_workspace(
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceViewOutput {
-    pub id: Uuid,
+    pub id: WorkspaceId,
     pub display_name: String,
     pub metadata: Value,
 
@@ -70,6 +94,19 @@ pub struct WorkspaceViewOutput {
     pub deleted_at: Option<OffsetDateTime>,
 }
 
+/// Returns the query used by [`view_workspace`].
+#[auto_type(no_type_alias)]
+pub fn view_workspace_query(form_workspace_id: WorkspaceId) -> _ {
+    let filter_cond = workspaces::id
+        .eq(form_workspace_id)
+        .and(workspaces::deleted_at.is_null());
+    let selection: AsSelect<WorkspaceViewOutput, Pg> = WorkspaceViewOutput::as_select();
+    workspaces::table
+        .filter(filter_cond)
+        .select(selection)
+        .limit(1i64)
+}
+
 /// Retrieves a workspace by its unique ID.
 ///
 /// # Tables
@@ -77,15 +114,9 @@ pub struct WorkspaceViewOutput {
 /// - workspaces
 pub async fn view_workspace(
     conn: &mut AsyncPgConnection,
-    form_workspace_id: Uuid,
+    form_workspace_id: WorkspaceId,
 ) -> DatabaseResult<WorkspaceViewOutput> {
-    use schema::workspaces::dsl::*;
-
-    let filter_cond = id.eq(form_workspace_id).and(deleted_at.is_null());
-    let query = workspaces
-        .filter(filter_cond)
-        .select(WorkspaceViewOutput::as_select())
-        .limit(1)
+    let query = view_workspace_query(form_workspace_id)
         .get_result(conn)
         .await?;
 
@@ -98,8 +129,33 @@ pub async fn view_workspace(
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[must_use = "forms do nothing unless you use them"]
 pub struct WorkspaceUpdateInput<'a> {
-    pub display_name: Option<&'a str>,
-    pub metadata: Value,
+    pub display_name: Option<Cow<'a, str>>,
+    pub metadata: Option<Value>,
+}
+
+impl Validate for WorkspaceUpdateInput<'_> {
+    fn validate(&self) -> Result<(), ConstraintViolation> {
+        if let Some(x) = &self.metadata {
+            ConstraintViolation::WorkspacesMetadataLimit.check_length(jsonb::text_length(x))?;
+        }
+        if let Some(x) = &self.display_name {
+            ConstraintViolation::WorkspacesNonEmptyDisplayName.check_text(x)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the query used by [`update_workspace`].
+#[auto_type(no_type_alias)]
+pub fn update_workspace_query<'a>(
+    form_workspace_id: WorkspaceId,
+    form: WorkspaceUpdateInput<'a>,
+) -> _ {
+    let filter_cond = workspaces::id
+        .eq(form_workspace_id)
+        .and(workspaces::deleted_at.is_null());
+    update(workspaces::table.filter(filter_cond)).set(form)
 }
 
 /// Updates a workspace's details.
@@ -109,20 +165,25 @@ pub struct WorkspaceUpdateInput<'a> {
 /// - workspaces
 pub async fn update_workspace(
     conn: &mut AsyncPgConnection,
-    form_workspace_id: Uuid,
+    form_workspace_id: WorkspaceId,
     form: WorkspaceUpdateInput<'_>,
 ) -> DatabaseResult<()> {
-    use schema::workspaces::dsl::*;
-
-    let filter_cond = id.eq(form_workspace_id).and(deleted_at.is_null());
-    let _query = update(workspaces.filter(filter_cond))
-        .set(form)
+    let _query = update_workspace_query(form_workspace_id, form)
         .execute(conn)
         .await?;
 
     Ok(())
 }
 
+/// Returns the query used by [`delete_workspace`].
+#[auto_type(no_type_alias)]
+pub fn delete_workspace_query(form_workspace_id: WorkspaceId) -> _ {
+    let filter_cond = workspaces::id
+        .eq(form_workspace_id)
+        .and(workspaces::deleted_at.is_null());
+    update(workspaces::table.filter(filter_cond)).set(workspaces::deleted_at.eq(now))
+}
+
 /// Flags the specified workspace as deleted.
 ///
 /// # Tables
@@ -130,15 +191,46 @@ pub async fn update_workspace(
 /// - workspaces
 pub async fn delete_workspace(
     conn: &mut AsyncPgConnection,
-    form_workspace_id: Uuid,
+    form_workspace_id: WorkspaceId,
 ) -> DatabaseResult<()> {
-    use schema::workspaces::dsl::*;
-
-    let filter_cond = id.eq(form_workspace_id).and(deleted_at.is_null());
-    let _query = update(workspaces.filter(filter_cond))
-        .set(deleted_at.eq(now))
+    let _query = delete_workspace_query(form_workspace_id)
         .execute(conn)
         .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use diesel::debug_query;
+    use diesel::pg::Pg;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use crate::workspaces::*;
+
+    #[test]
+    fn workspaces_queries() {
+        let form = WorkspaceCreateInput {
+            display_name: "Workspace".into(),
+            metadata: Value::default(),
+        };
+
+        let query = create_workspace_query(&form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"INSERT INTO "workspaces" ("display_name", "metadata") VALUES ($1, $2) RETURNING "workspaces"."id", "workspaces"."created_at", "workspaces"."updated_at", "workspaces"."deleted_at" -- binds: ["Workspace", Null]"#);
+
+        let query = view_workspace_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"SELECT "workspaces"."id", "workspaces"."display_name", "workspaces"."metadata", "workspaces"."created_at", "workspaces"."updated_at", "workspaces"."deleted_at" FROM "workspaces" WHERE (("workspaces"."id" = $1) AND ("workspaces"."deleted_at" IS NULL)) LIMIT $2 -- binds: [WorkspaceId(00000000-0000-0000-0000-000000000000), 1]"#);
+
+        let form = WorkspaceUpdateInput {
+            display_name: Some("Workspace".into()),
+            metadata: Some(Value::default()),
+        };
+
+        let query = update_workspace_query(Uuid::nil().into(), form);
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workspaces" SET "display_name" = $1, "metadata" = $2 WHERE (("workspaces"."id" = $3) AND ("workspaces"."deleted_at" IS NULL)) -- binds: ["Workspace", Null, WorkspaceId(00000000-0000-0000-0000-000000000000)]"#);
+
+        let query = delete_workspace_query(Uuid::nil().into());
+        insta::assert_snapshot!(debug_query::<Pg, _>(&query), @r#"UPDATE "workspaces" SET "deleted_at" = CURRENT_TIMESTAMP WHERE (("workspaces"."id" = $1) AND ("workspaces"."deleted_at" IS NULL)) -- binds: [WorkspaceId(00000000-0000-0000-0000-000000000000)]"#);
+    }
+}
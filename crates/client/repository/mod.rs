@@ -0,0 +1,334 @@
+//! Repository traits over the data layer.
+//!
+//! [`Database`] implements all repositories with the query functions,
+//! and the in-memory fake (see the `testing` feature) mimics their behavior.
+//!
+//! Only entity tables (accounts, workspaces and workflows) are covered.
+//! Sessions, tokens, permissions and executions rely on the database for
+//! expiry, hashing and row locking, which the fake can't mimic faithfully,
+//! so they are used through the query functions and tested against the
+//! `TestDatabase` instead.
+
+use axiston_db_schema::identifiers::{AccountId, WorkflowId, WorkspaceId};
+
+use crate::accounts::{
+    AccountCreateInput, AccountCreateOutput, AccountUpdateInput, AccountViewOutput,
+};
+use crate::workflows::{
+    WorkflowCreateInput, WorkflowCreateOutput, WorkflowUpdateInput, WorkflowViewOutput,
+};
+use crate::workspaces::{
+    WorkspaceCreateInput, WorkspaceCreateOutput, WorkspaceUpdateInput, WorkspaceViewOutput,
+};
+use crate::{accounts, workflows, workspaces, Database, DatabaseResult};
+
+/// Repository of the `accounts` table.
+///
+/// See the [`accounts`] module for more details.
+pub trait AccountRepository {
+    /// Creates the new account and returns its ID.
+    async fn create_account(
+        &self,
+        form: &AccountCreateInput<'_>,
+    ) -> DatabaseResult<AccountCreateOutput>;
+
+    /// Returns the account data by its ID.
+    async fn view_account(&self, account_id: AccountId) -> DatabaseResult<AccountViewOutput>;
+
+    /// Updates the account with provided data.
+    async fn update_account(
+        &self,
+        account_id: AccountId,
+        form: AccountUpdateInput<'_>,
+    ) -> DatabaseResult<()>;
+
+    /// Flags the account as deleted.
+    async fn delete_account(&self, account_id: AccountId) -> DatabaseResult<()>;
+}
+
+/// Repository of the `workspaces` table.
+///
+/// See the [`workspaces`] module for more details.
+pub trait WorkspaceRepository {
+    /// Creates a new workspace and returns its details.
+    async fn create_workspace(
+        &self,
+        form: &WorkspaceCreateInput<'_>,
+    ) -> DatabaseResult<WorkspaceCreateOutput>;
+
+    /// Retrieves a workspace by its unique ID.
+    async fn view_workspace(
+        &self,
+        workspace_id: WorkspaceId,
+    ) -> DatabaseResult<WorkspaceViewOutput>;
+
+    /// Updates a workspace's details.
+    async fn update_workspace(
+        &self,
+        workspace_id: WorkspaceId,
+        form: WorkspaceUpdateInput<'_>,
+    ) -> DatabaseResult<()>;
+
+    /// Flags the specified workspace as deleted.
+    async fn delete_workspace(&self, workspace_id: WorkspaceId) -> DatabaseResult<()>;
+}
+
+/// Repository of the `workflows` table.
+///
+/// See the [`workflows`] module for more details.
+pub trait WorkflowRepository {
+    /// Creates a new workflow in the database.
+    async fn create_workflow(
+        &self,
+        form: &WorkflowCreateInput<'_>,
+    ) -> DatabaseResult<WorkflowCreateOutput>;
+
+    /// Returns the workflow data by its ID.
+    async fn view_workflow(&self, workflow_id: WorkflowId) -> DatabaseResult<WorkflowViewOutput>;
+
+    /// Updates the workflow with provided data.
+    async fn update_workflow(
+        &self,
+        workflow_id: WorkflowId,
+        form: WorkflowUpdateInput<'_>,
+    ) -> DatabaseResult<()>;
+
+    /// Flags a specified workflow as deleted.
+    async fn delete_workflow(&self, workflow_id: WorkflowId) -> DatabaseResult<()>;
+}
+
+impl AccountRepository for Database {
+    async fn create_account(
+        &self,
+        form: &AccountCreateInput<'_>,
+    ) -> DatabaseResult<AccountCreateOutput> {
+        let mut conn = self.get_connection().await?;
+        accounts::create_account(&mut conn, form).await
+    }
+
+    async fn view_account(&self, account_id: AccountId) -> DatabaseResult<AccountViewOutput> {
+        let mut conn = self.get_connection().await?;
+        accounts::view_account(&mut conn, account_id).await
+    }
+
+    async fn update_account(
+        &self,
+        account_id: AccountId,
+        form: AccountUpdateInput<'_>,
+    ) -> DatabaseResult<()> {
+        let mut conn = self.get_connection().await?;
+        accounts::update_account(&mut conn, account_id, form).await
+    }
+
+    async fn delete_account(&self, account_id: AccountId) -> DatabaseResult<()> {
+        let mut conn = self.get_connection().await?;
+        accounts::delete_account(&mut conn, account_id).await
+    }
+}
+
+impl WorkspaceRepository for Database {
+    async fn create_workspace(
+        &self,
+        form: &WorkspaceCreateInput<'_>,
+    ) -> DatabaseResult<WorkspaceCreateOutput> {
+        let mut conn = self.get_connection().await?;
+        workspaces::create_workspace(&mut conn, form).await
+    }
+
+    async fn view_workspace(
+        &self,
+        workspace_id: WorkspaceId,
+    ) -> DatabaseResult<WorkspaceViewOutput> {
+        let mut conn = self.get_connection().await?;
+        workspaces::view_workspace(&mut conn, workspace_id).await
+    }
+
+    async fn update_workspace(
+        &self,
+        workspace_id: WorkspaceId,
+        form: WorkspaceUpdateInput<'_>,
+    ) -> DatabaseResult<()> {
+        let mut conn = self.get_connection().await?;
+        workspaces::update_workspace(&mut conn, workspace_id, form).await
+    }
+
+    async fn delete_workspace(&self, workspace_id: WorkspaceId) -> DatabaseResult<()> {
+        let mut conn = self.get_connection().await?;
+        workspaces::delete_workspace(&mut conn, workspace_id).await
+    }
+}
+
+impl WorkflowRepository for Database {
+    async fn create_workflow(
+        &self,
+        form: &WorkflowCreateInput<'_>,
+    ) -> DatabaseResult<WorkflowCreateOutput> {
+        let mut conn = self.get_connection().await?;
+        workflows::create_workflow(&mut conn, form).await
+    }
+
+    async fn view_workflow(&self, workflow_id: WorkflowId) -> DatabaseResult<WorkflowViewOutput> {
+        let mut conn = self.get_connection().await?;
+        workflows::view_workflows_by_id(&mut conn, workflow_id).await
+    }
+
+    async fn update_workflow(
+        &self,
+        workflow_id: WorkflowId,
+        form: WorkflowUpdateInput<'_>,
+    ) -> DatabaseResult<()> {
+        let mut conn = self.get_connection().await?;
+        workflows::update_workflow(&mut conn, workflow_id, form).await
+    }
+
+    async fn delete_workflow(&self, workflow_id: WorkflowId) -> DatabaseResult<()> {
+        let mut conn = self.get_connection().await?;
+        workflows::delete_workflow(&mut conn, workflow_id).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axiston_db_schema::identifiers::WorkspaceId;
+    use diesel::result::Error;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use crate::accounts::{AccountCreateInput, AccountUpdateInput};
+    use crate::repository::{AccountRepository, WorkflowRepository, WorkspaceRepository};
+    use crate::testing::{InMemoryDatabase, TestDatabase};
+    use crate::workflows::WorkflowCreateInput;
+    use crate::workspaces::{WorkspaceCreateInput, WorkspaceUpdateInput};
+    use crate::{DatabaseError, DatabaseResult};
+
+    /// Returns the kind and name of the violated constraint, if any.
+    fn constraint<T>(result: DatabaseResult<T>) -> Option<String> {
+        match result {
+            Err(DatabaseError::Query(Error::DatabaseError(kind, info))) => {
+                Some(format!("{kind:?} {}", info.constraint_name()?))
+            }
+            _ => None,
+        }
+    }
+
+    /// Runs the same scenario against any repository implementation.
+    async fn check_repository<R>(repository: &R) -> DatabaseResult<()>
+    where
+        R: AccountRepository + WorkspaceRepository + WorkflowRepository,
+    {
+        let email_address = format!("{}@example.com", Uuid::new_v4());
+        let form = AccountCreateInput {
+            display_name: "Account".into(),
+            email_address: email_address.as_str().into(),
+            password_hash: "hash".into(),
+        };
+
+        let account = repository.create_account(&form).await?;
+        let duplicate = constraint(repository.create_account(&form).await);
+        let expected = "UniqueViolation accounts_email_address_idx";
+        assert_eq!(duplicate.as_deref(), Some(expected));
+
+        let email_address = format!(" {} ", email_address.to_uppercase());
+        let form = AccountCreateInput {
+            email_address: email_address.as_str().into(),
+            ..form
+        };
+
+        let duplicate = constraint(repository.create_account(&form).await);
+        assert_eq!(duplicate.as_deref(), Some(expected));
+
+        let form_update = AccountUpdateInput {
+            display_name: Some("".into()),
+            email_address: None,
+            password_hash: None,
+        };
+
+        let empty = constraint(repository.update_account(account.id, form_update).await);
+        let expected = "CheckViolation accounts_non_empty_display_name";
+        assert_eq!(empty.as_deref(), Some(expected));
+
+        repository.delete_account(account.id).await?;
+        let deleted = repository.view_account(account.id).await;
+        assert!(matches!(
+            deleted,
+            Err(DatabaseError::Query(Error::NotFound))
+        ));
+        let _ = repository.create_account(&form).await?;
+
+        let form = WorkspaceCreateInput {
+            display_name: "Workspace".into(),
+            metadata: json!({ "name": "Workspace" }),
+        };
+
+        let workspace = repository.create_workspace(&form).await?;
+        let form_update = WorkspaceUpdateInput {
+            display_name: Some("Renamed".into()),
+            metadata: None,
+        };
+
+        repository
+            .update_workspace(workspace.id, form_update)
+            .await?;
+        let output = repository.view_workspace(workspace.id).await?;
+        assert_eq!(output.display_name, "Renamed");
+        assert_eq!(output.metadata, json!({ "name": "Workspace" }));
+
+        let form_update = WorkspaceUpdateInput {
+            display_name: None,
+            metadata: Some(Value::String("x".repeat(2048))),
+        };
+
+        let limit = constraint(repository.update_workspace(workspace.id, form_update).await);
+        let expected = "CheckViolation workspaces_metadata_limit";
+        assert_eq!(limit.as_deref(), Some(expected));
+
+        let form = WorkflowCreateInput {
+            workspace_id: workspace.id,
+            display_name: Some("Workflow".into()),
+            metadata: None,
+            input_graph: None,
+            rt_metadata: None,
+        };
+
+        let oversized = WorkflowCreateInput {
+            input_graph: Some(Value::String("x".repeat(4096))),
+            ..form.clone()
+        };
+
+        let limit = constraint(repository.create_workflow(&oversized).await);
+        let expected = "CheckViolation workflows_input_graph_limit";
+        assert_eq!(limit.as_deref(), Some(expected));
+
+        let workflow = repository.create_workflow(&form).await?;
+        let output = repository.view_workflow(workflow.id).await?;
+        assert_eq!(output.display_name, "Workflow");
+
+        repository.delete_workflow(workflow.id).await?;
+        let duplicate = constraint(repository.create_workflow(&form).await);
+        let expected = "UniqueViolation workflows_unique_display_name";
+        assert_eq!(duplicate.as_deref(), Some(expected));
+
+        let form = WorkflowCreateInput {
+            workspace_id: WorkspaceId::default(),
+            ..form
+        };
+
+        let orphan = constraint(repository.create_workflow(&form).await);
+        let expected = "ForeignKeyViolation workflows_workspace_id_fkey";
+        assert_eq!(orphan.as_deref(), Some(expected));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn database_repository() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        check_repository(&*database).await
+    }
+
+    #[tokio::test]
+    async fn in_memory_repository() -> DatabaseResult<()> {
+        let database = InMemoryDatabase::new();
+        check_repository(&database).await
+    }
+}
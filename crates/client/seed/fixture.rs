@@ -0,0 +1,140 @@
+//! Declarative description of the seed data.
+
+use std::path::Path;
+
+use axiston_db_schema::enumerations::{AccountStatus, ProjectRole};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::seed::{SeedError, SeedResult};
+
+/// Declarative set of entities used to populate the database.
+///
+/// Entities reference each other by the symbolic `ref` names,
+/// which are resolved into IDs by [`apply_fixture`].
+///
+/// [`apply_fixture`]: crate::seed::apply_fixture
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[must_use = "fixtures do nothing unless you use them"]
+pub struct Fixture {
+    pub accounts: Vec<AccountFixture>,
+    pub workspaces: Vec<WorkspaceFixture>,
+    pub members: Vec<MemberFixture>,
+    pub schedules: Vec<ScheduleFixture>,
+    pub webhooks: Vec<WebhookFixture>,
+    pub workflows: Vec<WorkflowFixture>,
+}
+
+impl Fixture {
+    /// Parses the fixture from the `TOML` document.
+    pub fn from_toml(document: &str) -> SeedResult<Self> {
+        toml::from_str(document).map_err(|x| SeedError::Parse(x.into()))
+    }
+
+    /// Parses the fixture from the `JSON` document.
+    pub fn from_json(document: &str) -> SeedResult<Self> {
+        serde_json::from_str(document).map_err(|x| SeedError::Parse(x.into()))
+    }
+
+    /// Reads and parses the fixture file.
+    ///
+    /// Files with the `.json` extension are parsed as `JSON`, others as `TOML`.
+    pub fn from_path(path: impl AsRef<Path>) -> SeedResult<Self> {
+        let path = path.as_ref();
+        let document = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("json") => Self::from_json(&document),
+            _ => Self::from_toml(&document),
+        }
+    }
+}
+
+/// Account, identified by its email address.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountFixture {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub display_name: String,
+    pub email_address: String,
+    pub password_hash: String,
+    #[serde(default = "pending_status")]
+    pub status: AccountStatus,
+}
+
+/// Workspace, identified by its display name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceFixture {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub display_name: String,
+    #[serde(default = "empty_object")]
+    pub metadata: Value,
+}
+
+/// Workspace membership, identified by its workspace and account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemberFixture {
+    pub workspace: String,
+    pub account: String,
+    /// Defaults to the member's account.
+    #[serde(default)]
+    pub created_by: Option<String>,
+    #[serde(default)]
+    pub role: Option<ProjectRole>,
+}
+
+/// Workspace schedule, identified by its workspace and metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleFixture {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub workspace: String,
+    #[serde(default = "empty_object")]
+    pub metadata: Value,
+}
+
+/// Workspace webhook, identified by its workspace and metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookFixture {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub workspace: String,
+    #[serde(default = "empty_object")]
+    pub metadata: Value,
+}
+
+/// Workflow, identified by its workspace and display name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowFixture {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub workspace: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+    #[serde(default)]
+    pub input_graph: Option<Value>,
+    #[serde(default)]
+    pub rt_metadata: Option<Value>,
+    /// References of the attached workspace schedules.
+    #[serde(default)]
+    pub schedules: Vec<String>,
+    /// References of the attached workspace webhooks.
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+}
+
+fn empty_object() -> Value {
+    Value::Object(Default::default())
+}
+
+fn pending_status() -> AccountStatus {
+    AccountStatus::Pending
+}
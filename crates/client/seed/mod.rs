@@ -0,0 +1,470 @@
+//! Seed data and fixtures loader.
+//!
+//! Populates the database with entities described by the [`Fixture`].
+//! Entities that already exist are looked up by their natural keys,
+//! so the same fixture could be applied multiple times.
+
+mod fixture;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use axiston_db_schema::enumerations::AccountStatus;
+use axiston_db_schema::identifiers::{AccountId, ScheduleId, WebhookId, WorkflowId, WorkspaceId};
+use axiston_db_schema::schema;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+use crate::accounts::{
+    create_account, normalize_email, update_account_status_query, AccountCreateInput,
+    AccountStatusUpdateInput,
+};
+pub use crate::seed::fixture::{
+    AccountFixture, Fixture, MemberFixture, ScheduleFixture, WebhookFixture, WorkflowFixture,
+    WorkspaceFixture,
+};
+use crate::workflow_schedules::{create_all_workflow_schedules, delete_all_workflow_schedules};
+use crate::workflow_webhooks::{create_all_workflow_webhooks, delete_all_workflow_webhooks};
+use crate::workflows::{create_workflow, WorkflowCreateInput};
+use crate::workspace_members::{
+    create_workspace_member, update_workspace_member, WorkspaceMemberCreateInput,
+    WorkspaceMemberUpdateInput,
+};
+use crate::workspace_schedules::{create_workspace_schedule, WorkspaceScheduleCreateInput};
+use crate::workspace_webhooks::{create_workspace_webhook, WorkspaceWebhookCreateInput};
+use crate::workspaces::{create_workspace, WorkspaceCreateInput};
+use crate::{BoxError, DatabaseError};
+
+/// Failure of parsing or applying the [`Fixture`].
+#[derive(Debug, thiserror::Error)]
+#[must_use = "errors do nothing unless you use them"]
+pub enum SeedError {
+    /// Fixture file could not be read.
+    #[error("fixture io error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Fixture document is not a valid `TOML` or `JSON` fixture.
+    #[error("fixture parse error: {0}")]
+    Parse(BoxError),
+    /// Fixture reference is either duplicated or unknown.
+    #[error("fixture reference error: {0}")]
+    Reference(String),
+    /// Errors returned by the [`Database`].
+    ///
+    /// [`Database`]: crate::Database
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+}
+
+impl From<diesel::result::Error> for SeedError {
+    #[inline]
+    fn from(value: diesel::result::Error) -> Self {
+        Self::Database(value.into())
+    }
+}
+
+/// Specialized [`Result`] alias for the [`SeedError`] type.
+pub type SeedResult<T, E = SeedError> = Result<T, E>;
+
+/// IDs of all seeded entities, keyed by their references.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[must_use = "forms do nothing unless you use them"]
+pub struct SeedOutput {
+    pub accounts: BTreeMap<String, AccountId>,
+    pub workspaces: BTreeMap<String, WorkspaceId>,
+    pub schedules: BTreeMap<String, ScheduleId>,
+    pub webhooks: BTreeMap<String, WebhookId>,
+    pub workflows: BTreeMap<String, WorkflowId>,
+
+    /// Number of newly created entities.
+    pub created: u64,
+}
+
+/// Creates all entities of the fixture inside a single transaction.
+///
+/// Entities are looked up by their natural keys first:
+///
+/// - accounts by the email address
+/// - workspaces by the display name
+/// - members by the workspace and account
+/// - schedules and webhooks by the workspace and metadata
+/// - workflows by the workspace and display name
+///
+/// # Tables
+///
+/// - accounts
+/// - workspaces
+/// - workspace_members
+/// - workspace_schedules
+/// - workspace_webhooks
+/// - workflows
+/// - workflow_schedules
+/// - workflow_webhooks
+pub async fn apply_fixture(
+    conn: &mut AsyncPgConnection,
+    fixture: &Fixture,
+) -> SeedResult<SeedOutput> {
+    conn.transaction(|conn| seed_all(conn, fixture).scope_boxed())
+        .await
+}
+
+async fn seed_all(conn: &mut AsyncPgConnection, fixture: &Fixture) -> SeedResult<SeedOutput> {
+    let mut output = SeedOutput::default();
+
+    for account in &fixture.accounts {
+        let id = seed_account(conn, account, &mut output.created).await?;
+        insert_reference(&mut output.accounts, "account", &account.reference, id)?;
+    }
+
+    for workspace in &fixture.workspaces {
+        let id = seed_workspace(conn, workspace, &mut output.created).await?;
+        insert_reference(
+            &mut output.workspaces,
+            "workspace",
+            &workspace.reference,
+            id,
+        )?;
+    }
+
+    for member in &fixture.members {
+        seed_member(conn, member, &mut output).await?;
+    }
+
+    for schedule in &fixture.schedules {
+        let workspace_id = resolve(&output.workspaces, "workspace", &schedule.workspace)?;
+        let id = seed_schedule(conn, workspace_id, schedule, &mut output.created).await?;
+        insert_reference(&mut output.schedules, "schedule", &schedule.reference, id)?;
+    }
+
+    for webhook in &fixture.webhooks {
+        let workspace_id = resolve(&output.workspaces, "workspace", &webhook.workspace)?;
+        let id = seed_webhook(conn, workspace_id, webhook, &mut output.created).await?;
+        insert_reference(&mut output.webhooks, "webhook", &webhook.reference, id)?;
+    }
+
+    for workflow in &fixture.workflows {
+        let id = seed_workflow(conn, workflow, &mut output).await?;
+        insert_reference(&mut output.workflows, "workflow", &workflow.reference, id)?;
+    }
+
+    Ok(output)
+}
+
+/// Registers a new reference, rejecting duplicates.
+fn insert_reference<T>(
+    refs: &mut BTreeMap<String, T>,
+    kind: &str,
+    reference: &str,
+    id: T,
+) -> SeedResult<()> {
+    if refs.insert(reference.to_owned(), id).is_some() {
+        let error = format!("duplicate {kind} reference: {reference}");
+        return Err(SeedError::Reference(error));
+    }
+
+    Ok(())
+}
+
+/// Resolves the reference into the ID of the already seeded entity.
+fn resolve<T: Copy>(refs: &BTreeMap<String, T>, kind: &str, reference: &str) -> SeedResult<T> {
+    refs.get(reference).copied().ok_or_else(|| {
+        let error = format!("unknown {kind} reference: {reference}");
+        SeedError::Reference(error)
+    })
+}
+
+async fn seed_account(
+    conn: &mut AsyncPgConnection,
+    account: &AccountFixture,
+    created: &mut u64,
+) -> SeedResult<AccountId> {
+    use schema::accounts::dsl::*;
+
+    let filter_cond = email_address
+        .eq(normalize_email(&account.email_address))
+        .and(deleted_at.is_null());
+    let existing = accounts
+        .filter(filter_cond)
+        .select(id)
+        .first::<AccountId>(conn)
+        .await
+        .optional()?;
+
+    if let Some(existing) = existing {
+        return Ok(existing);
+    }
+
+    let form = AccountCreateInput {
+        display_name: Cow::Borrowed(&account.display_name),
+        email_address: Cow::Borrowed(&account.email_address),
+        password_hash: Cow::Borrowed(&account.password_hash),
+    };
+
+    let output = create_account(conn, &form).await?;
+    if account.status != AccountStatus::Pending {
+        // Fixtures declare the status, so transitions are not validated.
+        let form = AccountStatusUpdateInput {
+            status: account.status,
+            status_reason: None,
+        };
+
+        update_account_status_query(output.id, form)
+            .execute(conn)
+            .await?;
+    }
+
+    *created += 1;
+    Ok(output.id)
+}
+
+async fn seed_workspace(
+    conn: &mut AsyncPgConnection,
+    workspace: &WorkspaceFixture,
+    created: &mut u64,
+) -> SeedResult<WorkspaceId> {
+    use schema::workspaces::dsl::*;
+
+    let filter_cond = display_name
+        .eq(&workspace.display_name)
+        .and(deleted_at.is_null());
+    let existing = workspaces
+        .filter(filter_cond)
+        .select(id)
+        .first::<WorkspaceId>(conn)
+        .await
+        .optional()?;
+
+    if let Some(existing) = existing {
+        return Ok(existing);
+    }
+
+    let form = WorkspaceCreateInput {
+        display_name: Cow::Borrowed(&workspace.display_name),
+        metadata: workspace.metadata.clone(),
+    };
+
+    let output = create_workspace(conn, &form).await?;
+    *created += 1;
+    Ok(output.id)
+}
+
+async fn seed_member(
+    conn: &mut AsyncPgConnection,
+    member: &MemberFixture,
+    output: &mut SeedOutput,
+) -> SeedResult<()> {
+    use schema::workspace_members::dsl::*;
+
+    let form_workspace_id = resolve(&output.workspaces, "workspace", &member.workspace)?;
+    let form_account_id = resolve(&output.accounts, "account", &member.account)?;
+    let created_by_ref = member.created_by.as_ref().unwrap_or(&member.account);
+    let form_created_by = resolve(&output.accounts, "account", created_by_ref)?;
+
+    let filter_cond = workspace_id
+        .eq(form_workspace_id)
+        .and(account_id.eq(form_account_id));
+    let existing = workspace_members
+        .filter(filter_cond)
+        .select(account_id)
+        .first::<AccountId>(conn)
+        .await
+        .optional()?;
+
+    if existing.is_none() {
+        let form = WorkspaceMemberCreateInput {
+            workspace_id: form_workspace_id,
+            account_id: form_account_id,
+            created_by: form_created_by,
+            updated_by: form_created_by,
+        };
+
+        create_workspace_member(conn, &form).await?;
+        output.created += 1;
+    }
+
+    if let Some(role) = member.role {
+        let form = WorkspaceMemberUpdateInput {
+            account_role: Some(role),
+            ..WorkspaceMemberUpdateInput::default()
+        };
+
+        update_workspace_member(conn, form_workspace_id, form_account_id, form).await?;
+    }
+
+    Ok(())
+}
+
+async fn seed_schedule(
+    conn: &mut AsyncPgConnection,
+    form_workspace_id: WorkspaceId,
+    schedule: &ScheduleFixture,
+    created: &mut u64,
+) -> SeedResult<ScheduleId> {
+    use schema::workspace_schedules::dsl::*;
+
+    let filter_cond = workspace_id
+        .eq(form_workspace_id)
+        .and(metadata.eq(&schedule.metadata))
+        .and(deleted_at.is_null());
+    let existing = workspace_schedules
+        .filter(filter_cond)
+        .select(id)
+        .first::<ScheduleId>(conn)
+        .await
+        .optional()?;
+
+    if let Some(existing) = existing {
+        return Ok(existing);
+    }
+
+    let form = WorkspaceScheduleCreateInput {
+        workspace_id: form_workspace_id,
+        metadata: schedule.metadata.clone(),
+    };
+
+    let output = create_workspace_schedule(conn, &form).await?;
+    *created += 1;
+    Ok(output.id)
+}
+
+async fn seed_webhook(
+    conn: &mut AsyncPgConnection,
+    form_workspace_id: WorkspaceId,
+    webhook: &WebhookFixture,
+    created: &mut u64,
+) -> SeedResult<WebhookId> {
+    use schema::workspace_webhooks::dsl::*;
+
+    let filter_cond = workspace_id
+        .eq(form_workspace_id)
+        .and(metadata.eq(&webhook.metadata))
+        .and(deleted_at.is_null());
+    let existing = workspace_webhooks
+        .filter(filter_cond)
+        .select(id)
+        .first::<WebhookId>(conn)
+        .await
+        .optional()?;
+
+    if let Some(existing) = existing {
+        return Ok(existing);
+    }
+
+    let form = WorkspaceWebhookCreateInput {
+        workspace_id: form_workspace_id,
+        metadata: webhook.metadata.clone(),
+    };
+
+    let output = create_workspace_webhook(conn, &form).await?;
+    *created += 1;
+    Ok(output.id)
+}
+
+async fn seed_workflow(
+    conn: &mut AsyncPgConnection,
+    workflow: &WorkflowFixture,
+    output: &mut SeedOutput,
+) -> SeedResult<WorkflowId> {
+    use schema::workflows::dsl::*;
+
+    let form_workspace_id = resolve(&output.workspaces, "workspace", &workflow.workspace)?;
+    let schedule_ids = workflow
+        .schedules
+        .iter()
+        .map(|x| resolve(&output.schedules, "schedule", x))
+        .collect::<SeedResult<Vec<_>>>()?;
+    let webhook_ids = workflow
+        .webhooks
+        .iter()
+        .map(|x| resolve(&output.webhooks, "webhook", x))
+        .collect::<SeedResult<Vec<_>>>()?;
+
+    let filter_cond = workspace_id
+        .eq(form_workspace_id)
+        .and(display_name.eq(&workflow.display_name))
+        .and(deleted_at.is_null());
+    let existing = workflows
+        .filter(filter_cond)
+        .select(id)
+        .first::<WorkflowId>(conn)
+        .await
+        .optional()?;
+
+    let workflow_id = match existing {
+        Some(existing) => existing,
+        None => {
+            let form = WorkflowCreateInput {
+                workspace_id: form_workspace_id,
+                display_name: Some(Cow::Borrowed(&workflow.display_name)),
+                metadata: workflow.metadata.clone(),
+                input_graph: workflow.input_graph.clone(),
+                rt_metadata: workflow.rt_metadata.clone(),
+            };
+
+            let output_id = create_workflow(conn, &form).await?.id;
+            output.created += 1;
+            output_id
+        }
+    };
+
+    // Attachments are replaced to match the fixture exactly.
+    delete_all_workflow_schedules(conn, workflow_id).await?;
+    if !schedule_ids.is_empty() {
+        create_all_workflow_schedules(conn, workflow_id, schedule_ids).await?;
+    }
+
+    delete_all_workflow_webhooks(conn, workflow_id).await?;
+    if !webhook_ids.is_empty() {
+        create_all_workflow_webhooks(conn, workflow_id, webhook_ids).await?;
+    }
+
+    Ok(workflow_id)
+}
+
+#[cfg(test)]
+mod test {
+    use axiston_db_schema::enumerations::AccountStatus;
+
+    use crate::accounts::view_account;
+    use crate::seed::{apply_fixture, Fixture, SeedError, SeedResult};
+    use crate::testing::TestDatabase;
+
+    #[test]
+    fn parse_fixture() -> SeedResult<()> {
+        let fixture = Fixture::from_toml(include_str!("../../../configs/seed.toml"))?;
+        assert_eq!(fixture.accounts.len(), 2);
+        assert_eq!(fixture.workflows[0].schedules, vec!["hourly"]);
+        assert!(Fixture::from_json(r#"{ "unknown": [] }"#).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn apply_fixture_twice() -> SeedResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let fixture = Fixture::from_toml(include_str!("../../../configs/seed.toml"))?;
+
+        let first = apply_fixture(&mut conn, &fixture).await?;
+        let second = apply_fixture(&mut conn, &fixture).await?;
+        assert!(first.created > 0);
+        assert_eq!(second.created, 0);
+        assert_eq!(first.workflows, second.workflows);
+
+        let account = view_account(&mut conn, first.accounts["alice"]).await?;
+        assert_eq!(account.status, AccountStatus::Active);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn apply_unknown_reference() -> SeedResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+        let fixture =
+            Fixture::from_json(r#"{ "members": [{ "workspace": "x", "account": "y" }] }"#)?;
+
+        let error = apply_fixture(&mut conn, &fixture).await.unwrap_err();
+        assert!(matches!(error, SeedError::Reference(_)));
+        Ok(())
+    }
+}
@@ -0,0 +1,491 @@
+//! Builder-style entity factories with randomized defaults.
+//!
+//! Defaults satisfy all `CHECK` constraints of the migrations,
+//! and required parents are created automatically unless specified.
+
+use std::borrow::Cow;
+
+use axiston_db_schema::enumerations::AccountStatus;
+use axiston_db_schema::identifiers::{AccountId, WorkflowId, WorkspaceId};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde_json::Value;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::accounts::{
+    create_account, update_account_status_query, AccountCreateInput, AccountCreateOutput,
+    AccountStatusUpdateInput,
+};
+use crate::workflow_executions::{
+    create_workflow_execution, WorkflowExecutionCreateInput, WorkflowExecutionCreateOutput,
+};
+use crate::workflows::{create_workflow, WorkflowCreateInput, WorkflowCreateOutput};
+use crate::workspace_members::{create_workspace_member, WorkspaceMemberCreateInput};
+use crate::workspace_schedules::{
+    create_workspace_schedule, WorkspaceScheduleCreateInput, WorkspaceScheduleCreateOutput,
+};
+use crate::workspace_webhooks::{
+    create_workspace_webhook, WorkspaceWebhookCreateInput, WorkspaceWebhookCreateOutput,
+};
+use crate::workspaces::{create_workspace, WorkspaceCreateInput, WorkspaceCreateOutput};
+use crate::DatabaseResult;
+
+/// Returns a random suffix, used to keep unique columns unique.
+fn random_suffix() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Returns an empty `JSON` object.
+fn empty_object() -> Value {
+    Value::Object(Default::default())
+}
+
+/// Returns the workspace ID or creates a new workspace.
+async fn workspace_or_create(
+    conn: &mut AsyncPgConnection,
+    workspace_id: Option<WorkspaceId>,
+) -> DatabaseResult<WorkspaceId> {
+    match workspace_id {
+        Some(workspace_id) => Ok(workspace_id),
+        None => Ok(WorkspaceFactory::new().create(conn).await?.id),
+    }
+}
+
+/// Factory for the `accounts` table.
+#[derive(Debug, Default, Clone)]
+#[must_use = "factories do nothing unless you use them"]
+pub struct AccountFactory {
+    display_name: Option<String>,
+    email_address: Option<String>,
+    password_hash: Option<String>,
+    status: Option<AccountStatus>,
+}
+
+impl AccountFactory {
+    /// Returns a new [`AccountFactory`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the display name.
+    pub fn display_name(mut self, display_name: impl Into<String>) -> Self {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
+    /// Overrides the email address.
+    pub fn email_address(mut self, email_address: impl Into<String>) -> Self {
+        self.email_address = Some(email_address.into());
+        self
+    }
+
+    /// Overrides the password hash.
+    pub fn password_hash(mut self, password_hash: impl Into<String>) -> Self {
+        self.password_hash = Some(password_hash.into());
+        self
+    }
+
+    /// Overrides the status, bypassing [`AccountStatus::can_transition_to`].
+    pub fn status(mut self, status: AccountStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Creates the new account.
+    pub async fn create(self, conn: &mut AsyncPgConnection) -> DatabaseResult<AccountCreateOutput> {
+        let suffix = random_suffix();
+        let display_name = self
+            .display_name
+            .unwrap_or_else(|| format!("Account {suffix}"));
+        let email_address = self
+            .email_address
+            .unwrap_or_else(|| format!("{suffix}@example.com"));
+        let password_hash = self
+            .password_hash
+            .unwrap_or_else(|| format!("$argon2id${suffix}"));
+
+        let form = AccountCreateInput {
+            display_name: Cow::Borrowed(&display_name),
+            email_address: Cow::Borrowed(&email_address),
+            password_hash: Cow::Borrowed(&password_hash),
+        };
+
+        let account = create_account(conn, &form).await?;
+        if let Some(status) = self.status {
+            let form = AccountStatusUpdateInput {
+                status,
+                status_reason: None,
+            };
+
+            let _query = update_account_status_query(account.id, form)
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(account)
+    }
+}
+
+/// Factory for the `workspaces` table.
+#[derive(Debug, Default, Clone)]
+#[must_use = "factories do nothing unless you use them"]
+pub struct WorkspaceFactory {
+    display_name: Option<String>,
+    metadata: Option<Value>,
+}
+
+impl WorkspaceFactory {
+    /// Returns a new [`WorkspaceFactory`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the display name.
+    pub fn display_name(mut self, display_name: impl Into<String>) -> Self {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
+    /// Overrides the metadata.
+    pub fn metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Creates the new workspace.
+    pub async fn create(
+        self,
+        conn: &mut AsyncPgConnection,
+    ) -> DatabaseResult<WorkspaceCreateOutput> {
+        let display_name = self
+            .display_name
+            .unwrap_or_else(|| format!("Workspace {}", random_suffix()));
+
+        let form = WorkspaceCreateInput {
+            display_name: Cow::Borrowed(&display_name),
+            metadata: self.metadata.unwrap_or_else(empty_object),
+        };
+
+        create_workspace(conn, &form).await
+    }
+}
+
+/// Factory for the `workspace_members` table.
+///
+/// Creates both the workspace and the account unless specified.
+#[derive(Debug, Default, Clone)]
+#[must_use = "factories do nothing unless you use them"]
+pub struct MemberFactory {
+    workspace_id: Option<WorkspaceId>,
+    account_id: Option<AccountId>,
+    created_by: Option<AccountId>,
+}
+
+impl MemberFactory {
+    /// Returns a new [`MemberFactory`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the parent workspace.
+    pub fn workspace_id(mut self, workspace_id: WorkspaceId) -> Self {
+        self.workspace_id = Some(workspace_id);
+        self
+    }
+
+    /// Overrides the member's account.
+    pub fn account_id(mut self, account_id: AccountId) -> Self {
+        self.account_id = Some(account_id);
+        self
+    }
+
+    /// Overrides the creator's account, defaults to the member's account.
+    pub fn created_by(mut self, created_by: AccountId) -> Self {
+        self.created_by = Some(created_by);
+        self
+    }
+
+    /// Creates the new workspace member.
+    pub async fn create(self, conn: &mut AsyncPgConnection) -> DatabaseResult<()> {
+        let workspace_id = workspace_or_create(conn, self.workspace_id).await?;
+        let account_id = match self.account_id {
+            Some(account_id) => account_id,
+            None => AccountFactory::new().create(conn).await?.id,
+        };
+
+        let created_by = self.created_by.unwrap_or(account_id);
+        let form = WorkspaceMemberCreateInput {
+            workspace_id,
+            account_id,
+            created_by,
+            updated_by: created_by,
+        };
+
+        create_workspace_member(conn, &form).await
+    }
+}
+
+/// Factory for the `workflows` table.
+///
+/// Creates the workspace unless specified.
+#[derive(Debug, Default, Clone)]
+#[must_use = "factories do nothing unless you use them"]
+pub struct WorkflowFactory {
+    workspace_id: Option<WorkspaceId>,
+    display_name: Option<String>,
+    metadata: Option<Value>,
+    input_graph: Option<Value>,
+}
+
+impl WorkflowFactory {
+    /// Returns a new [`WorkflowFactory`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the parent workspace.
+    pub fn workspace_id(mut self, workspace_id: WorkspaceId) -> Self {
+        self.workspace_id = Some(workspace_id);
+        self
+    }
+
+    /// Overrides the display name, unique within the workspace.
+    pub fn display_name(mut self, display_name: impl Into<String>) -> Self {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
+    /// Overrides the metadata.
+    pub fn metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Overrides the input graph.
+    pub fn input_graph(mut self, input_graph: Value) -> Self {
+        self.input_graph = Some(input_graph);
+        self
+    }
+
+    /// Creates the new workflow.
+    pub async fn create(
+        self,
+        conn: &mut AsyncPgConnection,
+    ) -> DatabaseResult<WorkflowCreateOutput> {
+        let workspace_id = workspace_or_create(conn, self.workspace_id).await?;
+        let display_name = self
+            .display_name
+            .unwrap_or_else(|| format!("Workflow {}", random_suffix()));
+
+        let form = WorkflowCreateInput {
+            workspace_id,
+            display_name: Some(Cow::Borrowed(&display_name)),
+            metadata: self.metadata,
+            input_graph: self.input_graph,
+            rt_metadata: None,
+        };
+
+        create_workflow(conn, &form).await
+    }
+}
+
+/// Factory for the `workflow_executions` table.
+///
+/// Creates the workflow (and its workspace) unless specified.
+#[derive(Debug, Default, Clone)]
+#[must_use = "factories do nothing unless you use them"]
+pub struct ExecutionFactory {
+    workflow_id: Option<WorkflowId>,
+    output_graph: Option<Value>,
+    started_at: Option<OffsetDateTime>,
+    time_spent: Option<Duration>,
+}
+
+impl ExecutionFactory {
+    /// Returns a new [`ExecutionFactory`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the parent workflow.
+    pub fn workflow_id(mut self, workflow_id: WorkflowId) -> Self {
+        self.workflow_id = Some(workflow_id);
+        self
+    }
+
+    /// Overrides the output graph.
+    pub fn output_graph(mut self, output_graph: Value) -> Self {
+        self.output_graph = Some(output_graph);
+        self
+    }
+
+    /// Overrides the start timestamp.
+    pub fn started_at(mut self, started_at: OffsetDateTime) -> Self {
+        self.started_at = Some(started_at);
+        self
+    }
+
+    /// Overrides the execution duration, must not be negative.
+    pub fn time_spent(mut self, time_spent: Duration) -> Self {
+        self.time_spent = Some(time_spent);
+        self
+    }
+
+    /// Creates the new workflow execution.
+    pub async fn create(
+        self,
+        conn: &mut AsyncPgConnection,
+    ) -> DatabaseResult<WorkflowExecutionCreateOutput> {
+        let workflow_id = match self.workflow_id {
+            Some(workflow_id) => workflow_id,
+            None => WorkflowFactory::new().create(conn).await?.id,
+        };
+
+        // Random duration up to a minute, ended before now.
+        let random = Uuid::new_v4().as_u128();
+        let time_spent = self
+            .time_spent
+            .unwrap_or_else(|| Duration::milliseconds((random % 60_000) as i64));
+        let started_at = self
+            .started_at
+            .unwrap_or_else(|| OffsetDateTime::now_utc() - time_spent - Duration::minutes(1));
+
+        let form = WorkflowExecutionCreateInput {
+            workflow_id,
+            output_graph: self.output_graph.unwrap_or_else(empty_object),
+            rt_metadata: empty_object(),
+            started_at,
+            ended_at: started_at + time_spent,
+        };
+
+        create_workflow_execution(conn, form).await
+    }
+}
+
+/// Factory for the `workspace_schedules` table.
+///
+/// Creates the workspace unless specified.
+#[derive(Debug, Default, Clone)]
+#[must_use = "factories do nothing unless you use them"]
+pub struct ScheduleFactory {
+    workspace_id: Option<WorkspaceId>,
+    metadata: Option<Value>,
+}
+
+impl ScheduleFactory {
+    /// Returns a new [`ScheduleFactory`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the parent workspace.
+    pub fn workspace_id(mut self, workspace_id: WorkspaceId) -> Self {
+        self.workspace_id = Some(workspace_id);
+        self
+    }
+
+    /// Overrides the metadata.
+    pub fn metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Creates the new workspace schedule.
+    pub async fn create(
+        self,
+        conn: &mut AsyncPgConnection,
+    ) -> DatabaseResult<WorkspaceScheduleCreateOutput> {
+        let form = WorkspaceScheduleCreateInput {
+            workspace_id: workspace_or_create(conn, self.workspace_id).await?,
+            metadata: self.metadata.unwrap_or_else(empty_object),
+        };
+
+        create_workspace_schedule(conn, &form).await
+    }
+}
+
+/// Factory for the `workspace_webhooks` table.
+///
+/// Creates the workspace unless specified.
+#[derive(Debug, Default, Clone)]
+#[must_use = "factories do nothing unless you use them"]
+pub struct WebhookFactory {
+    workspace_id: Option<WorkspaceId>,
+    metadata: Option<Value>,
+}
+
+impl WebhookFactory {
+    /// Returns a new [`WebhookFactory`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the parent workspace.
+    pub fn workspace_id(mut self, workspace_id: WorkspaceId) -> Self {
+        self.workspace_id = Some(workspace_id);
+        self
+    }
+
+    /// Overrides the metadata.
+    pub fn metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Creates the new workspace webhook.
+    pub async fn create(
+        self,
+        conn: &mut AsyncPgConnection,
+    ) -> DatabaseResult<WorkspaceWebhookCreateOutput> {
+        let form = WorkspaceWebhookCreateInput {
+            workspace_id: workspace_or_create(conn, self.workspace_id).await?,
+            metadata: self.metadata.unwrap_or_else(empty_object),
+        };
+
+        create_workspace_webhook(conn, &form).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{
+        AccountFactory, ExecutionFactory, MemberFactory, ScheduleFactory, TestDatabase,
+        WebhookFactory, WorkflowFactory,
+    };
+    use crate::workflow_executions::{view_workflow_execution, WorkflowExecutionViewInput};
+    use crate::DatabaseResult;
+
+    #[tokio::test]
+    async fn create_with_parents() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+
+        let _ = AccountFactory::new().create(&mut conn).await?;
+        let _ = AccountFactory::new().create(&mut conn).await?;
+        MemberFactory::new().create(&mut conn).await?;
+        let _ = ScheduleFactory::new().create(&mut conn).await?;
+        let _ = WebhookFactory::new().create(&mut conn).await?;
+
+        let workflow = WorkflowFactory::new().create(&mut conn).await?;
+        let execution = ExecutionFactory::new()
+            .workflow_id(workflow.id)
+            .create(&mut conn)
+            .await?;
+
+        let form = WorkflowExecutionViewInput {
+            execution_id: execution.execution_id,
+        };
+
+        let output = view_workflow_execution(&mut conn, form).await?;
+        assert_eq!(output.workflow_id, workflow.id);
+        assert!(output.ended_at >= output.started_at);
+        Ok(())
+    }
+}
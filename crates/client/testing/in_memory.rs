@@ -0,0 +1,544 @@
+//! In-memory fake of the repository traits.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axiston_db_schema::enumerations::AccountStatus;
+use axiston_db_schema::identifiers::{AccountId, WorkflowId, WorkspaceId};
+use diesel::result::{DatabaseErrorInformation, DatabaseErrorKind, Error};
+use serde_json::Value;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::accounts::{
+    normalize_email, AccountCreateInput, AccountCreateOutput, AccountUpdateInput, AccountViewOutput,
+};
+use crate::repository::{AccountRepository, WorkflowRepository, WorkspaceRepository};
+use crate::workflows::{
+    WorkflowCreateInput, WorkflowCreateOutput, WorkflowUpdateInput, WorkflowViewOutput,
+};
+use crate::workspaces::{
+    WorkspaceCreateInput, WorkspaceCreateOutput, WorkspaceUpdateInput, WorkspaceViewOutput,
+};
+use crate::{jsonb, DatabaseError, DatabaseResult};
+
+/// In-memory implementation of all repository traits.
+///
+/// - Mimics soft deletion, `CHECK` (including `JSONB` size limits), `UNIQUE`
+///   and `FOREIGN KEY` constraints.
+/// - Returns the same [`DatabaseError`] variants as the [`Database`].
+///
+/// [`Database`]: crate::Database
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryDatabase {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    accounts: BTreeMap<AccountId, AccountRow>,
+    workspaces: BTreeMap<WorkspaceId, WorkspaceRow>,
+    workflows: BTreeMap<WorkflowId, WorkflowRow>,
+}
+
+#[derive(Debug, Clone)]
+struct AccountRow {
+    display_name: String,
+    email_address: String,
+    password_hash: String,
+
+    status: AccountStatus,
+    status_reason: Option<String>,
+    status_changed_at: OffsetDateTime,
+
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+    deleted_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone)]
+struct WorkspaceRow {
+    display_name: String,
+    metadata: Value,
+
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+    deleted_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone)]
+struct WorkflowRow {
+    workspace_id: WorkspaceId,
+    display_name: String,
+    metadata: Value,
+    input_graph: Value,
+    rt_metadata: Value,
+
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+    deleted_at: Option<OffsetDateTime>,
+}
+
+impl InMemoryDatabase {
+    /// Returns a new empty [`InMemoryDatabase`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("should not be poisoned")
+    }
+}
+
+/// Constraint violation, formatted the same way as by `postgres`.
+struct Violation {
+    message: String,
+    table_name: &'static str,
+    constraint_name: &'static str,
+}
+
+impl DatabaseErrorInformation for Violation {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn details(&self) -> Option<&str> {
+        None
+    }
+
+    fn hint(&self) -> Option<&str> {
+        None
+    }
+
+    fn table_name(&self) -> Option<&str> {
+        Some(self.table_name)
+    }
+
+    fn column_name(&self) -> Option<&str> {
+        None
+    }
+
+    fn constraint_name(&self) -> Option<&str> {
+        Some(self.constraint_name)
+    }
+
+    fn statement_position(&self) -> Option<i32> {
+        None
+    }
+}
+
+impl fmt::Debug for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Returns the error, returned by `postgres` on the constraint violation.
+fn violation(
+    kind: DatabaseErrorKind,
+    table: &'static str,
+    constraint: &'static str,
+) -> DatabaseError {
+    let message = match kind {
+        DatabaseErrorKind::UniqueViolation => {
+            format!("duplicate key value violates unique constraint \"{constraint}\"")
+        }
+        DatabaseErrorKind::ForeignKeyViolation => {
+            format!("insert or update on table \"{table}\" violates foreign key constraint \"{constraint}\"")
+        }
+        _ => format!("new row for relation \"{table}\" violates check constraint \"{constraint}\""),
+    };
+
+    let info = Violation {
+        message,
+        table_name: table,
+        constraint_name: constraint,
+    };
+
+    DatabaseError::Query(Error::DatabaseError(kind, Box::new(info)))
+}
+
+/// Returns the error, returned by `diesel` if no rows were found.
+fn not_found() -> DatabaseError {
+    DatabaseError::Query(Error::NotFound)
+}
+
+/// Fails with the check violation if the value is empty.
+fn check_non_empty(
+    value: &str,
+    table: &'static str,
+    constraint: &'static str,
+) -> DatabaseResult<()> {
+    match value.is_empty() {
+        true => Err(violation(
+            DatabaseErrorKind::CheckViolation,
+            table,
+            constraint,
+        )),
+        false => Ok(()),
+    }
+}
+
+/// Fails with the check violation if the value is longer than the limit.
+///
+/// Mimics `length(column::TEXT) <= limit` on the `JSONB` column.
+fn check_max_length(
+    value: &Value,
+    limit: usize,
+    table: &'static str,
+    constraint: &'static str,
+) -> DatabaseResult<()> {
+    match jsonb::text_length(value) > limit {
+        true => Err(violation(
+            DatabaseErrorKind::CheckViolation,
+            table,
+            constraint,
+        )),
+        false => Ok(()),
+    }
+}
+
+fn empty_object() -> Value {
+    Value::Object(Default::default())
+}
+
+impl State {
+    fn check_account(&self, id: AccountId, row: &AccountRow) -> DatabaseResult<()> {
+        check_non_empty(
+            &row.display_name,
+            "accounts",
+            "accounts_non_empty_display_name",
+        )?;
+        check_non_empty(
+            &row.email_address,
+            "accounts",
+            "accounts_non_empty_email_address",
+        )?;
+        check_non_empty(
+            &row.password_hash,
+            "accounts",
+            "accounts_non_empty_password_hash",
+        )?;
+
+        // Partial index, only covers active accounts.
+        let duplicate = self.accounts.iter().any(|(x_id, x)| {
+            *x_id != id && x.deleted_at.is_none() && x.email_address == row.email_address
+        });
+
+        if duplicate && row.deleted_at.is_none() {
+            let kind = DatabaseErrorKind::UniqueViolation;
+            return Err(violation(kind, "accounts", "accounts_email_address_idx"));
+        }
+
+        Ok(())
+    }
+
+    fn check_workspace(&self, row: &WorkspaceRow) -> DatabaseResult<()> {
+        let constraint = "workspaces_non_empty_display_name";
+        check_non_empty(&row.display_name, "workspaces", constraint)?;
+        let constraint = "workspaces_metadata_limit";
+        check_max_length(&row.metadata, 2048, "workspaces", constraint)
+    }
+
+    fn check_workflow(&self, id: WorkflowId, row: &WorkflowRow) -> DatabaseResult<()> {
+        let constraint = "workflows_non_empty_display_name";
+        check_non_empty(&row.display_name, "workflows", constraint)?;
+        let constraint = "workflows_metadata_limit";
+        check_max_length(&row.metadata, 2048, "workflows", constraint)?;
+        let constraint = "workflows_input_graph_limit";
+        check_max_length(&row.input_graph, 4096, "workflows", constraint)?;
+        let constraint = "workflows_rt_metadata_limit";
+        check_max_length(&row.rt_metadata, 2048, "workflows", constraint)?;
+
+        // Foreign keys don't take soft deletion into account.
+        if !self.workspaces.contains_key(&row.workspace_id) {
+            let kind = DatabaseErrorKind::ForeignKeyViolation;
+            return Err(violation(kind, "workflows", "workflows_workspace_id_fkey"));
+        }
+
+        // Unique constraint covers deleted workflows too.
+        let duplicate = self.workflows.iter().any(|(x_id, x)| {
+            *x_id != id && x.workspace_id == row.workspace_id && x.display_name == row.display_name
+        });
+
+        if duplicate {
+            let kind = DatabaseErrorKind::UniqueViolation;
+            return Err(violation(
+                kind,
+                "workflows",
+                "workflows_unique_display_name",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl AccountRepository for InMemoryDatabase {
+    async fn create_account(
+        &self,
+        form: &AccountCreateInput<'_>,
+    ) -> DatabaseResult<AccountCreateOutput> {
+        let mut state = self.lock();
+        let (id, now) = (Uuid::new_v4().into(), OffsetDateTime::now_utc());
+        let row = AccountRow {
+            display_name: form.display_name.to_string(),
+            email_address: normalize_email(&form.email_address).into_owned(),
+            password_hash: form.password_hash.to_string(),
+            status: AccountStatus::Pending,
+            status_reason: None,
+            status_changed_at: now,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        };
+
+        state.check_account(id, &row)?;
+        let _ = state.accounts.insert(id, row);
+
+        Ok(AccountCreateOutput {
+            id,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        })
+    }
+
+    async fn view_account(&self, account_id: AccountId) -> DatabaseResult<AccountViewOutput> {
+        let state = self.lock();
+        let row = state.accounts.get(&account_id);
+        let row = row
+            .filter(|x| x.deleted_at.is_none())
+            .ok_or_else(not_found)?;
+
+        Ok(AccountViewOutput {
+            display_name: row.display_name.clone(),
+            email_address: row.email_address.clone(),
+            password_hash: row.password_hash.clone(),
+            status: row.status,
+            status_reason: row.status_reason.clone(),
+            status_changed_at: row.status_changed_at,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            deleted_at: row.deleted_at,
+        })
+    }
+
+    async fn update_account(
+        &self,
+        account_id: AccountId,
+        form: AccountUpdateInput<'_>,
+    ) -> DatabaseResult<()> {
+        let mut state = self.lock();
+        let row = state.accounts.get(&account_id);
+        let Some(mut row) = row.filter(|x| x.deleted_at.is_none()).cloned() else {
+            return Ok(());
+        };
+
+        if let Some(display_name) = form.display_name {
+            row.display_name = display_name.into_owned();
+        }
+
+        if let Some(email_address) = form.email_address {
+            row.email_address = normalize_email(&email_address).into_owned();
+        }
+
+        if let Some(password_hash) = form.password_hash {
+            row.password_hash = password_hash.into_owned();
+        }
+
+        row.updated_at = OffsetDateTime::now_utc();
+        state.check_account(account_id, &row)?;
+        let _ = state.accounts.insert(account_id, row);
+        Ok(())
+    }
+
+    async fn delete_account(&self, account_id: AccountId) -> DatabaseResult<()> {
+        let mut state = self.lock();
+        let row = state.accounts.get_mut(&account_id);
+        if let Some(row) = row.filter(|x| x.deleted_at.is_none()) {
+            row.deleted_at = Some(OffsetDateTime::now_utc());
+        }
+
+        Ok(())
+    }
+}
+
+impl WorkspaceRepository for InMemoryDatabase {
+    async fn create_workspace(
+        &self,
+        form: &WorkspaceCreateInput<'_>,
+    ) -> DatabaseResult<WorkspaceCreateOutput> {
+        let mut state = self.lock();
+        let (id, now) = (Uuid::new_v4().into(), OffsetDateTime::now_utc());
+        let row = WorkspaceRow {
+            display_name: form.display_name.to_string(),
+            metadata: form.metadata.clone(),
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        };
+
+        state.check_workspace(&row)?;
+        let _ = state.workspaces.insert(id, row);
+
+        Ok(WorkspaceCreateOutput {
+            id,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        })
+    }
+
+    async fn view_workspace(
+        &self,
+        workspace_id: WorkspaceId,
+    ) -> DatabaseResult<WorkspaceViewOutput> {
+        let state = self.lock();
+        let row = state.workspaces.get(&workspace_id);
+        let row = row
+            .filter(|x| x.deleted_at.is_none())
+            .ok_or_else(not_found)?;
+
+        Ok(WorkspaceViewOutput {
+            id: workspace_id,
+            display_name: row.display_name.clone(),
+            metadata: row.metadata.clone(),
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            deleted_at: row.deleted_at,
+        })
+    }
+
+    async fn update_workspace(
+        &self,
+        workspace_id: WorkspaceId,
+        form: WorkspaceUpdateInput<'_>,
+    ) -> DatabaseResult<()> {
+        let mut state = self.lock();
+        let row = state.workspaces.get(&workspace_id);
+        let Some(mut row) = row.filter(|x| x.deleted_at.is_none()).cloned() else {
+            return Ok(());
+        };
+
+        if let Some(display_name) = form.display_name {
+            row.display_name = display_name.into_owned();
+        }
+
+        if let Some(metadata) = form.metadata {
+            row.metadata = metadata;
+        }
+
+        row.updated_at = OffsetDateTime::now_utc();
+        state.check_workspace(&row)?;
+        let _ = state.workspaces.insert(workspace_id, row);
+        Ok(())
+    }
+
+    async fn delete_workspace(&self, workspace_id: WorkspaceId) -> DatabaseResult<()> {
+        let mut state = self.lock();
+        let row = state.workspaces.get_mut(&workspace_id);
+        if let Some(row) = row.filter(|x| x.deleted_at.is_none()) {
+            row.deleted_at = Some(OffsetDateTime::now_utc());
+        }
+
+        Ok(())
+    }
+}
+
+impl WorkflowRepository for InMemoryDatabase {
+    async fn create_workflow(
+        &self,
+        form: &WorkflowCreateInput<'_>,
+    ) -> DatabaseResult<WorkflowCreateOutput> {
+        let mut state = self.lock();
+        let (id, now) = (Uuid::new_v4().into(), OffsetDateTime::now_utc());
+        let row = WorkflowRow {
+            workspace_id: form.workspace_id,
+            display_name: form
+                .display_name
+                .as_deref()
+                .unwrap_or("Untitled")
+                .to_owned(),
+            metadata: form.metadata.clone().unwrap_or_else(empty_object),
+            input_graph: form.input_graph.clone().unwrap_or_else(empty_object),
+            rt_metadata: form.rt_metadata.clone().unwrap_or_else(empty_object),
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        };
+
+        state.check_workflow(id, &row)?;
+        let _ = state.workflows.insert(id, row);
+
+        Ok(WorkflowCreateOutput {
+            id,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    async fn view_workflow(&self, workflow_id: WorkflowId) -> DatabaseResult<WorkflowViewOutput> {
+        let state = self.lock();
+        let row = state.workflows.get(&workflow_id);
+        let row = row
+            .filter(|x| x.deleted_at.is_none())
+            .ok_or_else(not_found)?;
+
+        Ok(WorkflowViewOutput {
+            id: workflow_id,
+            display_name: row.display_name.clone(),
+            metadata: row.metadata.clone(),
+            input_graph: row.input_graph.clone(),
+            rt_metadata: row.rt_metadata.clone(),
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            deleted_at: row.deleted_at,
+        })
+    }
+
+    async fn update_workflow(
+        &self,
+        workflow_id: WorkflowId,
+        form: WorkflowUpdateInput<'_>,
+    ) -> DatabaseResult<()> {
+        let mut state = self.lock();
+        let row = state.workflows.get(&workflow_id);
+        let Some(mut row) = row.filter(|x| x.deleted_at.is_none()).cloned() else {
+            return Ok(());
+        };
+
+        if let Some(display_name) = form.display_name {
+            row.display_name = display_name.into_owned();
+        }
+
+        if let Some(metadata) = form.metadata {
+            row.metadata = metadata;
+        }
+
+        if let Some(input_graph) = form.input_graph {
+            row.input_graph = input_graph;
+        }
+
+        if let Some(rt_metadata) = form.rt_metadata {
+            row.rt_metadata = rt_metadata;
+        }
+
+        row.updated_at = OffsetDateTime::now_utc();
+        state.check_workflow(workflow_id, &row)?;
+        let _ = state.workflows.insert(workflow_id, row);
+        Ok(())
+    }
+
+    async fn delete_workflow(&self, workflow_id: WorkflowId) -> DatabaseResult<()> {
+        let mut state = self.lock();
+        let row = state.workflows.get_mut(&workflow_id);
+        if let Some(row) = row.filter(|x| x.deleted_at.is_none()) {
+            row.deleted_at = Some(OffsetDateTime::now_utc());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+//! Test utilities, enabled with the `testing` feature.
+//!
+//! See [`TestDatabase`], [`InMemoryDatabase`], [`explain`] and entity
+//! factories (e.g. [`WorkflowFactory`]) for more details.
+
+mod factories;
+mod in_memory;
+mod query_plan;
+mod test_database;
+
+pub use crate::testing::factories::{
+    AccountFactory, ExecutionFactory, MemberFactory, ScheduleFactory, WebhookFactory,
+    WorkflowFactory, WorkspaceFactory,
+};
+pub use crate::testing::in_memory::InMemoryDatabase;
+pub use crate::testing::query_plan::{explain, QueryPlan};
+pub use crate::testing::test_database::TestDatabase;
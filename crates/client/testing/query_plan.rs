@@ -0,0 +1,232 @@
+//! Query plan assertions with `EXPLAIN (FORMAT JSON)`.
+
+use std::fmt;
+
+use diesel::pg::Pg;
+use diesel::query_builder::{AstPass, Query, QueryFragment, QueryId};
+use diesel::sql_types::Json;
+use diesel::QueryResult;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use serde_json::Value;
+
+use crate::{DatabaseError, DatabaseResult};
+
+/// `EXPLAIN (FORMAT JSON)` of the wrapped query.
+#[derive(Debug, Clone)]
+struct Explain<Q>(Q);
+
+impl<Q> QueryFragment<Pg> for Explain<Q>
+where
+    Q: QueryFragment<Pg>,
+{
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        out.push_sql("EXPLAIN (FORMAT JSON) ");
+        self.0.walk_ast(out.reborrow())
+    }
+}
+
+impl<Q> QueryId for Explain<Q> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<Q> Query for Explain<Q> {
+    type SqlType = Json;
+}
+
+/// Returns the plan of the query, without executing it.
+///
+/// Sequential scans are disabled (`SET LOCAL enable_seqscan = off`)
+/// for the duration of the plan, so any remaining sequential scan
+/// means there is no usable index, regardless of the table size.
+pub async fn explain<Q>(conn: &mut AsyncPgConnection, query: Q) -> DatabaseResult<QueryPlan>
+where
+    Q: QueryFragment<Pg> + Send,
+{
+    let plan = conn
+        .transaction::<_, DatabaseError, _>(|conn| {
+            async move {
+                let disable = "SET LOCAL enable_seqscan = off";
+                let _ = diesel::sql_query(disable).execute(conn).await?;
+                let plan: Value = Explain(query).get_result(conn).await?;
+                Ok(plan)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(QueryPlan { plan })
+}
+
+/// Plan of the query, returned by [`explain`].
+#[derive(Debug, Clone)]
+pub struct QueryPlan {
+    plan: Value,
+}
+
+impl QueryPlan {
+    /// Returns names of all indexes used by the plan.
+    pub fn indexes(&self) -> Vec<&str> {
+        self.nodes()
+            .into_iter()
+            .filter_map(|x| x.get("Index Name")?.as_str())
+            .collect()
+    }
+
+    /// Returns names of all sequentially scanned relations.
+    pub fn seq_scans(&self) -> Vec<&str> {
+        self.nodes()
+            .into_iter()
+            .filter(|x| x.get("Node Type").and_then(Value::as_str) == Some("Seq Scan"))
+            .filter_map(|x| x.get("Relation Name")?.as_str())
+            .collect()
+    }
+
+    /// Panics if the plan does not use the index.
+    #[track_caller]
+    pub fn assert_index(&self, name: &str) -> &Self {
+        let indexes = self.indexes();
+        assert!(
+            indexes.contains(&name),
+            "expected index `{name}`, used: {indexes:?}\n{self}"
+        );
+
+        self
+    }
+
+    /// Panics if the plan scans any relation sequentially.
+    #[track_caller]
+    pub fn assert_no_seq_scan(&self) -> &Self {
+        let seq_scans = self.seq_scans();
+        assert!(
+            seq_scans.is_empty(),
+            "expected no sequential scans, scanned: {seq_scans:?}\n{self}"
+        );
+
+        self
+    }
+
+    /// Returns all plan nodes, depth-first.
+    fn nodes(&self) -> Vec<&Value> {
+        let mut stack: Vec<_> = self
+            .plan
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|x| x.get("Plan"))
+            .collect();
+
+        let mut nodes = Vec::new();
+        while let Some(node) = stack.pop() {
+            if let Some(plans) = node.get("Plans").and_then(Value::as_array) {
+                stack.extend(plans.iter().rev());
+            }
+
+            nodes.push(node);
+        }
+
+        nodes
+    }
+}
+
+impl fmt::Display for QueryPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match serde_json::to_string_pretty(&self.plan) {
+            Ok(plan) => f.write_str(&plan),
+            Err(_) => fmt::Display::fmt(&self.plan, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axiston_db_schema::enumerations::{AccountStatus, TokenAction};
+    use axiston_db_schema::secrets::ActionToken;
+    use time::OffsetDateTime;
+
+    use crate::account_login_attempts::{
+        count_ip_failures_query, view_recent_account_attempts_query, LockoutPolicy,
+    };
+    use crate::account_sessions::{
+        authenticate_query, create_session, find_active_session_query, view_active_sessions_query,
+        AccountSession,
+    };
+    use crate::account_tokens::{consume_action_token_query, AccountTokenViewInput};
+    use crate::accounts::{find_account_by_email_query, view_account_query};
+    use crate::testing::{explain, AccountFactory, TestDatabase, WorkflowFactory};
+    use crate::workspace_schedules::view_workflows_by_interval_query;
+    use crate::DatabaseResult;
+
+    #[tokio::test]
+    async fn query_plans() -> DatabaseResult<()> {
+        let database = TestDatabase::new().await?;
+        let mut conn = database.get_connection().await?;
+
+        let account = AccountFactory::new()
+            .status(AccountStatus::Active)
+            .create(&mut conn)
+            .await?;
+        let _ = WorkflowFactory::new().create(&mut conn).await?;
+        let form = AccountSession {
+            region_id: "EU".to_owned(),
+            ip_address: "127.0.0.1/32".parse().unwrap(),
+            user_agent: "Mozilla/5.0".to_owned(),
+        };
+
+        let token = create_session(&mut conn, account.id, form).await?;
+
+        let query = view_account_query(account.id);
+        let plan = explain(&mut conn, query).await?;
+        plan.assert_index("accounts_pkey").assert_no_seq_scan();
+
+        let query = find_account_by_email_query("Account@Example.com");
+        let plan = explain(&mut conn, query).await?;
+        plan.assert_index("accounts_credentials_idx")
+            .assert_no_seq_scan();
+
+        let query = find_active_session_query(token.clone());
+        let plan = explain(&mut conn, query).await?;
+        plan.assert_index("account_sessions_active_idx")
+            .assert_no_seq_scan();
+
+        let query = authenticate_query(token.clone());
+        let plan = explain(&mut conn, query).await?;
+        plan.assert_index("account_sessions_pkey")
+            .assert_index("accounts_pkey")
+            .assert_no_seq_scan();
+
+        let query = view_active_sessions_query(account.id);
+        let plan = explain(&mut conn, query).await?;
+        plan.assert_index("account_sessions_active_idx")
+            .assert_no_seq_scan();
+
+        let policy = LockoutPolicy::default();
+        let query = view_recent_account_attempts_query(account.id, policy);
+        let plan = explain(&mut conn, query).await?;
+        plan.assert_index("account_login_attempts_account_idx")
+            .assert_no_seq_scan();
+
+        let query = count_ip_failures_query("127.0.0.1/32".parse().unwrap(), policy);
+        let plan = explain(&mut conn, query).await?;
+        plan.assert_index("account_login_attempts_ip_address_idx")
+            .assert_no_seq_scan();
+
+        let form = AccountTokenViewInput {
+            account_id: account.id,
+            action_token: ActionToken::generate(),
+        };
+
+        let query = consume_action_token_query(form, TokenAction::ResetPassword);
+        let plan = explain(&mut conn, query).await?;
+        plan.assert_index("account_tokens_idx").assert_no_seq_scan();
+
+        let query = view_workflows_by_interval_query(10, OffsetDateTime::now_utc());
+        let plan = explain(&mut conn, query).await?;
+        plan.assert_index("workflows_workspace_idx")
+            .assert_no_seq_scan();
+
+        Ok(())
+    }
+}
@@ -1,10 +1,35 @@
 //! Additional SQL definitions and utilities.
 
 use diesel::define_sql_function;
-use diesel::sql_types::Timestamptz;
+use diesel::pg::data_types::PgInterval;
+use diesel::sql_types::{Array, Bytea, Nullable, Timestamptz};
+use time::Duration;
 
 define_sql_function!(
     /// Represents the `AGE` SQL function.
     #[sql_name = "AGE"]
     fn age(end: Timestamptz, start: Timestamptz) -> Interval
 );
+
+define_sql_function!(
+    /// Represents the `ARRAY_REMOVE` SQL function.
+    #[sql_name = "ARRAY_REMOVE"]
+    fn array_remove(array: Array<Nullable<Bytea>>, element: Bytea) -> Array<Nullable<Bytea>>
+);
+
+define_sql_function!(
+    /// Represents the `CARDINALITY` SQL function.
+    #[sql_name = "CARDINALITY"]
+    fn cardinality(array: Array<Nullable<Bytea>>) -> Integer
+);
+
+define_sql_function!(
+    /// Represents the `LEAST` SQL function.
+    #[sql_name = "LEAST"]
+    fn least(a: Timestamptz, b: Timestamptz) -> Timestamptz
+);
+
+/// Returns the duration as the `INTERVAL`, with microsecond precision.
+pub fn interval(duration: Duration) -> PgInterval {
+    PgInterval::from_microseconds(duration.whole_microseconds() as i64)
+}
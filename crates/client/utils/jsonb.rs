@@ -0,0 +1,54 @@
+//! Mirrors the `jsonb` text representation of [`Value`]s.
+
+use serde_json::Value;
+
+/// Returns the length, in characters, of `value::TEXT` for the `jsonb` value.
+///
+/// Matches `length(value::TEXT)` in `UTF8` databases.
+///
+/// Unlike [`serde_json::to_string`], `jsonb` separates object entries and
+/// array elements with `", "` and keys from values with `": "`.
+/// Numbers are assumed to be rendered identically.
+pub fn text_length(value: &Value) -> usize {
+    match value {
+        Value::Null => 4,
+        Value::Bool(x) => {
+            if *x {
+                4
+            } else {
+                5
+            }
+        }
+        Value::Number(x) => x.to_string().len(),
+        Value::String(x) => string_length(x),
+        Value::Array(xs) => {
+            let elements: usize = xs.iter().map(text_length).sum();
+            2 + elements + separators(xs.len())
+        }
+        Value::Object(xs) => {
+            let entries: usize = xs
+                .iter()
+                .map(|(k, v)| string_length(k) + 2 + text_length(v))
+                .sum();
+            2 + entries + separators(xs.len())
+        }
+    }
+}
+
+/// Returns the length of the quoted and escaped string.
+fn string_length(value: &str) -> usize {
+    let escaped: usize = value
+        .chars()
+        .map(|x| match x {
+            '"' | '\\' | '\u{8}' | '\u{c}' | '\n' | '\r' | '\t' => 2,
+            x if x < ' ' => 6,
+            _ => 1,
+        })
+        .sum();
+    escaped + 2
+}
+
+/// Returns the length of all `", "` separators between elements.
+fn separators(elements: usize) -> usize {
+    elements.saturating_sub(1) * 2
+}
@@ -1,4 +1,5 @@
 //! Additional utilities for (de)serialization or object mapping.
 
 pub mod dsl;
+pub mod jsonb;
 pub mod serde;
@@ -1,5 +1,11 @@
 #![forbid(unsafe_code)]
 
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use sha2::{Digest, Sha256};
+
 /// Due to limitations in Rust's proc-macro API, there is currently no way to
 /// signal that a specific procedural macro should be rerun if
 /// an external file changes or is added.
@@ -9,6 +15,570 @@
 ///
 /// To work around this limitation, you can add a custom `build.rs` file to your crate.
 /// This ensures the crate is rebuilt whenever the migration directory changes.
+///
+/// Also generates the expected catalog (see `types/catalog.rs`) from the
+/// generated `schema.rs` file and the migration directory, and constraint
+/// definitions (see `types/constraints.rs`) from the migration directory.
+/// The catalog also includes the digest of all migrations, used to name
+/// test templates.
 fn main() {
     println!("cargo:rerun-if-changed=./migrations");
+    println!("cargo:rerun-if-changed=./schema.rs");
+
+    let schema = fs::read_to_string("./schema.rs").expect("schema.rs should be readable");
+    let tables = parse_schema(&schema);
+    let directories = migration_directories(Path::new("./migrations"));
+    let scripts = migration_scripts(&directories);
+    let digest = migrations_digest(&directories);
+    let indexes = parse_indexes(&scripts);
+    let constraints = parse_constraints(&scripts);
+
+    let out_dir = env::var("OUT_DIR").expect("should be set by cargo");
+    let out_path = Path::new(&out_dir).join("catalog.rs");
+    fs::write(out_path, render_catalog(&tables, &indexes, &digest)).expect("should be writable");
+    let out_path = Path::new(&out_dir).join("constraints.rs");
+    fs::write(out_path, render_constraints(&constraints)).expect("should be writable");
+}
+
+struct Column {
+    name: String,
+    sql_type: String,
+    nullable: bool,
+}
+
+/// Parses `diesel::table!` definitions generated by `diesel print-schema`.
+fn parse_schema(schema: &str) -> BTreeMap<String, Vec<Column>> {
+    let mut custom_types = BTreeMap::new();
+    let mut tables = BTreeMap::new();
+
+    let mut postgres_type = None;
+    let mut current_table = None;
+    let mut sql_name = None;
+
+    for line in schema.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("#[diesel(postgres_type(name = \"") {
+            postgres_type = rest.split('"').next().map(str::to_owned);
+        } else if let Some(rest) = line.strip_prefix("pub struct ") {
+            let struct_name = rest.trim_end_matches(';').to_owned();
+            if let Some(type_name) = postgres_type.take() {
+                custom_types.insert(struct_name, type_name);
+            }
+        } else if let Some(rest) = line.strip_prefix("#[sql_name = \"") {
+            sql_name = rest.split('"').next().map(str::to_owned);
+        } else if line.ends_with('{') && line.contains(" (") && !line.contains('!') {
+            let table_name = line.split_whitespace().next().unwrap_or_default();
+            tables.insert(table_name.to_owned(), Vec::new());
+            current_table = Some(table_name.to_owned());
+        } else if line == "}" {
+            current_table = None;
+        } else if let (Some(table), Some((name, sql_type))) =
+            (&current_table, line.split_once(" -> "))
+        {
+            let sql_type = sql_type.trim_end_matches(',');
+            let (sql_type, nullable) = match sql_type.strip_prefix("Nullable<") {
+                Some(inner) => (inner.strip_suffix('>').unwrap_or(inner), true),
+                None => (sql_type, false),
+            };
+
+            let column = Column {
+                name: sql_name.take().unwrap_or_else(|| name.to_owned()),
+                sql_type: udt_name(sql_type, &custom_types),
+                nullable,
+            };
+
+            tables
+                .entry(table.clone())
+                .or_insert_with(Vec::new)
+                .push(column);
+        }
+    }
+
+    tables
+}
+
+/// Converts the `diesel` SQL type into the `information_schema` UDT name.
+fn udt_name(sql_type: &str, custom_types: &BTreeMap<String, String>) -> String {
+    if let Some(inner) = sql_type.strip_prefix("Array<") {
+        let inner = inner.strip_suffix('>').unwrap_or(inner);
+        let inner = inner.strip_prefix("Nullable<").unwrap_or(inner);
+        let inner = inner.strip_suffix('>').unwrap_or(inner);
+        return format!("_{}", udt_name(inner, custom_types));
+    }
+
+    match custom_types.get(sql_type) {
+        Some(type_name) => type_name.clone(),
+        None => sql_type.to_lowercase(),
+    }
+}
+
+/// Returns all migration directories, in the order of migrations.
+fn migration_directories(migrations: &Path) -> Vec<PathBuf> {
+    let mut directories: Vec<_> = fs::read_dir(migrations)
+        .expect("migrations should be readable")
+        .map(|entry| entry.expect("migrations should be readable").path())
+        .filter(|path| path.is_dir())
+        .collect();
+    directories.sort();
+    directories
+}
+
+/// Returns contents of all `up.sql` scripts, in the order of migrations.
+fn migration_scripts(directories: &[PathBuf]) -> Vec<String> {
+    directories
+        .iter()
+        .map(|x| fs::read_to_string(x.join("up.sql")).expect("up.sql should be readable"))
+        .collect()
+}
+
+/// Returns the hex-encoded `SHA-256` digest of names, `up.sql` and `down.sql`
+/// scripts of all migrations.
+fn migrations_digest(directories: &[PathBuf]) -> String {
+    let mut hasher = Sha256::new();
+    for directory in directories {
+        let name = directory.file_name().expect("should have a name");
+        let up = fs::read(directory.join("up.sql")).expect("up.sql should be readable");
+        let down = fs::read(directory.join("down.sql")).unwrap_or_default();
+
+        // Lengths prevent different migrations from producing the same input.
+        for part in [name.as_encoded_bytes(), &up, &down] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+    }
+
+    let digest = hasher.finalize();
+    digest.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+/// Collects names of indexes created (and not dropped) by migrations.
+///
+/// Handles `UNIQUE`, `CONCURRENTLY`, `IF [NOT] EXISTS`, quoted names,
+/// comments and statements split over multiple lines.
+fn parse_indexes(scripts: &[String]) -> Vec<String> {
+    let mut indexes = Vec::new();
+    for up in scripts {
+        let script = strip_comments(up);
+        let tokens: Vec<_> = script
+            .split_whitespace()
+            .map(|x| x.trim_end_matches(';'))
+            .collect();
+
+        for (i, window) in tokens.windows(2).enumerate() {
+            let is_drop = match tokens.get(i.wrapping_sub(1)) {
+                Some(x) if x.eq_ignore_ascii_case("DROP") => true,
+                Some(x) if x.eq_ignore_ascii_case("CREATE") => false,
+                Some(x) if x.eq_ignore_ascii_case("UNIQUE") => false,
+                _ => continue,
+            };
+
+            if !window[0].eq_ignore_ascii_case("INDEX") {
+                continue;
+            }
+
+            let is_keyword = |x: &&str| {
+                ["CONCURRENTLY", "IF", "NOT", "EXISTS"]
+                    .iter()
+                    .any(|keyword| x.eq_ignore_ascii_case(keyword))
+            };
+
+            let Some(index_name) = tokens[i + 1..].iter().find(|x| !is_keyword(x)) else {
+                continue;
+            };
+
+            let index_name = index_name.trim_matches('"').to_lowercase();
+            match is_drop {
+                true => indexes.retain(|x| *x != index_name),
+                false if !indexes.contains(&index_name) => indexes.push(index_name),
+                false => {}
+            }
+        }
+    }
+
+    indexes.sort();
+    indexes
+}
+
+struct Constraint {
+    name: String,
+    table: String,
+    kind: &'static str,
+    columns: Vec<String>,
+    rule: Option<String>,
+    definition: String,
+}
+
+/// Collects `CHECK`, `UNIQUE` and `PRIMARY KEY` constraints created
+/// (and not dropped) by migrations, in the order of their creation.
+fn parse_constraints(scripts: &[String]) -> Vec<Constraint> {
+    let mut columns: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut constraints: Vec<Constraint> = Vec::new();
+
+    for up in scripts {
+        for statement in split_top_level(&strip_comments(up), ';') {
+            let statement = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+            if let Some(rest) = strip_keywords(&statement, &["CREATE", "TABLE"]) {
+                let rest = strip_keywords(rest, &["IF", "NOT", "EXISTS"]).unwrap_or(rest);
+                let Some((table, body)) = rest.split_once(" (") else {
+                    continue;
+                };
+
+                let body = body.rsplit_once(')').map_or(body, |(x, _)| x);
+                let elements: Vec<_> = split_top_level(body, ',');
+                let table_columns = columns.entry(table.to_owned()).or_default();
+                table_columns.extend(elements.iter().filter_map(|x| column_name(x.trim())));
+
+                for element in elements {
+                    parse_element(table, element.trim(), &columns, &mut constraints);
+                }
+            } else if let Some(rest) = strip_keywords(&statement, &["ALTER", "TABLE"]) {
+                let rest = strip_keywords(rest, &["IF", "EXISTS"]).unwrap_or(rest);
+                let rest = strip_keywords(rest, &["ONLY"]).unwrap_or(rest);
+                let Some((table, actions)) = rest.split_once(' ') else {
+                    continue;
+                };
+
+                for action in split_top_level(actions, ',') {
+                    let action = action.trim();
+                    if let Some(element) = strip_keywords(action, &["ADD", "COLUMN"]) {
+                        let element =
+                            strip_keywords(element, &["IF", "NOT", "EXISTS"]).unwrap_or(element);
+                        let table_columns = columns.entry(table.to_owned()).or_default();
+                        table_columns.extend(column_name(element));
+                        parse_element(table, element, &columns, &mut constraints);
+                    } else if let Some(element) = strip_keywords(action, &["ADD"]) {
+                        parse_element(table, element, &columns, &mut constraints);
+                    } else if let Some(name) = strip_keywords(action, &["DROP", "CONSTRAINT"]) {
+                        let name = strip_keywords(name, &["IF", "EXISTS"]).unwrap_or(name);
+                        let name = name.split(' ').next().unwrap_or_default();
+                        constraints.retain(|x| x.name != name);
+                    } else if let Some(rest) = strip_keywords(action, &["RENAME", "CONSTRAINT"]) {
+                        if let Some((from, to)) = rest.split_once(" TO ") {
+                            let constraint = constraints.iter_mut().find(|x| x.name == from);
+                            if let Some(constraint) = constraint {
+                                constraint.name = to.to_owned();
+                            }
+                        }
+                    }
+                }
+            } else if let Some(rest) = strip_keywords(&statement, &["DROP", "TABLE"]) {
+                let rest = strip_keywords(rest, &["IF", "EXISTS"]).unwrap_or(rest);
+                for table in rest.split([',', ' ']).filter(|x| !x.is_empty()) {
+                    constraints.retain(|x| x.table != table);
+                }
+            }
+        }
+    }
+
+    constraints
+}
+
+/// Returns the name of the column, if the element is a column definition.
+fn column_name(element: &str) -> Option<String> {
+    let keywords = [
+        "CONSTRAINT",
+        "PRIMARY",
+        "UNIQUE",
+        "CHECK",
+        "FOREIGN",
+        "EXCLUDE",
+    ];
+    let first = element.split(' ').next().filter(|x| !x.is_empty())?;
+    (!keywords.contains(&first.to_uppercase().as_str())).then(|| first.to_owned())
+}
+
+/// Parses a single column or table constraint definition of the table.
+fn parse_element(
+    table: &str,
+    element: &str,
+    columns: &BTreeMap<String, Vec<String>>,
+    constraints: &mut Vec<Constraint>,
+) {
+    let (name, definition) = match strip_keywords(element, &["CONSTRAINT"]) {
+        Some(rest) => match rest.split_once(' ') {
+            Some((name, definition)) => (Some(name.to_owned()), definition),
+            None => return,
+        },
+        None => (None, element),
+    };
+
+    let first = definition.split(' ').next().unwrap_or_default();
+    if let Some(column) = column_name(element) {
+        let upper = definition.to_uppercase();
+        if upper.contains(" PRIMARY KEY") {
+            let constraint = Constraint {
+                name: format!("{table}_pkey"),
+                table: table.to_owned(),
+                kind: "PrimaryKey",
+                columns: vec![column.clone()],
+                rule: None,
+                definition: "PRIMARY KEY".to_owned(),
+            };
+
+            constraints.push(constraint);
+        }
+
+        if upper.split(' ').any(|x| x == "UNIQUE") {
+            let constraint = Constraint {
+                name: format!("{table}_{column}_key"),
+                table: table.to_owned(),
+                kind: "Unique",
+                columns: vec![column],
+                rule: None,
+                definition: "UNIQUE".to_owned(),
+            };
+
+            constraints.push(constraint);
+        }
+
+        return;
+    }
+
+    let table_columns = columns.get(table).cloned().unwrap_or_default();
+    let inner = definition
+        .split_once('(')
+        .and_then(|(_, x)| x.rsplit_once(')'))
+        .map_or("", |(x, _)| x.trim());
+    let listed = || inner.split(',').map(|x| x.trim().to_owned()).collect();
+
+    let (kind, columns, rule) = match first.to_uppercase().as_str() {
+        "CHECK" => {
+            let columns = inner
+                .split(|x: char| !x.is_alphanumeric() && x != '_')
+                .filter(|x| table_columns.iter().any(|c| c == x))
+                .fold(Vec::new(), |mut acc, x| {
+                    if !acc.iter().any(|c| c == x) {
+                        acc.push(x.to_owned());
+                    }
+
+                    acc
+                });
+
+            ("Check", columns, parse_rule(inner))
+        }
+        "UNIQUE" => ("Unique", listed(), None),
+        "PRIMARY" => ("PrimaryKey", listed(), None),
+        _ => return,
+    };
+
+    let name = name.unwrap_or_else(|| match kind {
+        "PrimaryKey" => format!("{table}_pkey"),
+        "Unique" => format!("{table}_{}_key", columns.join("_")),
+        _ => format!(
+            "{table}_{}_check",
+            columns.first().cloned().unwrap_or_default()
+        ),
+    });
+
+    let constraint = Constraint {
+        name,
+        table: table.to_owned(),
+        kind,
+        columns,
+        rule,
+        definition: definition.to_owned(),
+    };
+
+    constraints.push(constraint);
+}
+
+/// Converts the `CHECK` expression into the `ConstraintRule`, if recognized.
+fn parse_rule(expression: &str) -> Option<String> {
+    let is_identifier =
+        |x: &str| !x.is_empty() && x.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+    if expression.strip_suffix(" <> ''").is_some_and(is_identifier) {
+        return Some("NonEmpty".to_owned());
+    }
+
+    if let Some(rest) = expression.strip_prefix("length(") {
+        let (column, limit) = rest.split_once("::TEXT) <= ")?;
+        let limit: usize = limit.parse().ok()?;
+        return is_identifier(column).then(|| format!("MaxLength({limit})"));
+    }
+
+    if let Some(column) = expression.strip_suffix(" ~ '^[A-Z0-9]{2}$'") {
+        return is_identifier(column).then(|| "RegionCode".to_owned());
+    }
+
+    let mut disjuncts: Vec<_> = expression.split(" OR ").collect();
+    let (later, earlier) = disjuncts.pop()?.split_once(" >= ")?;
+    let is_nullable = disjuncts
+        .iter()
+        .all(|x| x.strip_suffix(" IS NULL").is_some_and(is_identifier));
+    (is_nullable && is_identifier(later) && is_identifier(earlier))
+        .then(|| "Chronological".to_owned())
+}
+
+/// Returns the rest of the statement if it starts with all keywords.
+fn strip_keywords<'a>(statement: &'a str, keywords: &[&str]) -> Option<&'a str> {
+    keywords.iter().try_fold(statement, |rest, keyword| {
+        let (head, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+        head.eq_ignore_ascii_case(keyword).then_some(tail)
+    })
+}
+
+/// Removes `--` comments, outside of string literals.
+fn strip_comments(script: &str) -> String {
+    let mut output = String::with_capacity(script.len());
+    for line in script.lines() {
+        let mut in_literal = false;
+        let mut end = line.len();
+        for (i, x) in line.char_indices() {
+            match x {
+                '\'' => in_literal = !in_literal,
+                '-' if !in_literal && line[i..].starts_with("--") => {
+                    end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        output.push_str(&line[..end]);
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Splits by the separator outside of parentheses, literals and `$$` bodies.
+fn split_top_level(input: &str, separator: char) -> Vec<&str> {
+    let (mut depth, mut in_literal, mut in_body) = (0i32, false, false);
+    let (mut parts, mut start) = (Vec::new(), 0);
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((i, x)) = chars.next() {
+        match x {
+            '$' if !in_literal && chars.peek().map(|x| x.1) == Some('$') => {
+                let _ = chars.next();
+                in_body = !in_body;
+            }
+            '\'' if !in_body => in_literal = !in_literal,
+            '(' if !in_literal && !in_body => depth += 1,
+            ')' if !in_literal && !in_body => depth -= 1,
+            x if x == separator && depth == 0 && !in_literal && !in_body => {
+                parts.push(&input[start..i]);
+                start = i + x.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&input[start..]);
+    parts.retain(|x| !x.trim().is_empty());
+    parts
+}
+
+/// Renders the contents of the generated `catalog.rs` file.
+fn render_catalog(
+    tables: &BTreeMap<String, Vec<Column>>,
+    indexes: &[String],
+    digest: &str,
+) -> String {
+    let mut output = String::from("// @generated automatically by build.rs.\n\n");
+
+    output.push_str("/// All tables expected by the compiled crate.\n");
+    output.push_str("pub const TABLES: &[TableDef] = &[\n");
+    for (table, columns) in tables {
+        output.push_str(&format!("    TableDef {{\n        name: {table:?},\n"));
+        output.push_str("        columns: &[\n");
+        for column in columns {
+            output.push_str(&format!(
+                "            ColumnDef {{ name: {:?}, sql_type: {:?}, nullable: {} }},\n",
+                column.name, column.sql_type, column.nullable
+            ));
+        }
+
+        output.push_str("        ],\n    },\n");
+    }
+
+    output.push_str("];\n\n");
+    output.push_str("/// All indexes expected by the compiled crate.\n");
+    output.push_str(&format!("pub const INDEXES: &[&str] = &{indexes:?};\n\n"));
+    output.push_str("/// Hex-encoded `SHA-256` digest of all migrations.\n");
+    output.push_str(&format!(
+        "pub const MIGRATIONS_DIGEST: &str = {digest:?};\n"
+    ));
+    output
+}
+
+/// Renders the contents of the generated `constraints.rs` file.
+fn render_constraints(constraints: &[Constraint]) -> String {
+    let mut output = String::from("// @generated automatically by build.rs.\n\n");
+
+    let mut variants = Vec::new();
+    for constraint in constraints {
+        let name = match constraint
+            .name
+            .starts_with(&format!("{}_", constraint.table))
+        {
+            true => constraint.name.clone(),
+            false => format!("{}_{}", constraint.table, constraint.name),
+        };
+
+        let variant: String = name
+            .split('_')
+            .flat_map(|x| {
+                let mut chars = x.chars();
+                chars
+                    .next()
+                    .map(|c| c.to_ascii_uppercase())
+                    .into_iter()
+                    .chain(chars)
+            })
+            .collect();
+
+        assert!(
+            !variants.contains(&variant),
+            "constraint `{}` is defined more than once",
+            constraint.name
+        );
+
+        variants.push(variant);
+    }
+
+    output.push_str("/// Comprehensive list of all constraint violations.\n///\n");
+    output.push_str("/// Includes all check, unique and primary key constraints of migrations.\n");
+    output.push_str(
+        "#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, EnumString, EnumIter, Display)]\n",
+    );
+    output.push_str("#[cfg_attr(feature = \"serde\", derive(Serialize, Deserialize))]\n");
+    output.push_str("#[must_use = \"constraints do nothing unless they are used\"]\n");
+    output.push_str("pub enum ConstraintViolation {\n");
+    for (constraint, variant) in constraints.iter().zip(&variants) {
+        output.push_str(&format!(
+            "    /// `{}` on `{}`.\n",
+            constraint.definition.replace('`', "'"),
+            constraint.table
+        ));
+        output.push_str(&format!(
+            "    #[strum(serialize = {:?})]\n",
+            constraint.name
+        ));
+        output.push_str(&format!("    {variant},\n"));
+    }
+
+    output.push_str("}\n\n");
+    output.push_str(
+        "/// Definitions of all constraints, in the order of [`ConstraintViolation`] variants.\n",
+    );
+    output.push_str("pub const CONSTRAINTS: &[ConstraintDef] = &[\n");
+    for constraint in constraints {
+        let rule = match &constraint.rule {
+            Some(rule) => format!("Some(ConstraintRule::{rule})"),
+            None => "None".to_owned(),
+        };
+
+        output.push_str(&format!(
+            "    ConstraintDef {{\n        name: {:?},\n        table: {:?},\n",
+            constraint.name, constraint.table
+        ));
+        output.push_str(&format!(
+            "        kind: ConstraintKind::{},\n        columns: &{:?},\n        rule: {rule},\n    }},\n",
+            constraint.kind, constraint.columns
+        ));
+    }
+
+    output.push_str("];\n");
+    output
 }
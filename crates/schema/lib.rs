@@ -2,7 +2,10 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![doc = include_str!("./README.md")]
 
-pub use crate::types::{constraints, enumerations};
+#[cfg(feature = "i18n")]
+#[cfg_attr(docsrs, doc(cfg(feature = "i18n")))]
+pub use crate::types::i18n;
+pub use crate::types::{catalog, constraints, enumerations, identifiers, secrets};
 
 pub mod schema;
 mod types;
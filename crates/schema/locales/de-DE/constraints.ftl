@@ -0,0 +1,40 @@
+# Meldungen zu Constraint-Verletzungen, siehe `en-US/constraints.ftl`.
+
+constraint-non-empty = Das Feld „{ $field }“ darf nicht leer sein.
+constraint-max-length = Das Feld „{ $field }“ darf höchstens { $limit } Zeichen enthalten.
+constraint-region-code = Das Feld „{ $field }“ muss ein zweistelliger Regionscode sein.
+constraint-chronological = Das Feld „{ $field }“ darf nicht vor „{ $other }“ liegen.
+constraint-invalid = Das Feld „{ $field }“ ist ungültig.
+constraint-unique = Der Wert des Feldes „{ $field }“ ist bereits vergeben.
+constraint-primary-key = Der Wert des Feldes „{ $field }“ existiert bereits.
+
+field-display_name = Anzeigename
+field-email_address = E-Mail-Adresse
+field-password_hash = Passwort
+field-region_id = Region
+field-metadata = Metadaten
+field-rt_metadata = Laufzeit-Metadaten
+field-input_graph = Eingabegraph
+field-output_graph = Ausgabegraph
+field-token_data = Token-Daten
+field-token_hash = Token-Hash
+field-update_interval = Aktualisierungsintervall
+field-status_reason = Statusgrund
+field-id = ID
+field-account_id = Konto-ID
+field-invite_id = Einladungs-ID
+field-schedule_id = Zeitplan-ID
+field-webhook_id = Webhook-ID
+field-execution_id = Ausführungs-ID
+field-created_at = Erstellt am
+field-updated_at = Aktualisiert am
+field-deleted_at = Gelöscht am
+field-issued_at = Ausgestellt am
+field-expired_at = Läuft ab am
+field-used_at = Verwendet am
+field-started_at = Gestartet am
+field-ended_at = Beendet am
+field-confirmed_at = Bestätigt am
+field-status_changed_at = Status geändert am
+field-last_login_at = Letzte Anmeldung am
+field-last_seen_at = Zuletzt aktiv am
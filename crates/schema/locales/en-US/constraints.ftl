@@ -0,0 +1,45 @@
+# Messages of constraint violations, keyed by the rule of the constraint.
+# Any message may be overridden for a single constraint by its name,
+# e.g. `accounts_non_empty_email_address`, and any field name by `field-<column>`.
+#
+# Variables: $field (offending column), $other (second column), $table, $limit.
+
+constraint-non-empty = { $field } must not be empty.
+constraint-max-length = { $field } must not exceed { $limit } characters.
+constraint-region-code = { $field } must be a two-character region code.
+constraint-chronological = { $field } must not be earlier than { $other }.
+constraint-invalid = { $field } is invalid.
+constraint-unique = { $field } is already taken.
+constraint-primary-key = { $field } already exists.
+
+# Names of constrained columns, lower-cased as they are capitalized when needed.
+field-display_name = display name
+field-email_address = email address
+field-password_hash = password hash
+field-region_id = region id
+field-metadata = metadata
+field-rt_metadata = rt metadata
+field-input_graph = input graph
+field-output_graph = output graph
+field-token_data = token data
+field-token_hash = token hash
+field-update_interval = update interval
+field-status_reason = status reason
+field-id = id
+field-account_id = account id
+field-invite_id = invite id
+field-schedule_id = schedule id
+field-webhook_id = webhook id
+field-execution_id = execution id
+field-created_at = created at
+field-updated_at = updated at
+field-deleted_at = deleted at
+field-issued_at = issued at
+field-expired_at = expired at
+field-used_at = used at
+field-started_at = started at
+field-ended_at = ended at
+field-confirmed_at = confirmed at
+field-status_changed_at = status changed at
+field-last_login_at = last login at
+field-last_seen_at = last seen at
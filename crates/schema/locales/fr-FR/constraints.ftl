@@ -0,0 +1,40 @@
+# Messages des violations de contraintes, voir `en-US/constraints.ftl`.
+
+constraint-non-empty = Le champ « { $field } » ne doit pas être vide.
+constraint-max-length = Le champ « { $field } » ne doit pas dépasser { $limit } caractères.
+constraint-region-code = Le champ « { $field } » doit être un code de région à deux caractères.
+constraint-chronological = Le champ « { $field } » ne doit pas être antérieur à « { $other } ».
+constraint-invalid = Le champ « { $field } » est invalide.
+constraint-unique = La valeur du champ « { $field } » est déjà utilisée.
+constraint-primary-key = La valeur du champ « { $field } » existe déjà.
+
+field-display_name = Nom d'affichage
+field-email_address = Adresse e-mail
+field-password_hash = Mot de passe
+field-region_id = Région
+field-metadata = Métadonnées
+field-rt_metadata = Métadonnées d'exécution
+field-input_graph = Graphe d'entrée
+field-output_graph = Graphe de sortie
+field-token_data = Données du jeton
+field-token_hash = Empreinte du jeton
+field-update_interval = Intervalle de mise à jour
+field-status_reason = Motif du statut
+field-id = Identifiant
+field-account_id = Identifiant du compte
+field-invite_id = Identifiant de l'invitation
+field-schedule_id = Identifiant de la planification
+field-webhook_id = Identifiant du webhook
+field-execution_id = Identifiant de l'exécution
+field-created_at = Date de création
+field-updated_at = Date de mise à jour
+field-deleted_at = Date de suppression
+field-issued_at = Date d'émission
+field-expired_at = Date d'expiration
+field-used_at = Date d'utilisation
+field-started_at = Date de début
+field-ended_at = Date de fin
+field-confirmed_at = Date de confirmation
+field-status_changed_at = Date de changement de statut
+field-last_login_at = Date de dernière connexion
+field-last_seen_at = Date de dernière activité
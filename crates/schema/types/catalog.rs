@@ -0,0 +1,53 @@
+//! Tables, columns, indexes and migrations expected by the compiled crate.
+//!
+//! Generated by `build.rs` from `schema.rs` and the migration directory.
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// Definition of the single table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TableDef {
+    /// Name of the table.
+    pub name: &'static str,
+    /// All columns of the table, in the declaration order.
+    pub columns: &'static [ColumnDef],
+}
+
+/// Definition of the single column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ColumnDef {
+    /// Name of the column.
+    pub name: &'static str,
+    /// Name of the type, as in `information_schema.columns.udt_name`.
+    pub sql_type: &'static str,
+    /// Whether the column is nullable.
+    pub nullable: bool,
+}
+
+include!(concat!(env!("OUT_DIR"), "/catalog.rs"));
+
+#[cfg(test)]
+mod test {
+    use crate::catalog::{INDEXES, MIGRATIONS_DIGEST, TABLES};
+
+    #[test]
+    fn catalog_tables() {
+        let accounts = TABLES.iter().find(|x| x.name == "accounts").unwrap();
+        let deleted_at = accounts.columns.iter().find(|x| x.name == "deleted_at");
+        assert!(deleted_at.is_some_and(|x| x.nullable && x.sql_type == "timestamptz"));
+    }
+
+    #[test]
+    fn catalog_indexes() {
+        assert!(INDEXES.contains(&"accounts_email_address_idx"));
+    }
+
+    #[test]
+    fn catalog_digest() {
+        assert_eq!(MIGRATIONS_DIGEST.len(), 64);
+        assert!(MIGRATIONS_DIGEST.bytes().all(|x| x.is_ascii_hexdigit()));
+    }
+}
@@ -1,97 +1,70 @@
 //! Comprehensive list of all constraint violations.
+//!
+//! Generated by `build.rs` from the migration directory.
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use strum::{Display, EnumString};
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
 
-/// Comprehensive list of all constraint violations.
+/// Kind of the constraint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[must_use = "constraints do nothing unless they are used"]
+pub enum ConstraintKind {
+    /// `CHECK (expression)`.
+    Check,
+    /// `UNIQUE (columns)`.
+    Unique,
+    /// `PRIMARY KEY (columns)`.
+    PrimaryKey,
+}
+
+/// Category of the constraint violation, e.g. for mapping into status codes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[must_use = "constraints do nothing unless they are used"]
+pub enum ConstraintCategory {
+    /// Invalid input, rejected by the `CHECK` constraint.
+    Validation,
+    /// Conflicting input, rejected by the `UNIQUE` or `PRIMARY KEY` constraint.
+    Conflict,
+    /// Inconsistent row state, e.g. timestamps out of chronological order.
+    Integrity,
+}
+
+/// Definition of the single constraint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ConstraintDef {
+    /// Name of the constraint.
+    pub name: &'static str,
+    /// Name of the constrained table.
+    pub table: &'static str,
+    /// Kind of the constraint.
+    pub kind: ConstraintKind,
+    /// Constrained (or, for checks, referenced) columns.
+    pub columns: &'static [&'static str],
+    /// Rule of the `CHECK` constraint, if recognized.
+    pub rule: Option<ConstraintRule>,
+}
+
+/// Rule enforced by the `CHECK` constraint.
 ///
-/// This includes unique constraint violations as well as foreign key
-/// constraint violations for various tables, including workspaces.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString, Display)]
+/// Recognized from the expression of the constraint by `build.rs`, and
+/// shared by the database and client-side validation of input forms.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[must_use = "constraints do nothing unless they are used"]
-pub enum ConstraintViolation {
-    #[strum(serialize = "accounts_non_empty_display_name")]
-    AccountsNonEmptyName,
-    #[strum(serialize = "accounts_non_empty_email_address")]
-    AccountsNonEmptyEmail,
-    #[strum(serialize = "accounts_non_empty_password_hash")]
-    AccountsNonEmptyPassword,
-    #[strum(serialize = "accounts_updated_after_created")]
-    AccountsUpdatedAfterCreated,
-    #[strum(serialize = "accounts_deleted_after_created")]
-    AccountsDeletedAfterCreated,
-    #[strum(serialize = "accounts_deleted_after_updated")]
-    AccountsDeletedAfterUpdated,
-
-    #[strum(serialize = "account_sessions_region_alphanumeric")]
-    AccountSessionsRegionAlnum,
-    #[strum(serialize = "account_sessions_expired_after_issued")]
-    AccountSessionsExpiredAfterIssued,
-    #[strum(serialize = "account_sessions_deleted_after_issued")]
-    AccountSessionsDeletedAfterIssued,
-
-    #[strum(serialize = "account_permissions_updated_after_created")]
-    AccountPermissionsUpdatedAfterCreated,
-    #[strum(serialize = "account_permissions_deleted_after_created")]
-    AccountPermissionsDeletedAfterCreated,
-    #[strum(serialize = "account_permissions_deleted_after_updated")]
-    AccountPermissionsDeletedAfterUpdated,
-
-    #[strum(serialize = "account_tokens_expired_after_issued")]
-    AccountTokensExpiredAfterIssued,
-    #[strum(serialize = "account_tokens_used_after_issued")]
-    AccountTokensUsedAfterIssued,
-    #[strum(serialize = "account_tokens_expired_after_used")]
-    AccountTokensExpiredAfterUsed,
-
-    #[strum(serialize = "workspaces_unique_name")]
-    WorkspacesUniqueName,
-    #[strum(serialize = "workspaces_non_empty_name")]
-    WorkspacesNonEmptyName,
-    #[strum(serialize = "workspaces_metadata_limit")]
-    WorkspacesMetadataLimit,
-    #[strum(serialize = "workspaces_updated_after_created")]
-    WorkspacesUpdatedAfterCreated,
-    #[strum(serialize = "workspaces_deleted_after_created")]
-    WorkspacesDeletedAfterCreated,
-    #[strum(serialize = "workspaces_deleted_after_updated")]
-    WorkspacesDeletedAfterUpdated,
-
-    #[strum(serialize = "workflows_unique_display_name")]
-    WorkflowsUniqueName,
-    #[strum(serialize = "workflows_non_empty_display_name")]
-    WorkflowsNonEmptyName,
-    #[strum(serialize = "workflows_metadata_limit")]
-    WorkflowsMetadataLimit,
-    #[strum(serialize = "workflows_input_graph_limit")]
-    WorkflowsInputGraphLimit,
-    #[strum(serialize = "workflows_runtime_meta_limit")]
-    WorkflowsRuntimeMetaLimit,
-    #[strum(serialize = "workflows_updated_after_created")]
-    WorkflowsUpdatedAfterCreated,
-    #[strum(serialize = "workflows_deleted_after_created")]
-    WorkflowsDeletedAfterCreated,
-    #[strum(serialize = "workflows_deleted_after_updated")]
-    WorkflowsDeletedAfterUpdated,
-
-    #[strum(serialize = "workflow_schedules_pkey")]
-    WorkflowSchedulesUniquePair,
-
-    #[strum(serialize = "workflow_webhooks_pkey")]
-    WorkflowWebhooksUniquePair,
-
-    #[strum(serialize = "workflow_executions_output_graph_limit")]
-    WorkflowExecutionsOutputGraphLimit,
-    #[strum(serialize = "workflow_executions_runtime_meta_limit")]
-    WorkflowExecutionsRuntimeMetaLimit,
-    #[strum(serialize = "workflow_executions_ended_after_started")]
-    WorkflowExecutionsEndedAfterStarted,
-    #[strum(serialize = "workflow_executions_deleted_after_started")]
-    WorkflowExecutionsDeletedAfterStarted,
-    #[strum(serialize = "workflow_executions_deleted_after_ended")]
-    WorkflowExecutionsDeletedAfterEnded,
+pub enum ConstraintRule {
+    /// `column <> ''`.
+    NonEmpty,
+    /// `length(column::TEXT) <= limit`, in characters.
+    MaxLength(usize),
+    /// `column ~ '^[A-Z0-9]{2}$'`.
+    RegionCode,
+    /// `later >= earlier`.
+    Chronological,
 }
 
 impl ConstraintViolation {
@@ -99,17 +72,164 @@ impl ConstraintViolation {
     pub fn new(constraint: &str) -> Option<Self> {
         constraint.parse().ok()
     }
+
+    /// Returns an iterator over all known constraint violations.
+    pub fn all() -> impl Iterator<Item = Self> {
+        Self::iter()
+    }
+
+    /// Returns the definition of the constraint.
+    pub const fn def(self) -> &'static ConstraintDef {
+        &CONSTRAINTS[self as usize]
+    }
+
+    /// Returns the name of the constrained table.
+    pub const fn table(self) -> &'static str {
+        self.def().table
+    }
+
+    /// Returns the kind of the constraint.
+    pub const fn kind(self) -> ConstraintKind {
+        self.def().kind
+    }
+
+    /// Returns constrained (or, for checks, referenced) columns.
+    pub const fn columns(self) -> &'static [&'static str] {
+        self.def().columns
+    }
+
+    /// Returns the rule of the `CHECK` constraint, if recognized.
+    pub const fn rule(self) -> Option<ConstraintRule> {
+        self.def().rule
+    }
+
+    /// Returns the category of the constraint violation.
+    pub const fn category(self) -> ConstraintCategory {
+        match (self.kind(), self.rule()) {
+            (ConstraintKind::Check, Some(ConstraintRule::Chronological)) => {
+                ConstraintCategory::Integrity
+            }
+            (ConstraintKind::Check, _) => ConstraintCategory::Validation,
+            (ConstraintKind::Unique | ConstraintKind::PrimaryKey, _) => {
+                ConstraintCategory::Conflict
+            }
+        }
+    }
+
+    /// Returns the name of the offending field (column).
+    ///
+    /// That is the first referenced column of the check constraint, or the
+    /// last column of the unique or primary key constraint.
+    pub fn field(self) -> &'static str {
+        let columns = self.columns();
+        let field = match self.kind() {
+            ConstraintKind::Check => columns.first(),
+            ConstraintKind::Unique | ConstraintKind::PrimaryKey => columns.last(),
+        };
+
+        field.copied().unwrap_or(self.table())
+    }
+
+    /// Returns the user-facing message, in English.
+    ///
+    /// See the `i18n` feature for localized messages.
+    pub fn message(self) -> String {
+        let field = capitalize(&humanize(self.field()));
+        let other = self.columns().get(1).map(|x| humanize(x));
+
+        match (self.kind(), self.rule()) {
+            (ConstraintKind::Check, Some(ConstraintRule::NonEmpty)) => {
+                format!("{field} must not be empty.")
+            }
+            (ConstraintKind::Check, Some(ConstraintRule::MaxLength(limit))) => {
+                format!("{field} must not exceed {limit} characters.")
+            }
+            (ConstraintKind::Check, Some(ConstraintRule::RegionCode)) => {
+                format!("{field} must be a two-character region code.")
+            }
+            (ConstraintKind::Check, Some(ConstraintRule::Chronological)) => {
+                let other = other.unwrap_or_default();
+                format!("{field} must not be earlier than {other}.")
+            }
+            (ConstraintKind::Check, None) => format!("{field} is invalid."),
+            (ConstraintKind::Unique, _) => format!("{field} is already taken."),
+            (ConstraintKind::PrimaryKey, _) => format!("{field} already exists."),
+        }
+    }
+
+    /// Checks the text value against the rule of the constraint.
+    ///
+    /// Rules that do not apply to text values always pass.
+    pub fn check_text(self, value: &str) -> Result<(), Self> {
+        let is_valid = match self.rule() {
+            Some(ConstraintRule::NonEmpty) => !value.is_empty(),
+            Some(ConstraintRule::MaxLength(_)) => return self.check_length(value.chars().count()),
+            Some(ConstraintRule::RegionCode) => {
+                value.len() == 2
+                    && (value.bytes()).all(|x| x.is_ascii_uppercase() || x.is_ascii_digit())
+            }
+            _ => true,
+        };
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Checks the length of the value, in characters, against the rule of the constraint.
+    ///
+    /// Rules other than [`ConstraintRule::MaxLength`] always pass.
+    pub fn check_length(self, length: usize) -> Result<(), Self> {
+        match self.rule() {
+            Some(ConstraintRule::MaxLength(limit)) if length > limit => Err(self),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the later value does not precede the earlier one.
+    ///
+    /// Rules other than [`ConstraintRule::Chronological`] always pass.
+    pub fn check_order<T: PartialOrd>(self, earlier: &T, later: &T) -> Result<(), Self> {
+        match self.rule() {
+            Some(ConstraintRule::Chronological) if later < earlier => Err(self),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Converts the column name into words, e.g. `display_name` into `display name`.
+pub(crate) fn humanize(column: &str) -> String {
+    column.replace('_', " ")
+}
+
+/// Converts the first character into the uppercase.
+pub(crate) fn capitalize(words: &str) -> String {
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(x) => x.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
 }
 
+include!(concat!(env!("OUT_DIR"), "/constraints.rs"));
+
 #[cfg(test)]
 mod test {
-    use crate::constraints::ConstraintViolation;
+    use crate::constraints::{
+        ConstraintCategory, ConstraintKind, ConstraintRule, ConstraintViolation, CONSTRAINTS,
+    };
 
     #[test]
     fn parse_constraint_violation() {
         assert_eq!(
-            ConstraintViolation::new("workspaces_unique_name"),
-            Some(ConstraintViolation::WorkspacesUniqueName)
+            ConstraintViolation::new("workflows_unique_display_name"),
+            Some(ConstraintViolation::WorkflowsUniqueDisplayName)
+        );
+        assert_eq!(
+            ConstraintViolation::new("updated_after_created"),
+            Some(ConstraintViolation::WorkflowsUpdatedAfterCreated)
         );
         assert_eq!(ConstraintViolation::new("unknown_constraint"), None);
     }
@@ -117,8 +237,74 @@ mod test {
     #[test]
     fn stringify_constraint_violation() {
         assert_eq!(
-            ConstraintViolation::WorkspacesUniqueName.to_string(),
-            "workspaces_unique_name"
+            ConstraintViolation::WorkflowsUniqueDisplayName.to_string(),
+            "workflows_unique_display_name"
         );
     }
+
+    #[test]
+    fn constraint_definitions() {
+        assert_eq!(ConstraintViolation::all().count(), CONSTRAINTS.len());
+        for violation in ConstraintViolation::all() {
+            assert_eq!(violation.def().name, violation.to_string());
+        }
+
+        let violation = ConstraintViolation::WorkflowsUniqueDisplayName;
+        assert_eq!(violation.table(), "workflows");
+        assert_eq!(violation.kind(), ConstraintKind::Unique);
+        assert_eq!(violation.columns(), ["workspace_id", "display_name"]);
+
+        let violation = ConstraintViolation::AccountsPkey;
+        assert_eq!(violation.kind(), ConstraintKind::PrimaryKey);
+        assert_eq!(violation.columns(), ["id"]);
+
+        let violation = ConstraintViolation::AccountTokensExpiredAfterUsed;
+        assert_eq!(violation.kind(), ConstraintKind::Check);
+        assert_eq!(violation.columns(), ["expired_at", "used_at"]);
+        assert_eq!(violation.rule(), Some(ConstraintRule::Chronological));
+
+        let violation = ConstraintViolation::WorkspaceSchedulesUpdateIntervalNonZero;
+        assert_eq!(violation.rule(), None);
+    }
+
+    #[test]
+    fn check_constraint_rules() {
+        let violation = ConstraintViolation::AccountsNonEmptyDisplayName;
+        assert_eq!(violation.rule(), Some(ConstraintRule::NonEmpty));
+        assert_eq!(violation.check_text("Account"), Ok(()));
+        assert_eq!(violation.check_text(""), Err(violation));
+
+        let violation = ConstraintViolation::AccountSessionsRegionAlphanumeric;
+        assert_eq!(violation.check_text("EU"), Ok(()));
+        assert_eq!(violation.check_text("eu"), Err(violation));
+        assert_eq!(violation.check_text("EUR"), Err(violation));
+
+        let violation = ConstraintViolation::WorkflowsInputGraphLimit;
+        assert_eq!(violation.rule(), Some(ConstraintRule::MaxLength(4096)));
+        assert_eq!(violation.check_length(4096), Ok(()));
+        assert_eq!(violation.check_length(4097), Err(violation));
+
+        let violation = ConstraintViolation::WorkflowExecutionsEndedAfterStarted;
+        assert_eq!(violation.check_order(&1, &1), Ok(()));
+        assert_eq!(violation.check_order(&2, &1), Err(violation));
+    }
+
+    #[test]
+    fn constraint_messages() {
+        let violation = ConstraintViolation::AccountsNonEmptyEmailAddress;
+        assert_eq!(violation.category(), ConstraintCategory::Validation);
+        assert_eq!(violation.field(), "email_address");
+        assert_eq!(violation.message(), "Email address must not be empty.");
+
+        let violation = ConstraintViolation::WorkflowsUniqueDisplayName;
+        assert_eq!(violation.category(), ConstraintCategory::Conflict);
+        assert_eq!(violation.field(), "display_name");
+        assert_eq!(violation.message(), "Display name is already taken.");
+
+        let violation = ConstraintViolation::WorkflowExecutionsEndedAfterStarted;
+        assert_eq!(violation.category(), ConstraintCategory::Integrity);
+        assert_eq!(violation.field(), "ended_at");
+        let expected = "Ended at must not be earlier than started at.";
+        assert_eq!(violation.message(), expected);
+    }
 }
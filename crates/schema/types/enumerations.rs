@@ -3,8 +3,69 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+/// Implements a type-safe `AccountStatus` enumeration.
+///
+/// See [`AccountStatus::can_transition_to`] for allowed transitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, diesel_derive_enum::DbEnum)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[ExistingTypePath = "crate::schema::sql_types::AccountStatus"]
+pub enum AccountStatus {
+    /// Created, but not yet activated.
+    #[db_rename = "pending"]
+    #[cfg_attr(feature = "serde", serde(rename = "pending"))]
+    Pending,
+    /// Activated, the only status allowed to sign in.
+    #[db_rename = "active"]
+    #[cfg_attr(feature = "serde", serde(rename = "active"))]
+    Active,
+    /// Temporarily disabled by administrators.
+    #[db_rename = "suspended"]
+    #[cfg_attr(feature = "serde", serde(rename = "suspended"))]
+    Suspended,
+    /// Temporarily disabled for security reasons, e.g. after failed sign-ins.
+    #[db_rename = "locked"]
+    #[cfg_attr(feature = "serde", serde(rename = "locked"))]
+    Locked,
+    /// Deactivated by the owner and awaiting deletion.
+    #[db_rename = "pending_deletion"]
+    #[cfg_attr(feature = "serde", serde(rename = "pending_deletion"))]
+    PendingDeletion,
+    /// Permanently disabled by administrators.
+    #[db_rename = "banned"]
+    #[cfg_attr(feature = "serde", serde(rename = "banned"))]
+    Banned,
+}
+
+impl AccountStatus {
+    /// Returns `true` if the account is allowed to sign in.
+    #[inline]
+    pub const fn is_active(self) -> bool {
+        matches!(self, Self::Active)
+    }
+
+    /// Returns `true` if the account may transition into the status.
+    ///
+    /// - `pending` may be activated, deactivated or banned.
+    /// - `active` may be suspended, locked, deactivated or banned.
+    /// - `suspended` and `locked` may be reactivated, deactivated or banned.
+    /// - `pending_deletion` may be reactivated (i.e. the deletion canceled) or banned.
+    /// - `banned` may only be reactivated, i.e. unbanned.
+    pub const fn can_transition_to(self, status: Self) -> bool {
+        use AccountStatus::*;
+
+        matches!(
+            (self, status),
+            (Pending, Active | PendingDeletion | Banned)
+                | (Active, Suspended | Locked | PendingDeletion | Banned)
+                | (Suspended | Locked, Active | PendingDeletion | Banned)
+                | (PendingDeletion, Active | Banned)
+                | (Banned, Active)
+        )
+    }
+}
+
 /// Implements a type-safe `TokenAction` enumeration.
-#[derive(Debug, Clone, Copy, diesel_derive_enum::DbEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, diesel_derive_enum::DbEnum)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[ExistingTypePath = "crate::schema::sql_types::TokenAction"]
 pub enum TokenAction {
@@ -25,6 +86,35 @@ pub enum TokenAction {
     PendingInvite,
 }
 
+impl TokenAction {
+    /// Returns the account status the token transitions the account into, if any.
+    ///
+    /// - `activate_account` activates the account.
+    /// - `deactivate_account` schedules the account for deletion.
+    pub const fn account_status(self) -> Option<AccountStatus> {
+        match self {
+            Self::ActivateAccount => Some(AccountStatus::Active),
+            Self::DeactivateAccount => Some(AccountStatus::PendingDeletion),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the token may transition the account from the status.
+    ///
+    /// Narrower than [`AccountStatus::can_transition_to`], as other transitions
+    /// (e.g. unbanning) are reserved for administrators.
+    ///
+    /// - `activate_account` only activates `pending` accounts.
+    /// - `deactivate_account` only deactivates `active` accounts.
+    pub const fn can_transition_from(self, status: AccountStatus) -> bool {
+        matches!(
+            (self, status),
+            (Self::ActivateAccount, AccountStatus::Pending)
+                | (Self::DeactivateAccount, AccountStatus::Active)
+        )
+    }
+}
+
 /// Implements a type-safe `InviteStatus` enumeration.
 #[derive(Debug, Clone, Copy, diesel_derive_enum::DbEnum)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -56,3 +146,48 @@ pub enum ProjectRole {
     #[cfg_attr(feature = "serde", serde(rename = "member"))]
     Member,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::enumerations::{AccountStatus, TokenAction};
+
+    #[test]
+    fn account_status_transitions() {
+        use AccountStatus::*;
+
+        assert!(Pending.can_transition_to(Active));
+        assert!(Active.can_transition_to(Suspended));
+        assert!(Locked.can_transition_to(Active));
+        assert!(PendingDeletion.can_transition_to(Active));
+        assert!(Banned.can_transition_to(Active));
+
+        assert!(!Pending.can_transition_to(Pending));
+        assert!(!Active.can_transition_to(Pending));
+        assert!(!Pending.can_transition_to(Suspended));
+        assert!(!Banned.can_transition_to(PendingDeletion));
+
+        assert!(Active.is_active());
+        assert!(!Suspended.is_active());
+    }
+
+    #[test]
+    fn token_action_statuses() {
+        let status = TokenAction::ActivateAccount.account_status();
+        assert_eq!(status, Some(AccountStatus::Active));
+        let status = TokenAction::DeactivateAccount.account_status();
+        assert_eq!(status, Some(AccountStatus::PendingDeletion));
+        assert_eq!(TokenAction::ResetPassword.account_status(), None);
+
+        let action = TokenAction::ActivateAccount;
+        assert!(action.can_transition_from(AccountStatus::Pending));
+        assert!(!action.can_transition_from(AccountStatus::Banned));
+        assert!(!action.can_transition_from(AccountStatus::Suspended));
+        assert!(!action.can_transition_from(AccountStatus::PendingDeletion));
+
+        let action = TokenAction::DeactivateAccount;
+        assert!(action.can_transition_from(AccountStatus::Active));
+        assert!(!action.can_transition_from(AccountStatus::Pending));
+        assert!(!action.can_transition_from(AccountStatus::Locked));
+        assert!(!TokenAction::ResetPassword.can_transition_from(AccountStatus::Active));
+    }
+}
@@ -0,0 +1,260 @@
+//! Localized messages of [`ConstraintViolation`]s, loaded from Fluent catalogs.
+//!
+//! Bundled catalogs (`en-US`, `de-DE` and `fr-FR`) are embedded from the
+//! `locales` directory, and more may be added with [`ConstraintMessages::add_catalog`].
+
+use std::fmt;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use unic_langid::{LanguageIdentifier, LanguageIdentifierError};
+
+use crate::constraints::{
+    capitalize, humanize, ConstraintKind, ConstraintRule, ConstraintViolation,
+};
+
+/// Catalogs embedded into the crate.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en-US", include_str!("../locales/en-US/constraints.ftl")),
+    ("de-DE", include_str!("../locales/de-DE/constraints.ftl")),
+    ("fr-FR", include_str!("../locales/fr-FR/constraints.ftl")),
+];
+
+/// Locale used when no catalog matches the requested locale.
+const FALLBACK: &str = "en-US";
+
+/// Unrecoverable failure of [`ConstraintMessages::add_catalog`].
+#[derive(Debug)]
+#[must_use = "errors do nothing unless you use them"]
+pub enum CatalogError {
+    /// Malformed locale identifier.
+    Locale(LanguageIdentifierError),
+    /// Malformed Fluent catalog, with all parser errors.
+    Syntax(Vec<String>),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Locale(x) => write!(f, "invalid locale: {x}"),
+            Self::Syntax(x) => write!(f, "invalid catalog: {}", x.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+/// Localized messages of [`ConstraintViolation`]s.
+///
+/// Messages are looked up by the constraint name first, then by the rule
+/// of the constraint (e.g. `constraint-non-empty`), and field names by
+/// `field-<column>`, falling back to [`ConstraintViolation::message`].
+pub struct ConstraintMessages {
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl ConstraintMessages {
+    /// Returns a new [`ConstraintMessages`] with all bundled catalogs.
+    pub fn new() -> Self {
+        let mut messages = Self::empty();
+        for (locale, source) in CATALOGS {
+            let result = messages.add_catalog(locale, *source);
+            result.expect("bundled catalogs should be valid");
+        }
+
+        messages
+    }
+
+    /// Returns a new [`ConstraintMessages`] without any catalogs.
+    pub fn empty() -> Self {
+        Self {
+            bundles: Vec::new(),
+        }
+    }
+
+    /// Adds the catalog for the locale, overriding already added messages.
+    pub fn add_catalog(
+        &mut self,
+        locale: &str,
+        source: impl Into<String>,
+    ) -> Result<(), CatalogError> {
+        let locale: LanguageIdentifier = locale.parse().map_err(CatalogError::Locale)?;
+        let resource = FluentResource::try_new(source.into());
+        let resource = resource.map_err(|(_, errors)| {
+            CatalogError::Syntax(errors.iter().map(ToString::to_string).collect())
+        })?;
+
+        let position = self.bundles.iter().position(|x| x.locales[0] == locale);
+        let bundle = match position {
+            Some(position) => &mut self.bundles[position],
+            None => {
+                let mut bundle = FluentBundle::new_concurrent(vec![locale]);
+                bundle.set_use_isolating(false);
+                self.bundles.push(bundle);
+                self.bundles.last_mut().expect("should not be empty")
+            }
+        };
+
+        bundle.add_resource_overriding(resource);
+        Ok(())
+    }
+
+    /// Returns all locales with catalogs.
+    pub fn locales(&self) -> impl Iterator<Item = &LanguageIdentifier> {
+        self.bundles.iter().map(|x| &x.locales[0])
+    }
+
+    /// Returns the user-facing message in the closest available locale.
+    pub fn message(&self, violation: ConstraintViolation, locale: &str) -> String {
+        let Some(bundle) = self.negotiate(locale) else {
+            return violation.message();
+        };
+
+        let field = violation.field();
+        let other = violation.columns().get(1).copied().unwrap_or_default();
+
+        let mut args = FluentArgs::new();
+        args.set("field", capitalize(&Self::field(bundle, field)));
+        args.set("other", Self::field(bundle, other));
+        args.set("table", humanize(violation.table()));
+        if let Some(ConstraintRule::MaxLength(limit)) = violation.rule() {
+            args.set("limit", limit);
+        }
+
+        let name = violation.to_string();
+        let id = match bundle.has_message(&name) {
+            true => name.as_str(),
+            false => Self::message_id(violation),
+        };
+
+        Self::format(bundle, id, Some(&args)).unwrap_or_else(|| violation.message())
+    }
+
+    /// Returns the bundle of the exact locale, its language, or the fallback.
+    fn negotiate(&self, locale: &str) -> Option<&FluentBundle<FluentResource>> {
+        let locale: Option<LanguageIdentifier> = locale.parse().ok();
+        let fallback: LanguageIdentifier = FALLBACK.parse().expect("should be valid");
+
+        let find = |f: &dyn Fn(&LanguageIdentifier) -> bool| {
+            self.bundles.iter().find(|x| f(&x.locales[0]))
+        };
+
+        locale
+            .as_ref()
+            .and_then(|locale| find(&|x| x == locale))
+            .or_else(|| {
+                let locale = locale.as_ref()?;
+                find(&|x| x.language == locale.language)
+            })
+            .or_else(|| find(&|x| *x == fallback))
+    }
+
+    /// Returns the identifier of the message for the rule of the constraint.
+    fn message_id(violation: ConstraintViolation) -> &'static str {
+        match (violation.kind(), violation.rule()) {
+            (ConstraintKind::Check, Some(ConstraintRule::NonEmpty)) => "constraint-non-empty",
+            (ConstraintKind::Check, Some(ConstraintRule::MaxLength(_))) => "constraint-max-length",
+            (ConstraintKind::Check, Some(ConstraintRule::RegionCode)) => "constraint-region-code",
+            (ConstraintKind::Check, Some(ConstraintRule::Chronological)) => {
+                "constraint-chronological"
+            }
+            (ConstraintKind::Check, None) => "constraint-invalid",
+            (ConstraintKind::Unique, _) => "constraint-unique",
+            (ConstraintKind::PrimaryKey, _) => "constraint-primary-key",
+        }
+    }
+
+    /// Returns the localized name of the field, or the humanized column name.
+    fn field(bundle: &FluentBundle<FluentResource>, column: &str) -> String {
+        let id = format!("field-{column}");
+        Self::format(bundle, &id, None).unwrap_or_else(|| humanize(column))
+    }
+
+    /// Formats the message of the bundle, if it exists and has no errors.
+    fn format(
+        bundle: &FluentBundle<FluentResource>,
+        id: &str,
+        args: Option<&FluentArgs<'_>>,
+    ) -> Option<String> {
+        let pattern = bundle.get_message(id)?.value()?;
+        let mut errors = Vec::new();
+        let message = bundle.format_pattern(pattern, args, &mut errors);
+        errors.is_empty().then(|| message.into_owned())
+    }
+}
+
+impl Default for ConstraintMessages {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ConstraintMessages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let locales: Vec<_> = self.locales().map(ToString::to_string).collect();
+        f.debug_struct("ConstraintMessages")
+            .field("locales", &locales)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::constraints::{ConstraintRule, ConstraintViolation};
+    use crate::i18n::ConstraintMessages;
+
+    #[test]
+    fn bundled_catalogs() {
+        let messages = ConstraintMessages::new();
+        for violation in ConstraintViolation::all() {
+            let message = messages.message(violation, "en-US");
+            assert_eq!(message, violation.message());
+        }
+
+        let violation = ConstraintViolation::AccountsNonEmptyEmailAddress;
+        let expected = "Das Feld „E-Mail-Adresse“ darf nicht leer sein.";
+        assert_eq!(messages.message(violation, "de-AT"), expected);
+        let expected = "Le champ « Adresse e-mail » ne doit pas être vide.";
+        assert_eq!(messages.message(violation, "fr-FR"), expected);
+        assert_eq!(messages.message(violation, "ja-JP"), violation.message());
+    }
+
+    #[test]
+    fn complete_catalogs() {
+        let messages = ConstraintMessages::new();
+        for bundle in &messages.bundles {
+            let locale = &bundle.locales[0];
+            for violation in ConstraintViolation::all() {
+                let name = violation.to_string();
+                let id = ConstraintMessages::message_id(violation);
+                let has_message = bundle.has_message(&name) || bundle.has_message(id);
+                assert!(has_message, "{locale}: no message for {name}");
+
+                let mut fields = vec![violation.field()];
+                if violation.rule() == Some(ConstraintRule::Chronological) {
+                    fields.extend(violation.columns().get(1));
+                }
+
+                for field in fields {
+                    let id = format!("field-{field}");
+                    assert!(bundle.has_message(&id), "{locale}: no {id} for {name}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn custom_catalogs() {
+        let mut messages = ConstraintMessages::new();
+        let source = "accounts_non_empty_email_address = { $field } is required.";
+        messages.add_catalog("en-US", source).unwrap();
+
+        let violation = ConstraintViolation::AccountsNonEmptyEmailAddress;
+        let message = messages.message(violation, "en-US");
+        assert_eq!(message, "Email address is required.");
+
+        assert!(messages.add_catalog("invalid locale", source).is_err());
+        assert!(messages.add_catalog("en-US", "invalid =").is_err());
+    }
+}
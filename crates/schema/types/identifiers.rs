@@ -0,0 +1,133 @@
+//! Implements type-safe identifiers for database queries.
+
+use std::fmt;
+use std::str::FromStr;
+
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Implements a type-safe identifier over the `UUID` column.
+macro_rules! impl_identifier {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[derive(AsExpression, FromSqlRow)]
+        #[diesel(sql_type = sql_types::Uuid)]
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        #[cfg_attr(feature = "serde", serde(transparent))]
+        pub struct $name(Uuid);
+
+        impl $name {
+            /// Returns a new identifier from the underlying [`Uuid`].
+            #[inline]
+            pub const fn new(id: Uuid) -> Self {
+                Self(id)
+            }
+
+            /// Returns the underlying [`Uuid`].
+            #[inline]
+            pub const fn into_uuid(self) -> Uuid {
+                self.0
+            }
+        }
+
+        impl From<Uuid> for $name {
+            #[inline]
+            fn from(id: Uuid) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<$name> for Uuid {
+            #[inline]
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = uuid::Error;
+
+            #[inline]
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Uuid::from_str(s).map(Self)
+            }
+        }
+
+        impl fmt::Display for $name {
+            #[inline]
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl ToSql<sql_types::Uuid, Pg> for $name {
+            fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+                <Uuid as ToSql<sql_types::Uuid, Pg>>::to_sql(&self.0, out)
+            }
+        }
+
+        impl FromSql<sql_types::Uuid, Pg> for $name {
+            fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+                <Uuid as FromSql<sql_types::Uuid, Pg>>::from_sql(bytes).map(Self)
+            }
+        }
+    };
+}
+
+impl_identifier!(
+    /// Identifier of the `accounts` row.
+    AccountId
+);
+
+impl_identifier!(
+    /// Identifier of the `workspaces` row.
+    WorkspaceId
+);
+
+impl_identifier!(
+    /// Identifier of the `workflows` row.
+    WorkflowId
+);
+
+impl_identifier!(
+    /// Identifier of the `workflow_executions` row.
+    ExecutionId
+);
+
+impl_identifier!(
+    /// Identifier of the `workspace_schedules` row.
+    ScheduleId
+);
+
+impl_identifier!(
+    /// Identifier of the `workspace_webhooks` row.
+    WebhookId
+);
+
+impl_identifier!(
+    /// Identifier of the `workspace_invites` row.
+    InviteId
+);
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use crate::identifiers::{AccountId, WorkspaceId};
+
+    #[test]
+    fn identifier_conversions() {
+        let uuid = Uuid::from_u128(0x42);
+        let account_id = AccountId::new(uuid);
+        assert_eq!(account_id.to_string(), uuid.to_string());
+        assert_eq!(account_id.to_string().parse(), Ok(account_id));
+        assert_eq!(Uuid::from(account_id), WorkspaceId::from(uuid).into_uuid());
+    }
+}
@@ -1,4 +1,12 @@
-//! Contains [`constraints`] and [`enumerations`].
+//! Contains [`catalog`], [`constraints`], [`enumerations`], [`identifiers`] and [`secrets`].
+//!
+//! Also contains `i18n` with the `i18n` feature enabled.
 
+pub mod catalog;
 pub mod constraints;
 pub mod enumerations;
+#[cfg(feature = "i18n")]
+#[cfg_attr(docsrs, doc(cfg(feature = "i18n")))]
+pub mod i18n;
+pub mod identifiers;
+pub mod secrets;
@@ -0,0 +1,488 @@
+//! Implements plaintext token secrets and their hashes stored at rest.
+//!
+//! Only the [`TokenHash`] of the secret is ever written to the database,
+//! so read access to the database is not enough to present the token.
+//!
+//! TOTP secrets have to be read back to verify codes, so they are stored as
+//! the [`EncryptedSecret`] instead, encrypted with the [`EncryptionKey`] kept
+//! outside of the database.
+
+use std::fmt;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types;
+use hmac::{Hmac, Mac};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::identifiers::AccountId;
+
+/// Length of the secret and its hash, in bytes.
+pub const SECRET_LENGTH: usize = 32;
+
+/// Length of the [`TotpSecret`], in bytes, as recommended by RFC 4226.
+pub const TOTP_SECRET_LENGTH: usize = 20;
+
+/// Duration of the TOTP time step, in seconds, as recommended by RFC 6238.
+pub const TOTP_STEP: i64 = 30;
+
+/// Number of digits of the TOTP code, as used by authenticator apps.
+pub const TOTP_DIGITS: u32 = 6;
+
+/// Length of the nonce prepended to the [`EncryptedSecret`], in bytes.
+const NONCE_LENGTH: usize = 12;
+
+/// Alphabet of the RFC 4648 base32 encoding, used by authenticator apps.
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Malformed [`SessionToken`], [`ActionToken`], [`RecoveryCode`] or [`EncryptionKey`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use = "errors do nothing unless you use them"]
+pub struct SecretError;
+
+impl fmt::Display for SecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid secret: expected {SECRET_LENGTH} base64url bytes"
+        )
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+/// [`EncryptedSecret`] that could not be decrypted.
+///
+/// Either the key is wrong, or the ciphertext was tampered with or
+/// moved to another account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use = "errors do nothing unless you use them"]
+pub struct DecryptionError;
+
+impl fmt::Display for DecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid encrypted secret: wrong key or modified ciphertext")
+    }
+}
+
+impl std::error::Error for DecryptionError {}
+
+/// Implements a plaintext secret, stored only as its [`TokenHash`].
+macro_rules! impl_secret {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, PartialEq, Eq)]
+        pub struct $name([u8; SECRET_LENGTH]);
+
+        impl $name {
+            /// Returns a new random secret from the OS random source.
+            pub fn generate() -> Self {
+                let mut bytes = [0; SECRET_LENGTH];
+                getrandom::fill(&mut bytes).expect("random source should be available");
+                Self(bytes)
+            }
+
+            /// Returns a new secret from the underlying bytes.
+            #[inline]
+            pub const fn from_bytes(bytes: [u8; SECRET_LENGTH]) -> Self {
+                Self(bytes)
+            }
+
+            /// Returns the underlying bytes.
+            #[inline]
+            pub const fn as_bytes(&self) -> &[u8; SECRET_LENGTH] {
+                &self.0
+            }
+
+            /// Returns the SHA-256 hash of the secret.
+            #[inline]
+            pub fn hash(&self) -> TokenHash {
+                TokenHash::new(&self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = SecretError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut bytes = [0; SECRET_LENGTH];
+                let len = URL_SAFE_NO_PAD.decode_slice(s, &mut bytes);
+                match len {
+                    Ok(SECRET_LENGTH) => Ok(Self(bytes)),
+                    _ => Err(SecretError),
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&URL_SAFE_NO_PAD.encode(self.0))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(concat!(stringify!($name), "(..)"))
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+impl_secret!(
+    /// Plaintext secret of the `account_sessions` row.
+    SessionToken
+);
+
+impl_secret!(
+    /// Plaintext secret of the `account_tokens` row.
+    ActionToken
+);
+
+impl_secret!(
+    /// Plaintext single-use recovery code of the `account_mfa` row.
+    RecoveryCode
+);
+
+/// SHA-256 hash of the [`SessionToken`], [`ActionToken`] or [`RecoveryCode`]
+/// over the `BYTEA` column.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow)]
+#[diesel(sql_type = sql_types::Bytea)]
+pub struct TokenHash([u8; SECRET_LENGTH]);
+
+impl TokenHash {
+    /// Returns the SHA-256 hash of the bytes.
+    pub fn new(bytes: &[u8]) -> Self {
+        Self(Sha256::digest(bytes).into())
+    }
+
+    /// Returns the underlying bytes.
+    #[inline]
+    pub const fn as_bytes(&self) -> &[u8; SECRET_LENGTH] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for TokenHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TokenHash(")?;
+        self.0.iter().try_for_each(|x| write!(f, "{x:02x}"))?;
+        f.write_str(")")
+    }
+}
+
+impl ToSql<sql_types::Bytea, Pg> for TokenHash {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <[u8] as ToSql<sql_types::Bytea, Pg>>::to_sql(&self.0, out)
+    }
+}
+
+impl FromSql<sql_types::Bytea, Pg> for TokenHash {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let bytes = <Vec<u8> as FromSql<sql_types::Bytea, Pg>>::from_sql(bytes)?;
+        let bytes = bytes.try_into().map_err(|_| "invalid hash length")?;
+        Ok(Self(bytes))
+    }
+}
+
+/// Plaintext TOTP secret, stored only as the [`EncryptedSecret`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct TotpSecret([u8; TOTP_SECRET_LENGTH]);
+
+impl TotpSecret {
+    /// Returns a new random secret from the OS random source.
+    pub fn generate() -> Self {
+        let mut bytes = [0; TOTP_SECRET_LENGTH];
+        getrandom::fill(&mut bytes).expect("random source should be available");
+        Self(bytes)
+    }
+
+    /// Returns a new secret from the underlying bytes.
+    #[inline]
+    pub const fn from_bytes(bytes: [u8; TOTP_SECRET_LENGTH]) -> Self {
+        Self(bytes)
+    }
+
+    /// Returns the underlying bytes.
+    #[inline]
+    pub const fn as_bytes(&self) -> &[u8; TOTP_SECRET_LENGTH] {
+        &self.0
+    }
+
+    /// Returns the time-step counter of the Unix timestamp, see RFC 6238.
+    #[inline]
+    pub const fn counter_at(unix_timestamp: i64) -> i64 {
+        unix_timestamp.div_euclid(TOTP_STEP)
+    }
+
+    /// Returns the code of the time-step counter, see RFC 4226.
+    pub fn code(&self, counter: i64) -> u32 {
+        let mut mac = Hmac::<Sha1>::new_from_slice(&self.0).expect("any key length is valid");
+        mac.update(&counter.to_be_bytes());
+        let digest = mac.finalize().into_bytes();
+
+        // Dynamic truncation of the last nibble offset.
+        let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+        let bytes = [
+            digest[offset],
+            digest[offset + 1],
+            digest[offset + 2],
+            digest[offset + 3],
+        ];
+
+        (u32::from_be_bytes(bytes) & 0x7fff_ffff) % 10u32.pow(TOTP_DIGITS)
+    }
+}
+
+/// Formats the secret with the unpadded base32 encoding of authenticator apps.
+impl fmt::Display for TotpSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mut buffer, mut bits) = (0u32, 0);
+        for x in self.0 {
+            buffer = (buffer << 8) | x as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                let index = (buffer >> bits) as usize & 31;
+                write!(f, "{}", BASE32_ALPHABET[index] as char)?;
+            }
+        }
+
+        if bits > 0 {
+            let index = (buffer << (5 - bits)) as usize & 31;
+            write!(f, "{}", BASE32_ALPHABET[index] as char)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TotpSecret(..)")
+    }
+}
+
+/// Key of the [`EncryptedSecret`], never written to the database.
+///
+/// Secrets encrypted with a lost key can not be recovered,
+/// so MFA has to be enrolled again after the key is replaced.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; SECRET_LENGTH]);
+
+impl EncryptionKey {
+    /// Returns a new random key from the OS random source.
+    pub fn generate() -> Self {
+        let mut bytes = [0; SECRET_LENGTH];
+        getrandom::fill(&mut bytes).expect("random source should be available");
+        Self(bytes)
+    }
+
+    /// Returns a new key from the underlying bytes.
+    #[inline]
+    pub const fn from_bytes(bytes: [u8; SECRET_LENGTH]) -> Self {
+        Self(bytes)
+    }
+
+    /// Encrypts the TOTP secret of the account with ChaCha20-Poly1305.
+    ///
+    /// The account is authenticated as associated data, so the ciphertext
+    /// can not be decrypted after it is moved to another account.
+    pub fn encrypt(&self, account_id: AccountId, secret: &TotpSecret) -> EncryptedSecret {
+        let mut nonce = [0; NONCE_LENGTH];
+        getrandom::fill(&mut nonce).expect("random source should be available");
+
+        let account_id = account_id.into_uuid();
+        let cipher = ChaCha20Poly1305::new(&Key::from(self.0));
+        let payload = Payload {
+            msg: secret.as_bytes(),
+            aad: account_id.as_bytes(),
+        };
+
+        let ciphertext = cipher
+            .encrypt(&Nonce::from(nonce), payload)
+            .expect("secret should not exceed the cipher limits");
+        EncryptedSecret([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// Decrypts the TOTP secret of the account.
+    pub fn decrypt(
+        &self,
+        account_id: AccountId,
+        secret: &EncryptedSecret,
+    ) -> Result<TotpSecret, DecryptionError> {
+        let (nonce, ciphertext) = secret
+            .0
+            .split_at_checked(NONCE_LENGTH)
+            .ok_or(DecryptionError)?;
+        let nonce: [u8; NONCE_LENGTH] = nonce.try_into().map_err(|_| DecryptionError)?;
+
+        let account_id = account_id.into_uuid();
+        let cipher = ChaCha20Poly1305::new(&Key::from(self.0));
+        let payload = Payload {
+            msg: ciphertext,
+            aad: account_id.as_bytes(),
+        };
+
+        let plaintext = cipher
+            .decrypt(&Nonce::from(nonce), payload)
+            .map_err(|_| DecryptionError)?;
+        let bytes = plaintext.try_into().map_err(|_| DecryptionError)?;
+        Ok(TotpSecret(bytes))
+    }
+}
+
+impl FromStr for EncryptionKey {
+    type Err = SecretError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0; SECRET_LENGTH];
+        let len = URL_SAFE_NO_PAD.decode_slice(s, &mut bytes);
+        match len {
+            Ok(SECRET_LENGTH) => Ok(Self(bytes)),
+            _ => Err(SecretError),
+        }
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for EncryptionKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// [`TotpSecret`] encrypted with the [`EncryptionKey`] over the `BYTEA` column.
+///
+/// Contains the random nonce followed by the ciphertext and its tag.
+#[derive(Clone, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = sql_types::Bytea)]
+pub struct EncryptedSecret(Vec<u8>);
+
+impl fmt::Debug for EncryptedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptedSecret(..)")
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for EncryptedSecret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&URL_SAFE_NO_PAD.encode(&self.0))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for EncryptedSecret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        let bytes = URL_SAFE_NO_PAD.decode(&*s);
+        bytes.map(Self).map_err(serde::de::Error::custom)
+    }
+}
+
+impl ToSql<sql_types::Bytea, Pg> for EncryptedSecret {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <[u8] as ToSql<sql_types::Bytea, Pg>>::to_sql(&self.0, out)
+    }
+}
+
+impl FromSql<sql_types::Bytea, Pg> for EncryptedSecret {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let bytes = <Vec<u8> as FromSql<sql_types::Bytea, Pg>>::from_sql(bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use crate::identifiers::AccountId;
+    use crate::secrets::{EncryptionKey, SessionToken, TokenHash, TotpSecret};
+
+    #[test]
+    fn secret_conversions() {
+        let token = SessionToken::generate();
+        assert_ne!(token, SessionToken::generate());
+        assert_eq!(token.to_string().len(), 43);
+        assert_eq!(token.to_string().parse(), Ok(token.clone()));
+        assert_eq!(format!("{token:?}"), "SessionToken(..)");
+
+        assert!("".parse::<SessionToken>().is_err());
+        assert!("AAAA".parse::<SessionToken>().is_err());
+
+        let token = SessionToken::from_bytes([0; 32]);
+        let expected = "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925";
+        assert_eq!(
+            format!("{:?}", token.hash()),
+            format!("TokenHash({expected})")
+        );
+        assert_eq!(token.hash(), TokenHash::new(&[0; 32]));
+    }
+
+    #[test]
+    fn totp_secret_encryption() {
+        let secret = TotpSecret::from_bytes(*b"12345678901234567890");
+        assert_eq!(secret.to_string(), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(format!("{secret:?}"), "TotpSecret(..)");
+
+        let key = EncryptionKey::generate();
+        let account_id = AccountId::new(Uuid::nil());
+        let encrypted = key.encrypt(account_id, &secret);
+        assert_ne!(encrypted, key.encrypt(account_id, &secret));
+        assert_eq!(key.decrypt(account_id, &encrypted), Ok(secret.clone()));
+
+        // Rejects other keys and other accounts.
+        let other_account_id = AccountId::new(Uuid::max());
+        assert!(EncryptionKey::generate()
+            .decrypt(account_id, &encrypted)
+            .is_err());
+        assert!(key.decrypt(other_account_id, &encrypted).is_err());
+
+        let key_str = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+        assert_eq!(key_str.parse(), Ok(EncryptionKey::from_bytes([0; 32])));
+        assert!("AAAA".parse::<EncryptionKey>().is_err());
+    }
+
+    #[test]
+    fn totp_secret_codes() {
+        // Test vectors of RFC 6238, truncated to six digits.
+        let secret = TotpSecret::from_bytes(*b"12345678901234567890");
+        assert_eq!(secret.code(TotpSecret::counter_at(59)), 287082);
+        assert_eq!(secret.code(TotpSecret::counter_at(1111111109)), 81804);
+        assert_eq!(secret.code(TotpSecret::counter_at(1234567890)), 5924);
+        assert_eq!(secret.code(TotpSecret::counter_at(20000000000)), 353130);
+    }
+}
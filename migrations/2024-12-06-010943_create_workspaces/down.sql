@@ -2,6 +2,22 @@
 DROP TABLE IF EXISTS workspace_webhooks;
 DROP TABLE IF EXISTS workspace_schedules;
 
+-- Removes the token action added to track pending invites.
+-- Postgres can't drop enum values, so the type is recreated without it.
+-- Pending invite tokens can't be represented by the old type, so they are
+-- deliberately deleted: invites have to be sent again after re-applying.
+DELETE FROM account_tokens WHERE action_type = 'pending_invite';
+ALTER TYPE TOKEN_ACTION RENAME TO TOKEN_ACTION_OLD;
+CREATE TYPE TOKEN_ACTION AS ENUM (
+    'activate_account',
+    'deactivate_account',
+    'update_email',
+    'reset_password'
+    );
+ALTER TABLE account_tokens
+    ALTER COLUMN action_type TYPE TOKEN_ACTION USING action_type::TEXT::TOKEN_ACTION;
+DROP TYPE TOKEN_ACTION_OLD;
+
 -- Drops workspace invites and associated objects.
 DROP INDEX IF EXISTS workspace_invites_account_id_idx;
 DROP INDEX IF EXISTS workspace_invites_workspace_id_idx;
@@ -0,0 +1,4 @@
+-- Drops the session activity tracking.
+ALTER TABLE account_sessions
+    DROP CONSTRAINT IF EXISTS account_sessions_seen_after_issued,
+    DROP COLUMN IF EXISTS last_seen_at;
@@ -0,0 +1,5 @@
+-- Tracks the last activity of the session, used for sliding expiration.
+ALTER TABLE account_sessions
+    ADD COLUMN last_seen_at TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    -- Integrity checks to maintain chronological consistency.
+    ADD CONSTRAINT account_sessions_seen_after_issued CHECK (last_seen_at >= issued_at);
@@ -0,0 +1,30 @@
+-- Restores plaintext token columns. Hashed tokens can't be recovered,
+-- so all existing sessions and action tokens are deleted.
+DELETE FROM account_sessions;
+DELETE FROM account_tokens;
+
+DROP INDEX IF EXISTS account_sessions_active_idx;
+
+ALTER TABLE account_sessions
+    DROP CONSTRAINT IF EXISTS account_sessions_token_hash_length,
+    DROP CONSTRAINT IF EXISTS account_sessions_pkey,
+    DROP COLUMN IF EXISTS token_hash,
+    ADD COLUMN token_seq UUID NOT NULL DEFAULT gen_random_uuid(),
+    ADD CONSTRAINT account_sessions_pkey PRIMARY KEY (account_id, token_seq);
+
+CREATE INDEX account_sessions_active_idx
+    ON account_sessions (account_id, token_seq)
+    WHERE deleted_at IS NULL;
+
+DROP INDEX IF EXISTS account_tokens_idx;
+
+ALTER TABLE account_tokens
+    DROP CONSTRAINT IF EXISTS account_tokens_token_hash_length,
+    DROP CONSTRAINT IF EXISTS account_tokens_pkey,
+    DROP COLUMN IF EXISTS token_hash,
+    ADD COLUMN action_token UUID NOT NULL DEFAULT gen_random_uuid(),
+    ADD CONSTRAINT account_tokens_pkey PRIMARY KEY (account_id, action_token);
+
+CREATE INDEX account_tokens_idx
+    ON account_tokens (account_id, action_token)
+    WHERE used_at IS NULL;
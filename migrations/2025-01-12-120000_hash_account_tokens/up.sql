@@ -0,0 +1,37 @@
+-- Stores SHA-256 hashes of session and action tokens instead of plaintext values.
+-- Previously issued tokens can't be hashed into the new format, so all existing
+-- sessions and action tokens are deleted, signing every account out.
+DELETE FROM account_sessions;
+DELETE FROM account_tokens;
+
+DROP INDEX IF EXISTS account_sessions_active_idx;
+
+ALTER TABLE account_sessions
+    ADD COLUMN token_hash BYTEA NOT NULL,
+    DROP CONSTRAINT account_sessions_pkey,
+    DROP COLUMN token_seq,
+    -- Ensures unique token hashes for each account.
+    ADD CONSTRAINT account_sessions_pkey PRIMARY KEY (account_id, token_hash),
+    -- Hash must be exactly the length of the SHA-256 digest.
+    ADD CONSTRAINT account_sessions_token_hash_length CHECK (octet_length(token_hash) = 32);
+
+-- Optimizes lookup for active sessions using session data.
+CREATE INDEX account_sessions_active_idx
+    ON account_sessions (account_id, token_hash)
+    WHERE deleted_at IS NULL;
+
+DROP INDEX IF EXISTS account_tokens_idx;
+
+ALTER TABLE account_tokens
+    ADD COLUMN token_hash BYTEA NOT NULL,
+    DROP CONSTRAINT account_tokens_pkey,
+    DROP COLUMN action_token,
+    -- Ensures unique token hashes for each account.
+    ADD CONSTRAINT account_tokens_pkey PRIMARY KEY (account_id, token_hash),
+    -- Hash must be exactly the length of the SHA-256 digest.
+    ADD CONSTRAINT account_tokens_token_hash_length CHECK (octet_length(token_hash) = 32);
+
+-- Optimizes yet unused token retrieval using token data.
+CREATE INDEX account_tokens_idx
+    ON account_tokens (account_id, token_hash)
+    WHERE used_at IS NULL;
@@ -0,0 +1,4 @@
+-- Drops the action token revocation tracking.
+ALTER TABLE account_tokens
+    DROP CONSTRAINT IF EXISTS account_tokens_deleted_after_issued,
+    DROP COLUMN IF EXISTS deleted_at;
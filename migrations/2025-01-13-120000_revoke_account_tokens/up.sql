@@ -0,0 +1,5 @@
+-- Tracks action tokens revoked before their expiration, e.g. superseded by a newer one.
+ALTER TABLE account_tokens
+    ADD COLUMN deleted_at TIMESTAMPTZ DEFAULT NULL,
+    -- Integrity checks to maintain chronological consistency.
+    ADD CONSTRAINT account_tokens_deleted_after_issued CHECK (deleted_at IS NULL OR deleted_at >= issued_at);
@@ -0,0 +1,11 @@
+-- Drops the procedure and functions used to purge sessions and tokens.
+DROP PROCEDURE IF EXISTS purge_expired(_session_retention INTERVAL, _token_retention INTERVAL, _batch_size INTEGER);
+DROP FUNCTION IF EXISTS purge_account_tokens(_retention INTERVAL, _batch_size INTEGER);
+DROP FUNCTION IF EXISTS purge_account_sessions(_retention INTERVAL, _batch_size INTEGER);
+
+-- Drops indexes used to look up sessions and tokens past their retention.
+DROP INDEX IF EXISTS account_tokens_deleted_idx;
+DROP INDEX IF EXISTS account_tokens_used_idx;
+DROP INDEX IF EXISTS account_tokens_expired_idx;
+DROP INDEX IF EXISTS account_sessions_deleted_idx;
+DROP INDEX IF EXISTS account_sessions_expired_idx;
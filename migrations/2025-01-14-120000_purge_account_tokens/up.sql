@@ -0,0 +1,83 @@
+-- Optimizes lookup of sessions and tokens past their retention.
+CREATE INDEX account_sessions_expired_idx
+    ON account_sessions (expired_at);
+
+CREATE INDEX account_sessions_deleted_idx
+    ON account_sessions (deleted_at)
+    WHERE deleted_at IS NOT NULL;
+
+CREATE INDEX account_tokens_expired_idx
+    ON account_tokens (expired_at);
+
+CREATE INDEX account_tokens_used_idx
+    ON account_tokens (used_at)
+    WHERE used_at IS NOT NULL;
+
+CREATE INDEX account_tokens_deleted_idx
+    ON account_tokens (deleted_at)
+    WHERE deleted_at IS NOT NULL;
+
+-- Deletes a single batch of sessions expired or deleted longer than the retention ago.
+CREATE OR REPLACE FUNCTION purge_account_sessions(_retention INTERVAL, _batch_size INTEGER) RETURNS INTEGER AS
+$$
+DECLARE
+    _deleted INTEGER;
+BEGIN
+    DELETE
+    FROM account_sessions
+    WHERE ctid = ANY (ARRAY(SELECT ctid
+                            FROM account_sessions
+                            WHERE expired_at < current_timestamp - _retention
+                            UNION
+                            SELECT ctid
+                            FROM account_sessions
+                            WHERE deleted_at < current_timestamp - _retention
+                            LIMIT _batch_size));
+    GET DIAGNOSTICS _deleted = ROW_COUNT;
+    RETURN _deleted;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Deletes a single batch of action tokens expired, used or revoked longer than the retention ago.
+CREATE OR REPLACE FUNCTION purge_account_tokens(_retention INTERVAL, _batch_size INTEGER) RETURNS INTEGER AS
+$$
+DECLARE
+    _deleted INTEGER;
+BEGIN
+    DELETE
+    FROM account_tokens
+    WHERE ctid = ANY (ARRAY(SELECT ctid
+                            FROM account_tokens
+                            WHERE expired_at < current_timestamp - _retention
+                            UNION
+                            SELECT ctid
+                            FROM account_tokens
+                            WHERE used_at < current_timestamp - _retention
+                            UNION
+                            SELECT ctid
+                            FROM account_tokens
+                            WHERE deleted_at < current_timestamp - _retention
+                            LIMIT _batch_size));
+    GET DIAGNOSTICS _deleted = ROW_COUNT;
+    RETURN _deleted;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Deletes all sessions and action tokens past their retention, committing after every batch.
+-- Intended to be scheduled with `pg_cron`, e.g. `CALL purge_expired('30 days', '7 days', 1000)`.
+CREATE OR REPLACE PROCEDURE purge_expired(_session_retention INTERVAL,
+                                          _token_retention INTERVAL,
+                                          _batch_size INTEGER) AS
+$$
+BEGIN
+    WHILE purge_account_sessions(_session_retention, _batch_size) > 0
+        LOOP
+            COMMIT;
+        END LOOP;
+
+    WHILE purge_account_tokens(_token_retention, _batch_size) > 0
+        LOOP
+            COMMIT;
+        END LOOP;
+END;
+$$ LANGUAGE plpgsql;
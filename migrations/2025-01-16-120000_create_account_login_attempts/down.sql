@@ -0,0 +1,4 @@
+-- Drops sign-in attempts and associated objects.
+DROP INDEX IF EXISTS account_login_attempts_ip_address_idx;
+DROP INDEX IF EXISTS account_login_attempts_account_idx;
+DROP TABLE IF EXISTS account_login_attempts;
@@ -0,0 +1,30 @@
+-- Sign-in attempts, both successful and failed, used for lockouts.
+CREATE TABLE IF NOT EXISTS account_login_attempts
+(
+    -- Unique identifier of the attempt.
+    id            UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    -- Reference to the account, unless no account matches the email address.
+    account_id    UUID                 DEFAULT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    -- Two-character region code for attempt origin (e.g., "US", "EU").
+    region_id     CHAR(2)     NOT NULL DEFAULT 'A0',
+
+    -- Region code must be alphanumeric and exactly two characters.
+    CONSTRAINT account_login_attempts_region_alphanumeric CHECK (region_id ~ '^[A-Z0-9]{2}$'),
+
+    -- Security-related information.
+    ip_address    INET        NOT NULL,
+    user_agent    TEXT        NOT NULL,
+
+    -- Outcome of the attempt.
+    is_successful BOOL        NOT NULL,
+    attempted_at  TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+-- Optimizes counting of recent attempts for the account.
+CREATE INDEX IF NOT EXISTS account_login_attempts_account_idx
+    ON account_login_attempts (account_id, attempted_at)
+    WHERE account_id IS NOT NULL;
+
+-- Optimizes counting of recent attempts from the IP address.
+CREATE INDEX IF NOT EXISTS account_login_attempts_ip_address_idx
+    ON account_login_attempts (ip_address, attempted_at);
@@ -0,0 +1,10 @@
+-- Restores the case-sensitive authentication lookup.
+DROP INDEX IF EXISTS accounts_credentials_idx;
+CREATE INDEX accounts_credentials_idx
+    ON accounts (email_address, password_hash)
+    WHERE deleted_at IS NULL;
+
+-- Drops the sign-in tracking.
+ALTER TABLE accounts
+    DROP CONSTRAINT IF EXISTS accounts_last_login_after_created,
+    DROP COLUMN IF EXISTS last_login_at;
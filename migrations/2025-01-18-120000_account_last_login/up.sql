@@ -0,0 +1,11 @@
+-- Tracks the last successful sign-in of the account.
+ALTER TABLE accounts
+    ADD COLUMN last_login_at TIMESTAMPTZ DEFAULT NULL,
+    -- Integrity checks to maintain chronological consistency.
+    ADD CONSTRAINT accounts_last_login_after_created CHECK (last_login_at IS NULL OR last_login_at >= created_at);
+
+-- Optimizes case-insensitive authentication lookup performance.
+DROP INDEX IF EXISTS accounts_credentials_idx;
+CREATE INDEX accounts_credentials_idx
+    ON accounts (lower(email_address), password_hash)
+    WHERE deleted_at IS NULL;
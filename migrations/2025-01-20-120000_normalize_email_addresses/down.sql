@@ -0,0 +1,12 @@
+-- Restores case-sensitive unique email addresses for active accounts.
+-- Normalized email addresses are not restored.
+DROP INDEX IF EXISTS accounts_email_address_idx;
+CREATE UNIQUE INDEX accounts_email_address_idx
+    ON accounts (email_address)
+    WHERE deleted_at IS NULL;
+
+-- Restores the case-insensitive authentication lookup.
+DROP INDEX IF EXISTS accounts_credentials_idx;
+CREATE INDEX accounts_credentials_idx
+    ON accounts (lower(email_address), password_hash)
+    WHERE deleted_at IS NULL;
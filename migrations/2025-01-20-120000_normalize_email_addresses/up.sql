@@ -0,0 +1,44 @@
+-- Email addresses are normalized as by `normalize_email`: only ASCII whitespace
+-- is trimmed and only ASCII letters are lower-cased, regardless of the database collation.
+
+-- Aborts with the report of active accounts with conflicting email addresses.
+-- Conflicts must be resolved manually, e.g. by renaming or deleting duplicates.
+DO
+$$
+DECLARE
+    _conflicts TEXT;
+BEGIN
+    SELECT string_agg(format('%s (%s)', _email_address, _account_ids), ', ')
+    INTO _conflicts
+    FROM (SELECT lower(btrim(email_address, E' \t\n\f\r') COLLATE "C") AS _email_address,
+                 string_agg(id::TEXT, ', ' ORDER BY created_at) AS _account_ids
+          FROM accounts
+          WHERE deleted_at IS NULL
+          GROUP BY 1
+          HAVING count(*) > 1) AS _duplicates;
+
+    IF _conflicts IS NOT NULL THEN
+        RAISE EXCEPTION 'Conflicting email addresses: %', _conflicts
+            USING HINT = 'Resolve conflicts before applying the migration.';
+    END IF;
+END;
+$$;
+
+-- Normalizes email addresses of active accounts.
+UPDATE accounts
+SET email_address = lower(btrim(email_address, E' \t\n\f\r') COLLATE "C")
+WHERE deleted_at IS NULL
+  AND email_address <> lower(btrim(email_address, E' \t\n\f\r') COLLATE "C");
+
+-- Enforces unique normalized email addresses for active accounts.
+-- Stored email addresses are already normalized, so lookups compare them as is.
+DROP INDEX IF EXISTS accounts_email_address_idx;
+CREATE UNIQUE INDEX accounts_email_address_idx
+    ON accounts (email_address)
+    WHERE deleted_at IS NULL;
+
+-- Restores the authentication lookup, as stored email addresses are already normalized.
+DROP INDEX IF EXISTS accounts_credentials_idx;
+CREATE INDEX accounts_credentials_idx
+    ON accounts (email_address, password_hash)
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,19 @@
+-- Restores the activation flag from the status.
+ALTER TABLE accounts
+    ADD COLUMN is_activated BOOL NOT NULL DEFAULT FALSE;
+
+ALTER TABLE accounts DISABLE TRIGGER accounts_manage_updated_at;
+UPDATE accounts
+SET is_activated = TRUE
+WHERE status = 'active';
+ALTER TABLE accounts ENABLE TRIGGER accounts_manage_updated_at;
+
+-- Drops the lifecycle status and associated objects.
+ALTER TABLE accounts
+    DROP CONSTRAINT IF EXISTS accounts_status_changed_after_created,
+    DROP CONSTRAINT IF EXISTS accounts_status_reason_limit,
+    DROP COLUMN IF EXISTS status_changed_at,
+    DROP COLUMN IF EXISTS status_reason,
+    DROP COLUMN IF EXISTS status;
+
+DROP TYPE IF EXISTS ACCOUNT_STATUS;
@@ -0,0 +1,29 @@
+-- Lifecycle status of the account, replaces the activation flag.
+CREATE TYPE ACCOUNT_STATUS AS ENUM (
+    'pending',
+    'active',
+    'suspended',
+    'locked',
+    'pending_deletion',
+    'banned'
+    );
+
+ALTER TABLE accounts
+    ADD COLUMN status            ACCOUNT_STATUS NOT NULL DEFAULT 'pending',
+    -- Optional explanation of the last status change, e.g. shown to the user.
+    ADD COLUMN status_reason     TEXT                    DEFAULT NULL,
+    ADD COLUMN status_changed_at TIMESTAMPTZ    NOT NULL DEFAULT current_timestamp,
+    -- Validation constraints to prevent oversized entries.
+    ADD CONSTRAINT accounts_status_reason_limit CHECK (length(status_reason::TEXT) <= 512),
+    -- Integrity checks to maintain chronological consistency.
+    ADD CONSTRAINT accounts_status_changed_after_created CHECK (status_changed_at >= created_at);
+
+-- Migrates activated accounts, without touching `updated_at`.
+ALTER TABLE accounts DISABLE TRIGGER accounts_manage_updated_at;
+UPDATE accounts
+SET status = 'active'
+WHERE is_activated;
+ALTER TABLE accounts ENABLE TRIGGER accounts_manage_updated_at;
+
+ALTER TABLE accounts
+    DROP COLUMN is_activated;
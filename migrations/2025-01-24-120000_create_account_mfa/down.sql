@@ -0,0 +1,5 @@
+-- Drops multi-factor authentication and associated objects.
+ALTER TABLE account_sessions
+    DROP COLUMN IF EXISTS is_mfa_satisfied;
+
+DROP TABLE IF EXISTS account_mfa;
@@ -0,0 +1,30 @@
+-- TOTP multi-factor authentication of the account.
+CREATE TABLE IF NOT EXISTS account_mfa
+(
+    -- Reference to the associated account.
+    account_id           UUID PRIMARY KEY REFERENCES accounts (id) ON DELETE CASCADE,
+
+    -- TOTP secret, encrypted with the account as associated data.
+    totp_secret          BYTEA       NOT NULL,
+    -- SHA-256 hashes of unused single-use recovery codes.
+    recovery_code_hashes BYTEA[]     NOT NULL DEFAULT '{}',
+    -- Time-step counter of the last accepted TOTP code, older codes are rejected.
+    last_totp_counter    BIGINT               DEFAULT NULL,
+
+    -- Timestamps for tracking the row's lifecycle.
+    created_at           TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at           TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    -- Set once the first TOTP code is verified, enrollment is pending until then.
+    confirmed_at         TIMESTAMPTZ          DEFAULT NULL,
+
+    -- Integrity checks to maintain chronological consistency.
+    CONSTRAINT account_mfa_updated_after_created CHECK (updated_at >= created_at),
+    CONSTRAINT account_mfa_confirmed_after_created CHECK (confirmed_at IS NULL OR confirmed_at >= created_at)
+);
+
+-- Automatically updates modification timestamp.
+SELECT manage_updated_at('account_mfa');
+
+-- Records whether the second factor was verified when the session was created.
+ALTER TABLE account_sessions
+    ADD COLUMN is_mfa_satisfied BOOL NOT NULL DEFAULT FALSE;